@@ -0,0 +1,28 @@
+use iab_gpp::v1::{GPPString, SectionDiff};
+use std::env::args;
+use std::error::Error;
+use std::str::FromStr;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let a = args().nth(1).unwrap_or("".to_string());
+    let b = args().nth(2).unwrap_or("".to_string());
+    let a = GPPString::from_str(&a)?;
+    let b = GPPString::from_str(&b)?;
+
+    for diff in a.diff(&b)? {
+        match diff {
+            SectionDiff::Added(id) => println!("+ {id}"),
+            SectionDiff::Removed(id) => println!("- {id}"),
+            SectionDiff::Changed(id, fields) => {
+                println!("~ {id}");
+                for f in fields {
+                    let old = f.old.unwrap_or("-".to_string());
+                    let new = f.new.unwrap_or("-".to_string());
+                    println!("    {}: {old} -> {new}", f.field);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
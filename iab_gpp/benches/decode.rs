@@ -0,0 +1,117 @@
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::{alphabet, Engine};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use iab_gpp::sections::tcfeuv2::TcfEuV2;
+use iab_gpp::sections::{DenseIdSet, Section, SectionId};
+use iab_gpp::v1::GPPString;
+use std::str::FromStr;
+
+const HEADER: &str = "DBACNY";
+const TCF_EU_V2_CORE: &str = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+const TCF_EU_V2_DISCLOSED_VENDORS: &str = "IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw";
+const GPP_STRING: &str = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+
+// GPP strings are not padded and may have non-zero trailing bits
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_decode_allow_trailing_bits(true)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+fn base64_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("base64");
+
+    for (name, s) in [
+        ("header", HEADER),
+        ("tcfeuv2 core", TCF_EU_V2_CORE),
+        ("tcfeuv2 disclosed vendors", TCF_EU_V2_DISCLOSED_VENDORS),
+    ] {
+        group.bench_with_input(BenchmarkId::new("iab_gpp", name), s, |b, s| {
+            b.iter(|| iab_gpp::base64::decode_url(black_box(s)).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("base64", name), s, |b, s| {
+            b.iter(|| URL_SAFE_LENIENT.decode(black_box(s)).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+fn gpp_decode(c: &mut Criterion) {
+    c.bench_function("parse gpp string", |b| {
+        b.iter(|| GPPString::from_str(black_box(GPP_STRING)).unwrap())
+    });
+    c.bench_function("parse gpp string with many sections", |b| {
+        let s = GPPString::try_from(vec![
+            Section::TcfEuV2(TCF_EU_V2_CORE.parse().unwrap()),
+            Section::UspV1("1YNN".parse().unwrap()),
+            Section::UsCa("BVqqqqpY.YA".parse().unwrap()),
+            Section::UsVa("BVVVVWY".parse().unwrap()),
+            Section::TcfCaV1(
+                "BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA"
+                    .parse()
+                    .unwrap(),
+            ),
+        ])
+        .unwrap()
+        .encode()
+        .unwrap();
+        b.iter(|| GPPString::from_str(black_box(&s)).unwrap())
+    });
+    c.bench_function("decode all sections", |b| {
+        let gpp_str = GPPString::from_str(GPP_STRING).unwrap();
+        b.iter(|| black_box(&gpp_str).decode_all_sections())
+    });
+
+    let mut group = c.benchmark_group("decode section");
+    let gpp_str = GPPString::from_str(GPP_STRING).unwrap();
+    group.bench_function("allocating", |b| {
+        b.iter(|| {
+            black_box(&gpp_str)
+                .decode_section(SectionId::TcfEuV2)
+                .unwrap()
+        })
+    });
+    group.bench_function("reused buffer", |b| {
+        let mut buf = Vec::new();
+        b.iter(|| {
+            black_box(&gpp_str)
+                .decode_section_into(SectionId::TcfEuV2, &mut buf)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn vendor_lookup(c: &mut Criterion) {
+    let gpp_str = GPPString::from_str(&format!(
+        "DBABMA~{TCF_EU_V2_CORE}.{TCF_EU_V2_DISCLOSED_VENDORS}"
+    ))
+    .unwrap();
+    let tcf = gpp_str.decode::<TcfEuV2>().unwrap();
+    let vendors = tcf.disclosed_vendors.unwrap();
+    let dense = DenseIdSet::from(&vendors);
+    let max = vendors.last().copied().unwrap_or_default();
+
+    let mut group = c.benchmark_group("vendor lookup");
+    group.bench_function("IdSet", |b| {
+        b.iter(|| {
+            (1..=max)
+                .filter(|id| black_box(&vendors).contains(id))
+                .count()
+        })
+    });
+    group.bench_function("DenseIdSet", |b| {
+        b.iter(|| {
+            (1..=max)
+                .filter(|&id| black_box(&dense).contains(id))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, base64_decode, gpp_decode, vendor_lookup);
+criterion_main!(benches);
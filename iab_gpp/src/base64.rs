@@ -0,0 +1,40 @@
+//! Base64 encoding and decoding as used by GPP strings.
+//!
+//! GPP segments are encoded with the URL-safe Base64 alphabet, without padding, and
+//! their length is not required to be a multiple of 4 characters.
+//!
+//! These functions are useful for inspecting the raw bytes of a single segment, without
+//! decoding a full [`GPPString`](crate::v1::GPPString).
+//!
+//! # Example
+//!
+//! ```
+//! use iab_gpp::base64::{decode_url, encode_url};
+//!
+//! let bytes = decode_url("DBABM").unwrap();
+//! assert_eq!(bytes, vec![12, 16, 1, 48]);
+//! assert_eq!(encode_url(&bytes), "DBABMA");
+//! ```
+
+pub use crate::core::base64::DecodeError;
+
+/// Decodes a URL-safe Base64 string into bytes.
+///
+/// If the input length in bits is not a multiple of 8, the last byte is completed with
+/// zero bits.
+///
+/// # Errors
+///
+/// Returns a [`DecodeError`] if the string contains a character outside the URL-safe
+/// Base64 alphabet.
+pub fn decode_url(s: &str) -> Result<Vec<u8>, DecodeError> {
+    crate::core::base64::decode(s)
+}
+
+/// Encodes bytes into a URL-safe Base64 string, without padding.
+///
+/// If the input length in bits is not a multiple of 6, the last character is completed with
+/// zero bits. As a consequence, decoding the result may yield an extra zero byte.
+pub fn encode_url(bytes: &[u8]) -> String {
+    crate::core::base64::encode(bytes)
+}
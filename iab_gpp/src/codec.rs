@@ -0,0 +1,42 @@
+//! Bit-level primitives used to decode and encode GPP sections.
+//!
+//! This module exposes the toolkit on which all sections of this crate are built. It is meant
+//! for advanced uses, such as implementing sections which are not supported yet, or checking
+//! the output of third-party encoders field by field.
+//!
+//! [`DataReader`] reads values from a slice of bytes, and [`DataWriter`] writes them back. Each
+//! `read_*` method of the reader has a `write_*` counterpart producing the same wire format.
+//! Types implementing [`FromDataReader`] and [`ToDataWriter`] can be read and written as a
+//! whole.
+//!
+//! The items exported here follow the semantic versioning of the crate.
+//!
+//! # Example
+//!
+//! ```
+//! use iab_gpp::codec::{DataReader, DataWriter};
+//! use std::collections::BTreeSet;
+//!
+//! let ids = BTreeSet::from([1, 2, 3, 8]);
+//!
+//! let mut w = DataWriter::new();
+//! w.write_fixed_integer(6, 3u8).unwrap();
+//! w.write_optimized_integer_range(&ids).unwrap();
+//! let bytes = w.into_bytes();
+//!
+//! let mut r = DataReader::new(&bytes);
+//! assert_eq!(r.read_fixed_integer::<u8>(6).unwrap(), 3);
+//! assert_eq!(r.read_optimized_integer_range().unwrap(), ids);
+//! ```
+//!
+//! The Fibonacci sequence used by Fibonacci-encoded integers is available as an iterator:
+//!
+//! ```
+//! use iab_gpp::codec::fibonacci_iterator;
+//!
+//! let first = fibonacci_iterator::<u16>().take(6).collect::<Vec<_>>();
+//! assert_eq!(first, vec![1, 2, 3, 5, 8, 13]);
+//! ```
+
+pub use crate::core::fibonacci::{fibonacci_iterator, Fibonacci};
+pub use crate::core::{DataReader, DataWriter, FromDataReader, GenericRange, Range, ToDataWriter};
@@ -1,4 +1,3 @@
-use bitstream_io::{BigEndian, BitWrite, BitWriter};
 use thiserror::Error;
 
 /// The error type that describes failures to decode Base64 encoded strings.
@@ -7,34 +6,92 @@ pub enum DecodeError {
     /// An invalid byte was found in the input. The offset and offending byte are provided.
     #[error("invalid byte {1} at offset {0}")]
     InvalidByte(usize, u8),
+    /// A character of the standard Base64 alphabet (`+` or `/`) was found in the input, where
+    /// the URL-safe alphabet uses `-` and `_` instead. The offset and offending byte are
+    /// provided.
+    #[error("standard Base64 character '{}' at offset {0}, expected the URL-safe alphabet", *.1 as char)]
+    StandardAlphabet(usize, u8),
 }
 
 /// Custom base64 implementation, 6-bits aligned, no padding,
 /// using the URL Safe Base64 dictionary.
 pub fn decode(s: &str) -> Result<Vec<u8>, DecodeError> {
-    // output buffer should not be larger than input string, so we pre-allocate enough bytes to
-    // avoid realloc which is slow, and could cause allocation of a bigger capacity than needed
-    // (x2 or more)
-    let mut buffer = Vec::with_capacity(s.len());
-    let mut bw = BitWriter::endian(&mut buffer, BigEndian);
-
-    // write 6 bits for every decoded character
-    for (i, b) in s.bytes().enumerate() {
-        let value = base64_value(b).ok_or(DecodeError::InvalidByte(i, b))?;
-        bw.write(6, value).expect("write into vec should not fail");
+    let mut buffer = Vec::new();
+    decode_into(s, &mut buffer)?;
+    Ok(buffer)
+}
+
+/// Same as [`decode`], but writes the decoded bytes into the given buffer, which is cleared
+/// first. This allows reusing the buffer's allocation across calls.
+pub(crate) fn decode_into(s: &str, buffer: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_into_with_alphabet(s, buffer, false)
+}
+
+/// Same as [`decode_into`], but also accepts the `+` and `/` characters of the standard
+/// alphabet if `accept_standard` is set, decoding them as `-` and `_` respectively.
+pub(crate) fn decode_into_with_alphabet(
+    s: &str,
+    buffer: &mut Vec<u8>,
+    accept_standard: bool,
+) -> Result<(), DecodeError> {
+    // every group of 4 characters decodes into exactly 3 bytes, so we reserve enough
+    // bytes to avoid reallocations
+    buffer.clear();
+    buffer.reserve(s.len().div_ceil(4) * 3);
+
+    for (n, chunk) in s.as_bytes().chunks(4).enumerate() {
+        let mut group = 0u32;
+        for (i, &b) in chunk.iter().enumerate() {
+            let value = match (base64_value(b), b) {
+                (Some(value), _) => value,
+                (None, b'+') if accept_standard => 62,
+                (None, b'/') if accept_standard => 63,
+                (None, b'+' | b'/') => return Err(DecodeError::StandardAlphabet(n * 4 + i, b)),
+                (None, _) => return Err(DecodeError::InvalidByte(n * 4 + i, b)),
+            };
+            group = group << 6 | value as u32;
+        }
+
+        // a shorter last group is completed with zero bits up to the next byte boundary
+        group <<= 6 * (4 - chunk.len());
+        let len = (chunk.len() * 6).div_ceil(8);
+        buffer.extend_from_slice(&group.to_be_bytes()[1..=len]);
     }
 
-    // write remaining value if we're not 8-bit aligned at this point
-    let (n, value) = bw.into_unwritten();
-    if n > 0 {
-        let n = 8 - n;
-        let value = value << n;
-        buffer.push(value);
+    Ok(())
+}
+
+/// Custom base64 implementation, 6-bits aligned, no padding,
+/// using the URL Safe Base64 dictionary.
+///
+/// The last character is completed with zero bits if the input length in bits is not a
+/// multiple of 6.
+pub fn encode(bytes: &[u8]) -> String {
+    encode_bits(bytes, bytes.len() as u64 * 8)
+}
+
+/// Encodes only the first `bits` bits of the given bytes.
+pub(crate) fn encode_bits(bytes: &[u8], bits: u64) -> String {
+    let mut s = String::with_capacity(bits.div_ceil(6) as usize);
+
+    for i in (0..bits).step_by(6) {
+        let value = (i..i + 6)
+            .map(|bit| bit < bits && bit_at(bytes, bit))
+            .fold(0, |acc, b| acc << 1 | b as u8);
+        s.push(BASE64_CHARS[value as usize] as char);
     }
 
-    Ok(buffer)
+    s
+}
+
+fn bit_at(bytes: &[u8], bit: u64) -> bool {
+    bytes
+        .get((bit / 8) as usize)
+        .is_some_and(|b| b & (0x80 >> (bit % 8)) != 0)
 }
 
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
 fn base64_value(b: u8) -> Option<u8> {
     match b {
         b'A'..=b'Z' => Some(b - b'A'),
@@ -64,14 +121,57 @@ mod tests {
     }
 
     #[test_case("DBABM" => vec![12, 16, 1, 48] ; "simple header")]
+    #[test_case("DBABMA" => vec![12, 16, 1, 48, 0] ; "padded header")]
+    #[test_case("_w" => vec![0xff, 0] ; "two characters")]
+    #[test_case("__8" => vec![0xff, 0xff, 0] ; "three characters")]
     #[test_case("" => is empty ; "empty string")]
     fn test_decode_base64(s: &str) -> Vec<u8> {
         decode(s).unwrap()
     }
 
+    #[test]
+    fn test_decode_base64_into_clears_buffer() {
+        let mut buf = vec![1, 2, 3, 4, 5, 6];
+        decode_into("DBABM", &mut buf).unwrap();
+        assert_eq!(buf, vec![12, 16, 1, 48]);
+    }
+
+    #[test_case(&[12, 16, 1, 48] => "DBABMA" ; "simple header")]
+    #[test_case(&[0xff] => "_w" ; "padded")]
+    #[test_case(&[] => "" ; "empty")]
+    fn test_encode_base64(bytes: &[u8]) -> String {
+        encode(bytes)
+    }
+
+    #[test_case(&[12, 16, 1, 48], 24 => "DBAB" ; "truncated header")]
+    #[test_case(&[12, 16, 1, 48], 27 => "DBABI" ; "partial character")]
+    #[test_case(&[0xff], 6 => "_" ; "exact")]
+    fn test_encode_bits_base64(bytes: &[u8], bits: u64) -> String {
+        encode_bits(bytes, bits)
+    }
+
+    #[test]
+    fn alphabet() {
+        for (i, &c) in BASE64_CHARS.iter().enumerate() {
+            assert_eq!(base64_value(c), Some(i as u8));
+        }
+    }
+
     #[test_case("===" => matches DecodeError::InvalidByte(0, b'=') ; "equal signs")]
     #[test_case("a  " => matches DecodeError::InvalidByte(1, b' ') ; "whitespaces")]
+    #[test_case("DBABM=" => matches DecodeError::InvalidByte(5, b'=') ; "second group")]
+    #[test_case("DBA+" => matches DecodeError::StandardAlphabet(3, b'+') ; "plus")]
+    #[test_case("DBAB/" => matches DecodeError::StandardAlphabet(4, b'/') ; "slash")]
     fn error(s: &str) -> DecodeError {
         decode(s).unwrap_err()
     }
+
+    #[test_case("+/" => "-_" ; "translated")]
+    #[test_case("DBABM" => "DBABM" ; "url safe")]
+    #[test_case("a+b-" => "a-b-" ; "mixed")]
+    fn decode_standard_alphabet(s: &str) -> String {
+        let mut buffer = Vec::new();
+        decode_into_with_alphabet(s, &mut buffer, true).unwrap();
+        encode_bits(&buffer, s.len() as u64 * 6)
+    }
 }
@@ -1,31 +1,61 @@
 use crate::core::fibonacci::fibonacci_iterator;
+use crate::sections::{SectionDecodeError, SectionEncodeError};
 use base64::DecodeError;
-use bitstream_io::{BigEndian, BitRead, BitReader, Numeric};
+use bitstream_io::{BigEndian, BitRead, BitReader, BitWrite, BitWriter, Numeric, SignedNumeric};
 use num_iter::range_inclusive;
-use num_traits::{CheckedAdd, Num, NumAssignOps, ToPrimitive};
+use num_traits::{CheckedAdd, FromPrimitive, Num, NumAssignOps, ToPrimitive};
 use std::collections::BTreeSet;
 use std::io;
 use std::iter::repeat_with;
+use std::ops;
 
 pub mod base64;
-mod fibonacci;
+pub(crate) mod fibonacci;
+
+/// The maximum length of a Fibonacci encoded integer, terminating bit included.
+///
+/// Longer sequences cannot hold a value of any supported integer type, so reading stops
+/// there instead of draining the rest of a malformed input.
+const MAX_FIBONACCI_BITS: u64 = 64;
 
 pub trait DecodeExt {
     fn decode_base64_url(&self) -> Result<Vec<u8>, DecodeError>;
+    fn decode_base64_into(
+        &self,
+        buf: &mut Vec<u8>,
+        accept_standard: bool,
+    ) -> Result<(), DecodeError>;
 }
 
 impl DecodeExt for &str {
     fn decode_base64_url(&self) -> Result<Vec<u8>, DecodeError> {
         base64::decode(self)
     }
+
+    fn decode_base64_into(
+        &self,
+        buf: &mut Vec<u8>,
+        accept_standard: bool,
+    ) -> Result<(), DecodeError> {
+        base64::decode_into_with_alphabet(self, buf, accept_standard)
+    }
 }
 
+/// A type which can be read from a [`DataReader`].
+///
+/// Implementations read a fixed sequence of fields, and are usually generated by
+/// `#[derive(FromDataReader)]` for section types.
 pub trait FromDataReader: Sized {
     type Err;
 
     fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err>;
 }
 
+/// A type which can be written to a [`DataWriter`], mirroring [`FromDataReader`].
+pub trait ToDataWriter {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError>;
+}
+
 impl FromDataReader for bool {
     type Err = io::Error;
 
@@ -50,26 +80,185 @@ impl FromDataReader for u16 {
     }
 }
 
+impl ToDataWriter for bool {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_bool(*self)
+    }
+}
+
+impl ToDataWriter for u8 {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(6, *self)
+    }
+}
+
+impl ToDataWriter for u16 {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(12, *self)
+    }
+}
+
+/// A reader of bit-level values from a slice of bytes.
+///
+/// Values are read most significant bit first, as specified by the GPP encoding rules.
+/// Each `read_*` method corresponds to a field encoding type of the specification.
 pub struct DataReader<'a> {
     bit_reader: BitReader<&'a [u8], BigEndian>,
+    position: u64,
+    len: u64,
+    strict_enums: bool,
+    spans: Option<Box<SpanRecorder>>,
+}
+
+/// The bit ranges of the fields read by a [`DataReader`], see [`DataReader::enter_field`].
+#[derive(Default)]
+struct SpanRecorder {
+    // names and start positions of the fields being read, outermost first
+    fields: Vec<(&'static str, u64)>,
+    spans: Vec<(String, ops::Range<u64>)>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+impl SpanRecorder {
+    fn push(&mut self, name: &str, range: ops::Range<u64>) {
+        let mut path = String::new();
+        for (parent, _) in &self.fields {
+            path.push_str(parent);
+            path.push('.');
+        }
+        path.push_str(name);
+        self.spans.push((path, range));
+    }
+}
+
+/// An entry of an array of ranges, associating a key and a range type with a set of IDs.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GenericRange<X, Y> {
     pub key: X,
     pub range_type: Y,
     pub ids: BTreeSet<u16>,
 }
 
+/// A range entry whose key and type fit in a byte, as used by publisher restrictions.
 pub type Range = GenericRange<u8, u8>;
 
 impl<'a> DataReader<'a> {
     pub fn new(bytes: &'a [u8]) -> Self {
         Self {
             bit_reader: BitReader::endian(bytes, BigEndian),
+            position: 0,
+            len: bytes.len() as u64 * 8,
+            strict_enums: false,
+            spans: None,
+        }
+    }
+
+    /// Creates a reader over a range of bytes, such as a length-delimited record embedded in
+    /// a larger segment.
+    ///
+    /// The reader behaves as if the bytes ended with the range: reading past it fails, and
+    /// [`remaining_bits`](Self::remaining_bits) only counts the bits left within it.
+    /// [`position`](Self::position) is relative to the start of the range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`io::ErrorKind::UnexpectedEof`] error if the range does not fit in `bytes`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::codec::DataReader;
+    ///
+    /// // two records of one byte each, each holding a 4-bit and a 3-bit integer
+    /// let bytes = [0b0011_0100, 0b0101_0110];
+    ///
+    /// let mut r = DataReader::with_range(&bytes, 1..2).unwrap();
+    /// assert_eq!(r.remaining_bits(), 8);
+    /// assert_eq!(r.read_fixed_integer::<u8>(4).unwrap(), 5);
+    /// assert_eq!(r.read_fixed_integer::<u8>(3).unwrap(), 3);
+    /// assert_eq!(r.remaining_bits(), 1);
+    /// assert!(r.read_fixed_integer::<u8>(2).is_err());
+    /// ```
+    pub fn with_range(bytes: &'a [u8], range: ops::Range<usize>) -> io::Result<Self> {
+        let (start, end) = (range.start, range.end);
+        bytes.get(range).map(Self::new).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "range {start}..{end} out of bounds of {} bytes",
+                    bytes.len()
+                ),
+            )
+        })
+    }
+
+    /// Sets whether enum values without a matching variant are rejected.
+    ///
+    /// See [`read_enum`](Self::read_enum).
+    pub(crate) fn with_strict_enums(mut self, strict_enums: bool) -> Self {
+        self.strict_enums = strict_enums;
+        self
+    }
+
+    /// Makes the reader record the bit range of each field, returned by
+    /// [`take_spans`](Self::take_spans).
+    pub(crate) fn with_spans(mut self) -> Self {
+        self.spans = Some(Box::default());
+        self
+    }
+
+    /// Returns the path and bit range of each field read so far, in reading order.
+    ///
+    /// Paths follow the nesting of the fields, with names separated by dots, as returned by
+    /// [`Fields`](crate::sections::Fields). Nothing is recorded unless the reader was created
+    /// with [`with_spans`](Self::with_spans).
+    pub(crate) fn take_spans(&mut self) -> Vec<(String, ops::Range<u64>)> {
+        self.spans
+            .as_mut()
+            .map(|spans| std::mem::take(&mut spans.spans))
+            .unwrap_or_default()
+    }
+
+    /// Marks the start of a field, whose bit range is recorded by the matching call to
+    /// [`exit_field`](Self::exit_field). Fields entered in between are nested in this one.
+    ///
+    /// Calls are generated by the `FromDataReader` derive macro.
+    pub(crate) fn enter_field(&mut self, name: &'static str) {
+        if let Some(spans) = &mut self.spans {
+            spans.fields.push((name, self.position));
+        }
+    }
+
+    /// Marks the end of the field last entered with [`enter_field`](Self::enter_field).
+    pub(crate) fn exit_field(&mut self) {
+        if let Some(spans) = &mut self.spans {
+            if let Some((name, start)) = spans.fields.pop() {
+                spans.push(name, start..self.position);
+            }
         }
     }
 
+    /// Records a field which was read from the given position to the current one.
+    pub(crate) fn record_field(&mut self, name: &'static str, start: u64) {
+        if let Some(spans) = &mut self.spans {
+            spans.push(name, start..self.position);
+        }
+    }
+
+    /// Returns the number of bits consumed so far.
+    ///
+    /// Read errors returned by this reader also mention this offset, which tells how far
+    /// into the stream decoding got before failing.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the number of bits left to read.
+    ///
+    /// This includes the padding bits of the last byte, if any.
+    pub fn remaining_bits(&self) -> u64 {
+        self.len - self.position
+    }
+
     pub fn parse<F>(&mut self) -> Result<F, <F as FromDataReader>::Err>
     where
         F: FromDataReader,
@@ -78,11 +267,60 @@ impl<'a> DataReader<'a> {
     }
 
     pub fn read_bool(&mut self) -> io::Result<bool> {
-        self.bit_reader.read_bit()
+        let b = self.bit_reader.read_bit().map_err(|e| self.error_at(e))?;
+        self.position += 1;
+        Ok(b)
     }
 
     pub fn read_fixed_integer<N: Numeric>(&mut self, bits: u32) -> io::Result<N> {
-        self.bit_reader.read(bits)
+        let n = self.bit_reader.read(bits).map_err(|e| self.error_at(e))?;
+        self.position += bits as u64;
+        Ok(n)
+    }
+
+    /// Reads a signed integer in two's complement, whose most significant bit is the sign.
+    ///
+    /// For instance, a width of 4 bits holds values from -8 to 7.
+    pub fn read_signed_integer<S: SignedNumeric>(&mut self, bits: u32) -> io::Result<S> {
+        let n = self
+            .bit_reader
+            .read_signed(bits)
+            .map_err(|e| self.error_at(e))?;
+        self.position += bits as u64;
+        Ok(n)
+    }
+
+    /// Reads the discriminant of an enum, as an integer of the given width.
+    ///
+    /// Values without a matching variant are read as `default`, unless the reader was made
+    /// strict, in which case they are reported as [`SectionDecodeError::InvalidFieldValue`].
+    pub(crate) fn read_enum<E: FromPrimitive>(
+        &mut self,
+        bits: u32,
+        default: E,
+    ) -> Result<E, SectionDecodeError> {
+        let value = self.read_fixed_integer::<u8>(bits)?;
+        match E::from_u8(value) {
+            Some(e) => Ok(e),
+            None if self.strict_enums => Err(SectionDecodeError::InvalidFieldValue {
+                expected: "a defined enum value".to_string(),
+                found: value.to_string(),
+            }),
+            None => Ok(default),
+        }
+    }
+
+    fn error_at(&self, e: io::Error) -> io::Error {
+        io::Error::new(e.kind(), format!("{e} at bit {}", self.position()))
+    }
+
+    // integers decoded from untrusted input may not fit in the requested type, which must
+    // be reported instead of panicking or wrapping around
+    fn overflow_error(&self) -> io::Error {
+        self.error_at(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "integer overflow",
+        ))
     }
 
     pub fn read_fibonacci_integer<T>(&mut self) -> io::Result<T>
@@ -93,45 +331,117 @@ impl<'a> DataReader<'a> {
         let mut total = T::zero();
         let mut last_bit = false;
 
-        loop {
+        for _ in 0..MAX_FIBONACCI_BITS {
             let bit = self.read_bool()?;
 
             // two consecutive 1's signal the end of the value
             if last_bit && bit {
-                break;
+                return Ok(total);
             }
 
-            let fib_value = fib.next().unwrap_or(T::zero());
+            // a set bit beyond the Fibonacci numbers which fit in T cannot be added
+            let fib_value = fib.next();
             if bit {
-                total += fib_value;
+                total = fib_value
+                    .and_then(|v| total.checked_add(&v))
+                    .ok_or_else(|| self.overflow_error())?;
             }
             last_bit = bit;
         }
 
-        Ok(total)
+        Err(self.error_at(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unterminated Fibonacci integer after {MAX_FIBONACCI_BITS} bits"),
+        )))
     }
 
-    pub fn read_string(&mut self, chars: usize) -> io::Result<String> {
-        repeat_with(|| self.read_fixed_integer::<u8>(6))
+    /// Reads a string of `chars` uppercase letters, each encoded on 6 bits.
+    ///
+    /// Values above 25 do not map to a letter, and cause an
+    /// [`InvalidCharacter`](SectionDecodeError::InvalidCharacter) error.
+    pub fn read_string(&mut self, chars: usize) -> Result<String, SectionDecodeError> {
+        let s = repeat_with(|| self.read_fixed_integer::<u8>(6))
             .take(chars)
             .map(|r| r.map(|n| (n + 65) as char))
-            .collect::<Result<String, _>>()
+            .collect::<Result<String, _>>()?;
+
+        if let Some(character) = s.chars().find(|c| !c.is_ascii_uppercase()) {
+            return Err(SectionDecodeError::InvalidCharacter {
+                character,
+                kind: "alphabetic",
+                s,
+            });
+        }
+
+        Ok(s)
+    }
+
+    /// Reads `n` bytes of opaque data, such as a signature.
+    ///
+    /// The bytes are read from the current position, which does not need to be aligned on a
+    /// byte boundary.
+    pub fn read_bytes(&mut self, n: usize) -> io::Result<Vec<u8>> {
+        let bytes = self
+            .bit_reader
+            .read_to_vec(n)
+            .map_err(|e| self.error_at(e))?;
+        self.position += n as u64 * 8;
+        Ok(bytes)
     }
 
-    pub fn read_datetime_as_unix_timestamp(&mut self) -> io::Result<i64> {
-        Ok(self.read_fixed_integer::<i64>(36)? / 10) // seconds
+    /// Reads a 36-bit date and time value, as the raw number of deciseconds since the Unix
+    /// epoch.
+    pub fn read_datetime_deciseconds(&mut self) -> io::Result<u64> {
+        self.read_fixed_integer(36)
     }
 
+    /// Reads a bitfield of the given length, returning the 1-based indices of the set bits.
+    ///
+    /// Indices are stored as `u16`, so a bitfield cannot be longer than 65535 bits. Requesting
+    /// a longer bitfield returns an error of kind [`io::ErrorKind::InvalidInput`].
     pub fn read_fixed_bitfield(&mut self, bits: usize) -> io::Result<BTreeSet<u16>> {
+        let bits = u16::try_from(bits).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bitfield of {bits} bits exceeds {} bits", u16::MAX),
+            )
+        })?;
+
         let mut result = BTreeSet::new();
+        self.read_bitfield_bits(bits, &mut result)?;
+        Ok(result)
+    }
+
+    /// Reads a fixed bitfield into the given set, as [`read_fixed_bitfield`](Self::read_fixed_bitfield).
+    ///
+    /// The set is cleared first, which lets hot loops keep a single set per field instead of
+    /// returning a new one for each decoded string. If an error is returned, the set holds the
+    /// IDs read before the error.
+    pub fn read_fixed_bitfield_into(
+        &mut self,
+        bits: usize,
+        set: &mut BTreeSet<u16>,
+    ) -> io::Result<()> {
+        let bits = u16::try_from(bits).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bitfield of {bits} bits exceeds {} bits", u16::MAX),
+            )
+        })?;
+
+        set.clear();
+        self.read_bitfield_bits(bits, set)
+    }
+
+    fn read_bitfield_bits(&mut self, bits: u16, set: &mut BTreeSet<u16>) -> io::Result<()> {
         for i in 1..=bits {
             let b = self.read_bool()?;
             if b {
-                result.insert(i as u16);
+                set.insert(i);
             }
         }
 
-        Ok(result)
+        Ok(())
     }
 
     pub fn read_variable_bitfield(&mut self) -> io::Result<BTreeSet<u16>> {
@@ -139,6 +449,15 @@ impl<'a> DataReader<'a> {
         self.read_fixed_bitfield(n)
     }
 
+    /// Reads a variable bitfield into the given set, as
+    /// [`read_variable_bitfield`](Self::read_variable_bitfield).
+    ///
+    /// See [`read_fixed_bitfield_into`](Self::read_fixed_bitfield_into).
+    pub fn read_variable_bitfield_into(&mut self, set: &mut BTreeSet<u16>) -> io::Result<()> {
+        let n = self.read_fixed_integer::<u16>(16)? as usize;
+        self.read_fixed_bitfield_into(n, set)
+    }
+
     pub fn read_integer_range(&mut self) -> io::Result<Vec<u16>> {
         let n = self.read_fixed_integer::<u16>(12)? as usize;
         let mut range = vec![];
@@ -162,6 +481,17 @@ impl<'a> DataReader<'a> {
     }
 
     pub fn read_fibonacci_range<T>(&mut self) -> io::Result<Vec<T>>
+    where
+        T: CheckedAdd + Copy + Num + NumAssignOps + PartialOrd + ToPrimitive,
+    {
+        self.read_fibonacci_range_with_limit(usize::MAX)
+    }
+
+    /// Reads a Fibonacci range, stopping as soon as it holds more than `limit` IDs.
+    ///
+    /// The IDs read so far are returned when the limit is exceeded, so the caller can tell from
+    /// their count that the range is too long, without reading or expanding the rest of it.
+    pub fn read_fibonacci_range_with_limit<T>(&mut self, limit: usize) -> io::Result<Vec<T>>
     where
         T: CheckedAdd + Copy + Num + NumAssignOps + PartialOrd + ToPrimitive,
     {
@@ -174,21 +504,48 @@ impl<'a> DataReader<'a> {
             if is_group {
                 let offset = self.read_fibonacci_integer()?;
                 let count = self.read_fibonacci_integer()?;
+                let start = last_id
+                    .checked_add(&offset)
+                    .ok_or_else(|| self.overflow_error())?;
+                let end = start
+                    .checked_add(&count)
+                    .ok_or_else(|| self.overflow_error())?;
 
-                for id in range_inclusive(last_id + offset, last_id + offset + count) {
+                for id in range_inclusive(start, end) {
+                    if range.len() > limit {
+                        return Ok(range);
+                    }
                     range.push(id);
                     last_id = id;
                 }
             } else {
-                let id = self.read_fibonacci_integer::<T>()?;
-                range.push(last_id + id);
+                let offset = self.read_fibonacci_integer::<T>()?;
+                let id = last_id
+                    .checked_add(&offset)
+                    .ok_or_else(|| self.overflow_error())?;
+                range.push(id);
                 last_id = id;
             }
+
+            if range.len() > limit {
+                return Ok(range);
+            }
         }
 
         Ok(range)
     }
 
+    /// Reads a set of IDs encoded either as a Fibonacci range or as a variable bitfield.
+    ///
+    /// The wire format is:
+    /// - 1 bit telling which encoding follows (1 for Fibonacci range, 0 for bitfield)
+    /// - a Fibonacci range (see [`read_fibonacci_range`](Self::read_fibonacci_range)), or
+    ///   a variable bitfield (see [`read_variable_bitfield`](Self::read_variable_bitfield))
+    ///
+    /// This format is used by the TCF CA vendor express/implied consents and disclosed vendors.
+    ///
+    /// Not to be confused with [`read_optimized_integer_range`](Self::read_optimized_integer_range),
+    /// which starts with a 16-bit length.
     pub fn read_optimized_range(&mut self) -> io::Result<BTreeSet<u16>> {
         let is_fibo = self.read_bool()?;
         if is_fibo {
@@ -198,6 +555,34 @@ impl<'a> DataReader<'a> {
         }
     }
 
+    /// Reads an optimized range into the given set, as
+    /// [`read_optimized_range`](Self::read_optimized_range).
+    ///
+    /// See [`read_fixed_bitfield_into`](Self::read_fixed_bitfield_into).
+    pub fn read_optimized_range_into(&mut self, set: &mut BTreeSet<u16>) -> io::Result<()> {
+        let is_fibo = self.read_bool()?;
+        if is_fibo {
+            set.clear();
+            set.extend(self.read_fibonacci_range::<u16>()?);
+            Ok(())
+        } else {
+            self.read_variable_bitfield_into(set)
+        }
+    }
+
+    /// Reads a set of IDs encoded either as an integer range or as a fixed bitfield.
+    ///
+    /// The wire format is:
+    /// - 16 bits containing the maximum ID of the set
+    /// - 1 bit telling which encoding follows (1 for integer range, 0 for bitfield)
+    /// - an integer range (see [`read_integer_range`](Self::read_integer_range)), or
+    ///   a bitfield whose length is the maximum ID read above
+    ///
+    /// This format is used by the TCF EU v2 vendor consents, vendor legitimate interests and
+    /// disclosed vendors.
+    ///
+    /// Not to be confused with [`read_optimized_range`](Self::read_optimized_range),
+    /// which starts with a single bit.
     pub fn read_optimized_integer_range(&mut self) -> io::Result<BTreeSet<u16>> {
         let n = self.read_fixed_integer::<u16>(16)? as usize;
         let is_int_range = self.read_bool()?;
@@ -208,6 +593,22 @@ impl<'a> DataReader<'a> {
         }
     }
 
+    /// Reads an optimized integer range into the given set, as
+    /// [`read_optimized_integer_range`](Self::read_optimized_integer_range).
+    ///
+    /// See [`read_fixed_bitfield_into`](Self::read_fixed_bitfield_into).
+    pub fn read_optimized_integer_range_into(&mut self, set: &mut BTreeSet<u16>) -> io::Result<()> {
+        let n = self.read_fixed_integer::<u16>(16)? as usize;
+        let is_int_range = self.read_bool()?;
+        if is_int_range {
+            set.clear();
+            set.extend(self.read_integer_range()?);
+            Ok(())
+        } else {
+            self.read_fixed_bitfield_into(n, set)
+        }
+    }
+
     pub fn read_array_of_ranges(&mut self) -> io::Result<Vec<Range>> {
         let n = self.read_fixed_integer::<u16>(12)? as usize;
         let mut ranges = vec![];
@@ -253,26 +654,391 @@ impl<'a> DataReader<'a> {
     }
 }
 
+/// The writing counterpart of [`DataReader`].
+///
+/// Every `write_*` method produces the wire format consumed by the `read_*` method of the
+/// same name.
+pub struct DataWriter {
+    bit_writer: BitWriter<Output, BigEndian>,
+    position: u64,
+    compact: bool,
+}
+
+// destination of the bytes written by a DataWriter
+enum Output {
+    Bytes(Vec<u8>),
+    Discard,
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Bytes(bytes) => bytes.write(buf),
+            Output::Discard => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl DataWriter {
+    pub fn new() -> Self {
+        Self::with_output(Output::Bytes(vec![]))
+    }
+
+    /// Creates a writer which only counts the written bits, without storing any output.
+    ///
+    /// Values are still validated as with a regular writer. The bytes returned by
+    /// [`into_bytes`](Self::into_bytes) are always empty.
+    pub fn counting() -> Self {
+        Self::with_output(Output::Discard)
+    }
+
+    /// Creates a writer producing the shortest output which reads back to the same values.
+    ///
+    /// Sets of IDs are written with their shortest encoding, see
+    /// [`write_optimized_range`](Self::write_optimized_range), and the output of
+    /// [`into_base64`](Self::into_base64) is not padded to a whole byte.
+    pub(crate) fn compact() -> Self {
+        Self {
+            compact: true,
+            ..Self::new()
+        }
+    }
+
+    fn with_output(output: Output) -> Self {
+        Self {
+            bit_writer: BitWriter::endian(output, BigEndian),
+            position: 0,
+            compact: false,
+        }
+    }
+
+    /// Returns the number of bits written so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the written bytes, padding the last byte with zeroes if needed.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.bit_writer
+            .byte_align()
+            .expect("write into vec should not fail");
+        match self.bit_writer.into_writer() {
+            Output::Bytes(bytes) => bytes,
+            Output::Discard => vec![],
+        }
+    }
+
+    /// Returns the written bytes encoded in base64.
+    ///
+    /// The output is padded to a whole byte, unless the writer was created with
+    /// [`compact`](Self::compact), in which case it has as few characters as needed.
+    pub(crate) fn into_base64(self) -> String {
+        if self.compact {
+            let bits = self.position;
+            base64::encode_bits(&self.into_bytes(), bits)
+        } else {
+            base64::encode(&self.into_bytes())
+        }
+    }
+
+    pub fn write<T>(&mut self, value: &T) -> Result<(), SectionEncodeError>
+    where
+        T: ToDataWriter + ?Sized,
+    {
+        value.to_data_writer(self)
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> Result<(), SectionEncodeError> {
+        self.bit_writer.write_bit(value)?;
+        self.position += 1;
+        Ok(())
+    }
+
+    pub fn write_fixed_integer<N>(&mut self, bits: u32, value: N) -> Result<(), SectionEncodeError>
+    where
+        N: Numeric + Into<u64>,
+    {
+        let v: u64 = value.into();
+        if bits < 64 && v >> bits != 0 {
+            return Err(SectionEncodeError::IntegerOutOfRange { bits, value: v });
+        }
+
+        self.bit_writer.write(bits, value)?;
+        self.position += bits as u64;
+        Ok(())
+    }
+
+    /// Writes a signed integer in two's complement, whose most significant bit is the sign.
+    pub fn write_signed_integer<S>(&mut self, bits: u32, value: S) -> Result<(), SectionEncodeError>
+    where
+        S: SignedNumeric + Into<i64>,
+    {
+        let v: i64 = value.into();
+        if bits == 0 || (bits < 64 && (v >> (bits - 1) != 0 && v >> (bits - 1) != -1)) {
+            return Err(SectionEncodeError::InvalidFieldValue {
+                expected: format!("signed integer of {bits} bits"),
+                found: v.to_string(),
+            });
+        }
+
+        self.bit_writer.write_signed(bits, value)?;
+        self.position += bits as u64;
+        Ok(())
+    }
+
+    pub fn write_fibonacci_integer(&mut self, value: u64) -> Result<(), SectionEncodeError> {
+        if value == 0 {
+            return Err(SectionEncodeError::InvalidFieldValue {
+                expected: "a positive integer".to_string(),
+                found: value.to_string(),
+            });
+        }
+
+        let fibs = fibonacci_iterator::<u64>()
+            .take_while(|&f| f <= value)
+            .collect::<Vec<_>>();
+
+        // greedy Zeckendorf representation, starting from the largest value
+        let mut bits = vec![false; fibs.len()];
+        let mut remainder = value;
+        for (i, &f) in fibs.iter().enumerate().rev() {
+            if f <= remainder {
+                bits[i] = true;
+                remainder -= f;
+            }
+        }
+
+        for b in bits {
+            self.write_bool(b)?;
+        }
+
+        // terminating bit, forming the final "11" pair
+        self.write_bool(true)
+    }
+
+    pub fn write_string(&mut self, chars: usize, s: &str) -> Result<(), SectionEncodeError> {
+        if let Some(character) = s.chars().find(|c| !c.is_ascii_uppercase()) {
+            return Err(SectionEncodeError::InvalidCharacter {
+                character,
+                s: s.to_string(),
+            });
+        }
+        if s.len() != chars {
+            return Err(SectionEncodeError::InvalidFieldValue {
+                expected: format!("{chars} characters"),
+                found: s.to_string(),
+            });
+        }
+
+        for c in s.chars() {
+            self.write_fixed_integer(6, c as u8 - 65)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `n` bytes of opaque data, in the format read by [`DataReader::read_bytes`].
+    ///
+    /// Returns [`SectionEncodeError::InvalidFieldValue`] if the data is not exactly `n` bytes
+    /// long.
+    pub fn write_bytes(&mut self, n: usize, bytes: &[u8]) -> Result<(), SectionEncodeError> {
+        if bytes.len() != n {
+            return Err(SectionEncodeError::InvalidFieldValue {
+                expected: format!("{n} bytes"),
+                found: format!("{} bytes", bytes.len()),
+            });
+        }
+
+        self.bit_writer.write_bytes(bytes)?;
+        self.position += n as u64 * 8;
+        Ok(())
+    }
+
+    pub fn write_datetime_deciseconds(
+        &mut self,
+        deciseconds: &u64,
+    ) -> Result<(), SectionEncodeError> {
+        self.write_fixed_integer(36, *deciseconds)
+    }
+
+    pub fn write_fixed_bitfield(
+        &mut self,
+        bits: usize,
+        ids: &BTreeSet<u16>,
+    ) -> Result<(), SectionEncodeError> {
+        if let Some(&id) = ids.iter().find(|&&id| id == 0 || id as usize > bits) {
+            return Err(SectionEncodeError::InvalidFieldValue {
+                expected: format!("ids between 1 and {bits}"),
+                found: id.to_string(),
+            });
+        }
+
+        for i in 1..=bits {
+            self.write_bool(ids.contains(&(i as u16)))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_variable_bitfield(
+        &mut self,
+        ids: &BTreeSet<u16>,
+    ) -> Result<(), SectionEncodeError> {
+        let n = max_id(ids);
+        self.write_fixed_integer(16, n)?;
+        self.write_fixed_bitfield(n as usize, ids)
+    }
+
+    pub fn write_integer_range(&mut self, ids: &BTreeSet<u16>) -> Result<(), SectionEncodeError> {
+        let runs = consecutive_runs(ids);
+        self.write_fixed_integer(12, runs.len() as u16)?;
+
+        for (start, end) in runs {
+            if start == end {
+                self.write_bool(false)?;
+                self.write_fixed_integer(16, start)?;
+            } else {
+                self.write_bool(true)?;
+                self.write_fixed_integer(16, start)?;
+                self.write_fixed_integer(16, end)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn write_fibonacci_range(&mut self, ids: &BTreeSet<u16>) -> Result<(), SectionEncodeError> {
+        let runs = consecutive_runs(ids);
+        self.write_fixed_integer(12, runs.len() as u16)?;
+
+        // each ID is encoded as an offset from the last one
+        let mut last_id = 0;
+        for (start, end) in runs {
+            if start == end {
+                self.write_bool(false)?;
+                self.write_fibonacci_integer((start - last_id) as u64)?;
+            } else {
+                self.write_bool(true)?;
+                self.write_fibonacci_integer((start - last_id) as u64)?;
+                self.write_fibonacci_integer((end - start) as u64)?;
+            }
+            last_id = end;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a set of IDs in the format read by
+    /// [`DataReader::read_optimized_range`].
+    ///
+    /// The Fibonacci range is chosen if it is shorter than the bits of the bitfield, not
+    /// counting its length, which makes the bitfield the choice for an empty set. When
+    /// encoding a section with
+    /// [`encode_compact`](crate::sections::EncodableSection::encode_compact), the length of
+    /// the bitfield is counted as well, so that the shortest encoding is always chosen.
+    pub fn write_optimized_range(&mut self, ids: &BTreeSet<u16>) -> Result<(), SectionEncodeError> {
+        let mut fibonacci = DataWriter::new();
+        fibonacci.write_fibonacci_range(ids)?;
+
+        let bitfield_len = if self.compact { 16 } else { 0 };
+        if fibonacci.position() < bitfield_len + max_id(ids) as u64 {
+            self.write_bool(true)?;
+            self.write_fibonacci_range(ids)
+        } else {
+            self.write_bool(false)?;
+            self.write_variable_bitfield(ids)
+        }
+    }
+
+    /// Writes a set of IDs in the format read by
+    /// [`DataReader::read_optimized_integer_range`].
+    ///
+    /// The shortest encoding is chosen, favoring the bitfield if both have the same length.
+    pub fn write_optimized_integer_range(
+        &mut self,
+        ids: &BTreeSet<u16>,
+    ) -> Result<(), SectionEncodeError> {
+        let mut range = DataWriter::new();
+        range.write_integer_range(ids)?;
+        let n = max_id(ids);
+
+        self.write_fixed_integer(16, n)?;
+        if range.position() < n as u64 {
+            self.write_bool(true)?;
+            self.write_integer_range(ids)
+        } else {
+            self.write_bool(false)?;
+            self.write_fixed_bitfield(n as usize, ids)
+        }
+    }
+
+    /// Writes ranges in the format read by [`DataReader::read_array_of_ranges`].
+    pub fn write_array_of_ranges(&mut self, ranges: &[Range]) -> Result<(), SectionEncodeError> {
+        self.write_fixed_integer(12, ranges.len() as u16)?;
+        for range in ranges {
+            self.write_fixed_integer(6, range.key)?;
+            self.write_fixed_integer(2, range.range_type)?;
+            self.write_optimized_integer_range(&range.ids)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes ranges in the format read by [`DataReader::read_n_array_of_ranges`].
+    pub fn write_n_array_of_ranges<X, Y>(
+        &mut self,
+        x: u32,
+        y: u32,
+        ranges: &[GenericRange<X, Y>],
+    ) -> Result<(), SectionEncodeError>
+    where
+        X: Numeric + Into<u64>,
+        Y: Numeric + Into<u64>,
+    {
+        self.write_fixed_integer(12, ranges.len() as u16)?;
+        for range in ranges {
+            self.write_fixed_integer(x, range.key)?;
+            self.write_fixed_integer(y, range.range_type)?;
+            self.write_optimized_range(&range.ids)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for DataWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn max_id(ids: &BTreeSet<u16>) -> u16 {
+    ids.last().copied().unwrap_or(0)
+}
+
+/// Groups a sorted set of IDs into inclusive ranges of consecutive values.
+fn consecutive_runs(ids: &BTreeSet<u16>) -> Vec<(u16, u16)> {
+    let mut runs: Vec<(u16, u16)> = vec![];
+    for &id in ids {
+        match runs.last_mut() {
+            Some((_, end)) if *end + 1 == id => *end = id,
+            _ => runs.push((id, id)),
+        }
+    }
+    runs
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::bits_from_str as b;
+    use iab_gpp_derive::{FromDataReader, ToDataWriter};
     use test_case::test_case;
 
-    /// Transform a string of literal binary digits into a vector of bytes.
-    /// Zeroes will be appended to fill missing bits.
-    fn b(s: &str) -> Vec<u8> {
-        let chars = s
-            .chars()
-            .filter(|&c| c == '1' || c == '0')
-            .collect::<Vec<_>>();
-        chars
-            .chunks(8)
-            .map(|c| (8 - c.len(), String::from_iter(c)))
-            .map(|(l, s)| u8::from_str_radix(&s, 2).map(|n| n << l))
-            .collect::<Result<Vec<_>, _>>()
-            .unwrap_or(vec![])
-    }
-
     #[test_case("00000001 00000010 00000011" => vec![1, 2, 3])]
     #[test_case("000000 010000 001000 000011" => vec![1, 2, 3])]
     #[test_case("000000 010000 001000 000011 1000" => vec![1, 2, 3, 128])]
@@ -282,12 +1048,67 @@ mod tests {
         b(s)
     }
 
+    #[test]
+    fn position() {
+        let bytes = b("1 000101 0011");
+        let mut r = DataReader::new(&bytes);
+        assert_eq!(r.position(), 0);
+        r.read_bool().unwrap();
+        assert_eq!(r.position(), 1);
+        r.read_fixed_integer::<u8>(6).unwrap();
+        assert_eq!(r.position(), 7);
+        r.read_fibonacci_integer::<u8>().unwrap();
+        assert_eq!(r.position(), 11);
+    }
+
+    #[test]
+    fn with_range() {
+        let bytes = b("00000001 10100000 11111111");
+        let mut r = DataReader::with_range(&bytes, 1..2).unwrap();
+        assert_eq!(r.remaining_bits(), 8);
+        assert_eq!(r.read_fixed_integer::<u8>(3).unwrap(), 5);
+        assert_eq!(r.position(), 3);
+        assert_eq!(r.remaining_bits(), 5);
+        let e = r.read_fixed_integer::<u8>(6).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(e.to_string().ends_with("at bit 3"));
+    }
+
+    #[test_case(0..4 ; "past the end")]
+    #[test_case(4..5 ; "after the end")]
+    fn with_range_out_of_bounds(range: ops::Range<usize>) {
+        let bytes = b("00000001 10100000 11111111");
+        let e = DataReader::with_range(&bytes, range).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_error_position() {
+        let bytes = b("00000001");
+        let mut r = DataReader::new(&bytes);
+        r.read_fixed_integer::<u8>(6).unwrap();
+        let e = r.read_fixed_integer::<u8>(6).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(e.to_string().ends_with("at bit 6"));
+        assert_eq!(r.position(), 6);
+    }
+
     #[test_case("000101", 6 => 5)]
     #[test_case("101010", 6 => 42)]
     fn read_int(s: &str, bits: u32) -> u32 {
         DataReader::new(&b(s)).read_fixed_integer(bits).unwrap()
     }
 
+    #[test_case("0111", 4 => 7 ; "max 4 bits")]
+    #[test_case("1000", 4 => -8 ; "min 4 bits")]
+    #[test_case("11111111", 8 => -1 ; "minus one 8 bits")]
+    #[test_case("10000000", 8 => -128 ; "min 8 bits")]
+    #[test_case("111111010110", 12 => -42 ; "12 bits")]
+    #[test_case("1000000000000000", 16 => -32768 ; "min 16 bits")]
+    fn read_signed_int(s: &str, bits: u32) -> i32 {
+        DataReader::new(&b(s)).read_signed_integer(bits).unwrap()
+    }
+
     #[test_case("11" => 1)]
     #[test_case("011" => 2)]
     #[test_case("0011" => 3)]
@@ -295,23 +1116,71 @@ mod tests {
     #[test_case("00011" => 5)]
     #[test_case("10011" => 6)]
     #[test_case("01011" => 7)]
-    #[test_case("0100000000001011" => 2 ; "overflow for u8")] // ignore bits we can't encode
     fn read_fibonacci(s: &str) -> u8 {
         DataReader::new(&b(s)).read_fibonacci_integer().unwrap()
     }
 
-    #[test_case("101010", 1 => "k")]
-    #[test_case("101010 101011", 2 => "kl")]
+    // 233 + 89 + 34 + 13 + 5 + 2 does not fit in a u8
+    #[test_case("0101010101011" ; "sum")]
+    // 377 and 987 are beyond the Fibonacci numbers which fit in a u8
+    #[test_case("0100000000001011" ; "single bit")]
+    fn read_fibonacci_overflow(s: &str) {
+        let e = DataReader::new(&b(s))
+            .read_fibonacci_integer::<u8>()
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test_case("000000000001 1 0000000000011 0000000000011" ; "group end")]
+    #[test_case("000000000010 0 0000000000011 1 0000000000011 011" ; "group start")]
+    #[test_case("000000000010 0 0000000000011 0 0000000000011" ; "single ids")]
+    fn read_fibonacci_range_overflow(s: &str) {
+        let e = DataReader::new(&b(s))
+            .read_fibonacci_range::<u8>()
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_fibonacci_max_bits() {
+        let mut bytes = vec![0; 7];
+        bytes.push(0b0000_0011);
+        let mut r = DataReader::new(&bytes);
+        assert_eq!(
+            r.read_fibonacci_integer::<u64>().unwrap(),
+            10_610_209_857_723
+        );
+        assert_eq!(r.position(), 64);
+    }
+
+    #[test]
+    fn read_fibonacci_unterminated() {
+        let bytes = [0; 32];
+        let mut r = DataReader::new(&bytes);
+        let e = r.read_fibonacci_integer::<u64>().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(e.to_string().ends_with("at bit 64"));
+        assert_eq!(r.position(), 64);
+    }
+
+    #[test_case("000100", 1 => "E")]
+    #[test_case("000100 001101", 2 => "EN")]
+    #[test_case("000000 011001", 2 => "AZ")]
     fn read_string(s: &str, chars: usize) -> String {
         DataReader::new(&b(s)).read_string(chars).unwrap()
     }
 
-    #[test_case("001111101100100110001110010001011101" => 1685434479)]
+    #[test_case("011010", 1 => matches SectionDecodeError::InvalidCharacter { character: '[', .. } ; "after z")]
+    #[test_case("000100 101010", 2 => matches SectionDecodeError::InvalidCharacter { character: 'k', .. } ; "lowercase")]
+    #[test_case("000100", 2 => matches SectionDecodeError::Read(_) ; "truncated")]
+    fn read_string_error(s: &str, chars: usize) -> SectionDecodeError {
+        DataReader::new(&b(s)).read_string(chars).unwrap_err()
+    }
+
+    #[test_case("001111101100100110001110010001011101" => 16854344797)]
     #[test_case("000000000000000000000000000000000000" => 0)]
-    fn read_datetime_as_unix_timestamp(s: &str) -> i64 {
-        DataReader::new(&b(s))
-            .read_datetime_as_unix_timestamp()
-            .unwrap()
+    fn read_datetime_deciseconds(s: &str) -> u64 {
+        DataReader::new(&b(s)).read_datetime_deciseconds().unwrap()
     }
 
     #[test_case("10101", 5 => BTreeSet::from_iter([1, 3, 5]))]
@@ -321,6 +1190,43 @@ mod tests {
         DataReader::new(&b(s)).read_fixed_bitfield(bits).unwrap()
     }
 
+    #[test]
+    fn read_fixed_bitfield_too_long() {
+        let bytes = vec![0xff; 8193];
+        let mut r = DataReader::new(&bytes);
+
+        assert_eq!(r.read_fixed_bitfield(65535).unwrap().len(), 65535);
+        let e = DataReader::new(&bytes)
+            .read_fixed_bitfield(65536)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn read_into_clears_set() {
+        let mut set = BTreeSet::from_iter([2, 4, 100]);
+
+        DataReader::new(&b("10101"))
+            .read_fixed_bitfield_into(5, &mut set)
+            .unwrap();
+        assert_eq!(set, BTreeSet::from_iter([1, 3, 5]));
+
+        DataReader::new(&b("0000000000000011 011"))
+            .read_variable_bitfield_into(&mut set)
+            .unwrap();
+        assert_eq!(set, BTreeSet::from_iter([2, 3]));
+
+        DataReader::new(&b("1 000000000010 0 0011 1 011 0011"))
+            .read_optimized_range_into(&mut set)
+            .unwrap();
+        assert_eq!(set, BTreeSet::from_iter([3, 5, 6, 7, 8]));
+
+        DataReader::new(&b("0000000000000000 1 000000000001 0 0000000000000100"))
+            .read_optimized_integer_range_into(&mut set)
+            .unwrap();
+        assert_eq!(set, BTreeSet::from_iter([4]));
+    }
+
     #[test_case("0000000000000101 10101" => BTreeSet::from_iter([1, 3, 5]))]
     fn read_variable_bitfield(s: &str) -> BTreeSet<u16> {
         DataReader::new(&b(s)).read_variable_bitfield().unwrap()
@@ -333,10 +1239,23 @@ mod tests {
 
     #[test_case("000000000010 0 0011 1 011 0011" => vec![3, 5, 6, 7, 8])]
     #[test_case("000000000010 0 011 0 1011" => vec![2, 6])]
+    #[test_case("000000000011 0 011 0 1011 0 11" => vec![2, 6, 7] ; "consecutive single ids")]
+    #[test_case("000000000011 1 0011 011 0 011 0 0011" => vec![3, 4, 5, 7, 10] ; "single ids after group")]
+    #[test_case("000000000011 0 011 1 011 11 0 0011" => vec![2, 4, 5, 8] ; "group between single ids")]
     fn read_fibonacci_range(s: &str) -> Vec<u8> {
         DataReader::new(&b(s)).read_fibonacci_range().unwrap()
     }
 
+    #[test_case("000000000010 0 0011 1 011 0011", 5 => vec![3, 5, 6, 7, 8] ; "within limit")]
+    #[test_case("000000000010 0 0011 1 011 0011", 2 => vec![3, 5, 6] ; "group beyond limit")]
+    #[test_case("000000000011 0 011 0 1011 0 11", 1 => vec![2, 6] ; "single id beyond limit")]
+    #[test_case("000000000011 0 011 0 1011 0", 1 => vec![2, 6] ; "truncated after limit")]
+    fn read_fibonacci_range_with_limit(s: &str, limit: usize) -> Vec<u8> {
+        DataReader::new(&b(s))
+            .read_fibonacci_range_with_limit(limit)
+            .unwrap()
+    }
+
     #[test_case("1 000000000010 0 0011 1 011 0011" => BTreeSet::from_iter([3, 5, 6, 7, 8]))]
     #[test_case("0 0000000000000101 10101" => BTreeSet::from_iter([1, 3, 5]))]
     fn read_optimized_range(s: &str) -> BTreeSet<u16> {
@@ -351,6 +1270,144 @@ mod tests {
             .unwrap()
     }
 
+    fn written(f: impl FnOnce(&mut DataWriter) -> Result<(), SectionEncodeError>) -> Vec<u8> {
+        let mut w = DataWriter::new();
+        f(&mut w).unwrap();
+        w.into_bytes()
+    }
+
+    #[test]
+    fn counting() {
+        let write = |w: &mut DataWriter| {
+            w.write_bool(true)?;
+            w.write_fixed_integer(12, 42u16)?;
+            w.write_fibonacci_range(&BTreeSet::from([2, 3, 4, 9]))
+        };
+
+        let mut w = DataWriter::new();
+        write(&mut w).unwrap();
+        let mut counting = DataWriter::counting();
+        write(&mut counting).unwrap();
+
+        assert_eq!(counting.position(), w.position());
+        assert!(counting.into_bytes().is_empty());
+    }
+
+    #[test_case(1 => b("11"))]
+    #[test_case(2 => b("011"))]
+    #[test_case(3 => b("0011"))]
+    #[test_case(4 => b("1011"))]
+    #[test_case(7 => b("01011"))]
+    fn write_fibonacci(n: u64) -> Vec<u8> {
+        written(|w| w.write_fibonacci_integer(n))
+    }
+
+    #[test]
+    fn write_fibonacci_zero() {
+        assert!(DataWriter::new().write_fibonacci_integer(0).is_err());
+    }
+
+    #[test_case(&[1, 3, 5], 5 => b("10101"))]
+    #[test_case(&[1, 3, 5], 6 => b("101010"))]
+    #[test_case(&[], 0 => b(""))]
+    fn write_fixed_bitfield(ids: &[u16], bits: usize) -> Vec<u8> {
+        written(|w| w.write_fixed_bitfield(bits, &BTreeSet::from_iter(ids.iter().copied())))
+    }
+
+    #[test_case(3, 8u8 => matches SectionEncodeError::IntegerOutOfRange { bits: 3, value: 8 } ; "u8")]
+    #[test_case(12, 4096u16 => matches SectionEncodeError::IntegerOutOfRange { bits: 12, value: 4096 } ; "u16")]
+    fn write_fixed_integer_out_of_range<N>(bits: u32, value: N) -> SectionEncodeError
+    where
+        N: Numeric + Into<u64>,
+    {
+        DataWriter::new()
+            .write_fixed_integer(bits, value)
+            .unwrap_err()
+    }
+
+    #[test_case(4, -8 => b("1000") ; "min 4 bits")]
+    #[test_case(12, -42 => b("111111010110") ; "12 bits")]
+    #[test_case(12, 42 => b("000000101010") ; "positive")]
+    fn write_signed_int(bits: u32, value: i32) -> Vec<u8> {
+        written(|w| w.write_signed_integer(bits, value))
+    }
+
+    #[test_case(4, 8 ; "too large")]
+    #[test_case(4, -9 ; "too small")]
+    #[test_case(0, 0 ; "no bits")]
+    fn write_signed_int_out_of_range(bits: u32, value: i32) {
+        assert!(matches!(
+            DataWriter::new().write_signed_integer(bits, value),
+            Err(SectionEncodeError::InvalidFieldValue { .. })
+        ));
+    }
+
+    #[test_case(1, "k" => matches SectionEncodeError::InvalidCharacter { character: 'k', .. } ; "lowercase")]
+    #[test_case(2, "K" => matches SectionEncodeError::InvalidFieldValue { .. } ; "too short")]
+    fn write_string_invalid(chars: usize, s: &str) -> SectionEncodeError {
+        DataWriter::new().write_string(chars, s).unwrap_err()
+    }
+
+    #[test_case(&[0], 5 ; "zero")]
+    #[test_case(&[6], 5 ; "too large")]
+    fn write_fixed_bitfield_invalid(ids: &[u16], bits: usize) {
+        assert!(DataWriter::new()
+            .write_fixed_bitfield(bits, &BTreeSet::from_iter(ids.iter().copied()))
+            .is_err());
+    }
+
+    #[test_case(&[3, 5, 6, 7, 8] => b("000000000010 0 0000000000000011 1 0000000000000101 0000000000001000"))]
+    fn write_integer_range(ids: &[u16]) -> Vec<u8> {
+        written(|w| w.write_integer_range(&BTreeSet::from_iter(ids.iter().copied())))
+    }
+
+    #[test_case(&[3, 5, 6, 7, 8] => b("000000000010 0 0011 1 011 0011"))]
+    #[test_case(&[2, 6] => b("000000000010 0 011 0 1011"))]
+    fn write_fibonacci_range(ids: &[u16]) -> Vec<u8> {
+        written(|w| w.write_fibonacci_range(&BTreeSet::from_iter(ids.iter().copied())))
+    }
+
+    #[test_case(&[1, 3, 5] => b("0 0000000000000101 10101") ; "bitfield")]
+    #[test_case(&[3, 100, 101, 102] => b("1 000000000010 0 0011 1 00001000011 011") ; "fibonacci")]
+    fn write_optimized_range(ids: &[u16]) -> Vec<u8> {
+        written(|w| w.write_optimized_range(&BTreeSet::from_iter(ids.iter().copied())))
+    }
+
+    #[test_case(&[] => b("1 000000000000") ; "empty")]
+    #[test_case(&[1, 3, 5] => b("0 0000000000000101 10101") ; "bitfield")]
+    #[test_case(&[3, 20] => b("1 000000000010 0 0011 0 1010011") ; "fibonacci")]
+    fn write_optimized_range_compact(ids: &[u16]) -> Vec<u8> {
+        let mut w = DataWriter::compact();
+        w.write_optimized_range(&BTreeSet::from_iter(ids.iter().copied()))
+            .unwrap();
+        w.into_bytes()
+    }
+
+    #[test_case(&[1, 3, 5] => b("0000000000000101 0 10101") ; "bitfield")]
+    #[test_case(&[3, 500] => b("0000000111110100 1 000000000010 0 0000000000000011 0 0000000111110100") ; "integer range")]
+    fn write_optimized_int_range(ids: &[u16]) -> Vec<u8> {
+        written(|w| w.write_optimized_integer_range(&BTreeSet::from_iter(ids.iter().copied())))
+    }
+
+    #[test_case(&[] ; "empty")]
+    #[test_case(&[1, 3, 5] ; "sparse")]
+    #[test_case(&[3, 5, 6, 7, 8] ; "mixed")]
+    #[test_case(&[7, 200, 201, 202, 203, 900] ; "wide")]
+    fn optimized_ranges_round_trip(ids: &[u16]) {
+        let ids = BTreeSet::from_iter(ids.iter().copied());
+
+        let bytes = written(|w| w.write_optimized_range(&ids));
+        assert_eq!(DataReader::new(&bytes).read_optimized_range().unwrap(), ids);
+
+        let bytes = written(|w| w.write_optimized_integer_range(&ids));
+        assert_eq!(
+            DataReader::new(&bytes)
+                .read_optimized_integer_range()
+                .unwrap(),
+            ids
+        );
+    }
+
     #[test_case("000000000000" => Vec::<Range>::new() ; "empty")]
     #[test_case("000000000001 000011 01 0000000000000101 0 10101" => vec![
         Range {
@@ -400,4 +1457,231 @@ mod tests {
             .read_n_array_of_ranges::<u8, u8>(6, 2)
             .unwrap()
     }
+
+    #[derive(Debug, FromDataReader)]
+    struct Ranges {
+        #[gpp(n_array_of_ranges(x = 4, y = 3))]
+        pub ranges: Vec<GenericRange<u8, u8>>,
+    }
+
+    #[test]
+    fn derive_n_array_of_ranges() {
+        let r = DataReader::new(&b("000000000001 0011 101 0 0000000000000101 10101"))
+            .parse::<Ranges>()
+            .unwrap();
+        assert_eq!(
+            r.ranges,
+            vec![Range {
+                key: 3,
+                range_type: 5,
+                ids: BTreeSet::from_iter([1, 3, 5]),
+            }]
+        );
+    }
+
+    #[derive(Debug, FromDataReader, ToDataWriter)]
+    struct Skipped {
+        pub first: u8,
+        #[gpp(skip)]
+        pub cached: Option<u8>,
+        #[gpp(skip)]
+        count: usize,
+        pub second: bool,
+    }
+
+    #[test]
+    fn derive_skip() {
+        let bytes = b("000011 1");
+        let r = DataReader::new(&bytes).parse::<Skipped>().unwrap();
+        assert_eq!(r.first, 3);
+        assert_eq!(r.cached, None);
+        assert_eq!(r.count, 0);
+        assert!(r.second);
+
+        let written = written(|w| {
+            w.write(&Skipped {
+                cached: Some(1),
+                count: 2,
+                ..r
+            })
+        });
+        assert_eq!(written, bytes);
+    }
+
+    #[test_case("00000001 00000010 00000011", 0, 2 => vec![1, 2] ; "aligned")]
+    #[test_case("101 00000001 11111111", 3, 2 => vec![1, 0xff] ; "misaligned")]
+    #[test_case("1", 1, 0 => Vec::<u8>::new() ; "empty")]
+    fn read_bytes(s: &str, skip: u32, n: usize) -> Vec<u8> {
+        let bytes = b(s);
+        let mut r = DataReader::new(&bytes);
+        r.read_fixed_integer::<u8>(skip).unwrap();
+        let read = r.read_bytes(n).unwrap();
+        assert_eq!(r.position(), skip as u64 + n as u64 * 8);
+        read
+    }
+
+    #[test]
+    fn read_bytes_truncated() {
+        let bytes = b("101 00000001 1111");
+        let e = DataReader::new(&bytes).read_bytes(3).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test_case(&[1, 0xff] => b("101 00000001 11111111") ; "misaligned")]
+    fn write_bytes(bytes: &[u8]) -> Vec<u8> {
+        written(|w| {
+            w.write_fixed_integer(3, 5u8)?;
+            w.write_bytes(bytes.len(), bytes)
+        })
+    }
+
+    #[test]
+    fn write_bytes_wrong_length() {
+        assert!(matches!(
+            DataWriter::new().write_bytes(2, &[1]),
+            Err(SectionEncodeError::InvalidFieldValue { .. })
+        ));
+    }
+
+    #[derive(Debug, FromDataReader, ToDataWriter)]
+    struct Signed {
+        pub key_id: u8,
+        #[gpp(bytes(2))]
+        pub signature: Vec<u8>,
+    }
+
+    #[test]
+    fn derive_bytes() {
+        let bytes = b("000011 10101010 01010101");
+        let r = DataReader::new(&bytes).parse::<Signed>().unwrap();
+        assert_eq!(r.key_id, 3);
+        assert_eq!(r.signature, vec![0xaa, 0x55]);
+        assert_eq!(written(|w| w.write(&r)), bytes);
+    }
+
+    #[derive(Debug, FromDataReader, ToDataWriter)]
+    struct Widths {
+        pub version: u8,
+        #[gpp(fixed_integer(bits = 12))]
+        pub cmp_id: u16,
+        #[gpp(fixed_integer(bits = 3))]
+        pub flags: u8,
+        #[gpp(fixed_integer(bits = 14))]
+        pub count: u16,
+    }
+
+    #[test]
+    fn derive_fixed_integer_bits() {
+        let bytes = b("000010 000000001010 101 00000000000111");
+        let r = DataReader::new(&bytes).parse::<Widths>().unwrap();
+        assert_eq!(r.version, 2);
+        assert_eq!(r.cmp_id, 10);
+        assert_eq!(r.flags, 5);
+        assert_eq!(r.count, 7);
+        assert_eq!(written(|w| w.write(&r)), bytes);
+
+        let mut schema = Vec::new();
+        <Widths as crate::sections::Schema>::push_schema("", &mut schema);
+        let bits = schema.iter().map(|f| f.bits).collect::<Vec<_>>();
+        assert_eq!(bits, [Some(6), Some(12), Some(3), Some(14)]);
+    }
+
+    // round trips of whole sections are tested in the modules of each section
+    mod round_trip {
+        use super::*;
+        use proptest::collection::btree_set;
+        use proptest::prelude::*;
+
+        fn id_set() -> impl Strategy<Value = BTreeSet<u16>> {
+            btree_set(1..=1000u16, 0..50)
+        }
+
+        proptest! {
+            #[test]
+            fn fixed_integer(bits in 1..=32u32, value: u32) {
+                let value = value >> (32 - bits);
+                let bytes = written(|w| w.write_fixed_integer(bits, value));
+                prop_assert_eq!(DataReader::new(&bytes).read_fixed_integer::<u32>(bits)?, value);
+            }
+
+            #[test]
+            fn signed_integer(bits in 1..=32u32, value: i32) {
+                let value = value >> (32 - bits);
+                let bytes = written(|w| w.write_signed_integer(bits, value));
+                prop_assert_eq!(DataReader::new(&bytes).read_signed_integer::<i32>(bits)?, value);
+            }
+
+            #[test]
+            fn fibonacci_integer(value in 1..=u32::MAX as u64) {
+                let bytes = written(|w| w.write_fibonacci_integer(value));
+                prop_assert_eq!(DataReader::new(&bytes).read_fibonacci_integer::<u64>()?, value);
+            }
+
+            #[test]
+            fn string(s in "[A-Z]{0,10}") {
+                let bytes = written(|w| w.write_string(s.len(), &s));
+                prop_assert_eq!(DataReader::new(&bytes).read_string(s.len())?, s);
+            }
+
+            #[test]
+            fn datetime(deciseconds in 0..1u64 << 36) {
+                let bytes = written(|w| w.write_datetime_deciseconds(&deciseconds));
+                prop_assert_eq!(DataReader::new(&bytes).read_datetime_deciseconds()?, deciseconds);
+            }
+
+            #[test]
+            fn fixed_bitfield(ids in id_set(), extra in 0..10usize) {
+                let bits = ids.last().copied().unwrap_or(0) as usize + extra;
+                let bytes = written(|w| w.write_fixed_bitfield(bits, &ids));
+                prop_assert_eq!(DataReader::new(&bytes).read_fixed_bitfield(bits)?, ids);
+            }
+
+            #[test]
+            fn variable_bitfield(ids in id_set()) {
+                let bytes = written(|w| w.write_variable_bitfield(&ids));
+                prop_assert_eq!(DataReader::new(&bytes).read_variable_bitfield()?, ids);
+            }
+
+            #[test]
+            fn integer_range(ids in id_set()) {
+                let bytes = written(|w| w.write_integer_range(&ids));
+                let r = DataReader::new(&bytes).read_integer_range()?;
+                prop_assert_eq!(BTreeSet::from_iter(r), ids);
+            }
+
+            #[test]
+            fn fibonacci_range(ids in id_set()) {
+                let bytes = written(|w| w.write_fibonacci_range(&ids));
+                let r = DataReader::new(&bytes).read_fibonacci_range::<u16>()?;
+                prop_assert_eq!(BTreeSet::from_iter(r), ids);
+            }
+
+            #[test]
+            fn optimized_range(ids in id_set()) {
+                let bytes = written(|w| w.write_optimized_range(&ids));
+                prop_assert_eq!(DataReader::new(&bytes).read_optimized_range()?, ids);
+            }
+
+            #[test]
+            fn optimized_integer_range(ids in id_set()) {
+                let bytes = written(|w| w.write_optimized_integer_range(&ids));
+                prop_assert_eq!(DataReader::new(&bytes).read_optimized_integer_range()?, ids);
+            }
+
+            #[test]
+            fn sequence(a: bool, s in "[A-Z]{2}", ids in id_set(), n in 1..=1000u64) {
+                let bytes = written(|w| {
+                    w.write_bool(a)?;
+                    w.write_string(2, &s)?;
+                    w.write_optimized_integer_range(&ids)?;
+                    w.write_fibonacci_integer(n)
+                });
+                let mut r = DataReader::new(&bytes);
+                prop_assert_eq!(r.read_bool()?, a);
+                prop_assert_eq!(r.read_string(2)?, s);
+                prop_assert_eq!(r.read_optimized_integer_range()?, ids);
+                prop_assert_eq!(r.read_fibonacci_integer::<u64>()?, n);
+            }
+        }
+    }
 }
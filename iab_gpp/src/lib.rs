@@ -85,6 +85,12 @@
 //! This is done to avoid obtaining erroneous user consent information from potentially corrupted
 //! payloads.
 //!
+pub mod base64;
+pub mod codec;
 pub(crate) mod core;
 pub mod sections;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_support;
 pub mod v1;
+
+pub use v1::decode_one;
@@ -0,0 +1,118 @@
+//! Decoding of sections encoded with a simple alphabet of one character per field.
+//!
+//! Unlike GPP sections, which are base64 encoded, deprecated sections such as
+//! [`UspV1`](crate::sections::uspv1::UspV1) hold a version digit followed by a character per
+//! flag, such as `1YN-`.
+
+use crate::sections::SectionDecodeError;
+use std::str::Chars;
+
+/// A field which is encoded as a single character.
+pub(crate) trait CharFlag: Sized {
+    fn from_char(c: char) -> Option<Self>;
+    fn to_char(self) -> char;
+}
+
+/// A reader of the characters of a section encoded with a simple alphabet.
+///
+/// Errors mention the kind of section and the whole string being read.
+pub(crate) struct CharReader<'a> {
+    kind: &'static str,
+    s: &'a str,
+    chars: Chars<'a>,
+}
+
+impl<'a> CharReader<'a> {
+    pub(crate) fn new(kind: &'static str, s: &'a str) -> Self {
+        Self {
+            kind,
+            s,
+            chars: s.chars(),
+        }
+    }
+
+    /// Reads a decimal digit.
+    pub(crate) fn read_digit(&mut self) -> Result<u8, SectionDecodeError> {
+        let c = self.next_char()?;
+        c.to_digit(10)
+            .map(|d| d as u8)
+            .ok_or_else(|| self.invalid_character(c))
+    }
+
+    /// Reads a version digit, which must be equal to `expected`.
+    pub(crate) fn read_version(&mut self, expected: u8) -> Result<u8, SectionDecodeError> {
+        let found = self.read_digit()?;
+        if found != expected {
+            return Err(SectionDecodeError::InvalidSectionVersion { expected, found });
+        }
+        Ok(found)
+    }
+
+    /// Reads a flag from its character.
+    pub(crate) fn read_flag<T: CharFlag>(&mut self) -> Result<T, SectionDecodeError> {
+        let c = self.next_char()?;
+        T::from_char(c).ok_or_else(|| self.invalid_character(c))
+    }
+
+    fn next_char(&mut self) -> Result<char, SectionDecodeError> {
+        self.chars
+            .next()
+            .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.s.to_string()))
+    }
+
+    fn invalid_character(&self, character: char) -> SectionDecodeError {
+        SectionDecodeError::InvalidCharacter {
+            character,
+            kind: self.kind,
+            s: self.s.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[derive(Debug, PartialEq)]
+    struct Flag(bool);
+
+    impl CharFlag for Flag {
+        fn from_char(c: char) -> Option<Self> {
+            match c {
+                'Y' => Some(Self(true)),
+                'N' => Some(Self(false)),
+                _ => None,
+            }
+        }
+
+        fn to_char(self) -> char {
+            if self.0 {
+                'Y'
+            } else {
+                'N'
+            }
+        }
+    }
+
+    #[test]
+    fn read() {
+        let mut r = CharReader::new("test", "3YN");
+        assert_eq!(r.read_version(3).unwrap(), 3);
+        assert_eq!(r.read_flag::<Flag>().unwrap(), Flag(true));
+        assert_eq!(r.read_flag::<Flag>().unwrap(), Flag(false));
+    }
+
+    #[test_case("" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "empty")]
+    #[test_case("Y" => matches SectionDecodeError::InvalidCharacter { character: 'Y', kind: "test", .. } ; "not a digit")]
+    #[test_case("2" => matches SectionDecodeError::InvalidSectionVersion { expected: 1, found: 2 } ; "unexpected version")]
+    fn read_version_error(s: &str) -> SectionDecodeError {
+        CharReader::new("test", s).read_version(1).unwrap_err()
+    }
+
+    #[test_case("" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "empty")]
+    #[test_case("1" => matches SectionDecodeError::InvalidCharacter { character: '1', .. } ; "invalid character")]
+    fn read_flag_error(s: &str) -> SectionDecodeError {
+        CharReader::new("test", s).read_flag::<Flag>().unwrap_err()
+    }
+}
@@ -19,8 +19,37 @@
 //! section types are marked with the `#[non_exhaustive]` attribute to preserve minor version
 //! compatibility.
 //!
+//! # Forward compatibility
+//!
+//! Sections are decoded according to the following policy, so that strings produced by
+//! encoders following a newer revision of a section can still be read:
+//!
+//! - The version field of a section must be one of the versions implemented by this crate.
+//!   Any other version is rejected with [`SectionDecodeError::UnknownSegmentVersion`], or
+//!   [`SectionDecodeError::InvalidSectionVersion`] for [`UspV1`], as its fields may have a
+//!   different layout.
+//! - Bits following the last documented field of a segment are ignored, as they may hold
+//!   fields appended by a later revision of the same version. The
+//!   [`strict_trailing_data`](crate::v1::ParseOptions::strict_trailing_data) option rejects
+//!   them instead, except for the zero bits padding the segment to a Base64 character.
+//! - Optional segments of an unknown type are rejected with
+//!   [`SectionDecodeError::UnknownSegmentType`].
+//!
+//! | Section | Versions | Optional segments |
+//! |---------|----------|-------------------|
+//! | [`TcfEuV1`] | 1 | none |
+//! | [`TcfEuV2`] | 2 | disclosed vendors (1), publisher purposes (3) |
+//! | [`TcfCaV1`] | 1 | disclosed vendors (1), publisher purposes (3) |
+//! | [`UspV1`] | 1 | none |
+//! | [`UsNat`] | 1, 2 | GPC (1) |
+//! | [`UsCa`], [`UsCo`], [`UsCt`], [`UsDe`], [`UsIa`], [`UsMt`], [`UsNe`], [`UsNh`], [`UsNj`], [`UsOr`], [`UsTn`] | 1 | GPC (1) |
+//! | [`UsFl`], [`UsTx`], [`UsUt`], [`UsVa`] | 1 | none |
+//!
+//! [`UspV1`] is not a binary section: characters following its three flags are ignored, or
+//! rejected with the `strict_trailing_data` option.
+//!
 use crate::core::base64::DecodeError;
-use crate::core::{DataReader, DecodeExt, FromDataReader};
+use crate::core::{DataReader, DataWriter, DecodeExt, FromDataReader, ToDataWriter};
 use crate::sections::tcfcav1::TcfCaV1;
 use crate::sections::tcfeuv1::TcfEuV1;
 use crate::sections::tcfeuv2::TcfEuV2;
@@ -41,13 +70,18 @@ use crate::sections::ustn::UsTn;
 use crate::sections::ustx::UsTx;
 use crate::sections::usut::UsUt;
 use crate::sections::usva::UsVa;
+use fnv::FnvHashMap;
 use num_derive::{FromPrimitive, ToPrimitive};
+use std::borrow::Borrow;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Debug;
 use std::io;
 use std::str::FromStr;
 use strum_macros::Display;
 use thiserror::Error;
 
+pub(crate) mod char_reader;
 pub mod tcfcav1;
 pub mod tcfeuv1;
 pub mod tcfeuv2;
@@ -70,7 +104,11 @@ pub mod ustx;
 pub mod usut;
 pub mod usva;
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+#[derive(
+    Clone, Copy, Debug, Display, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub enum SectionId {
     TcfEuV1 = 1,
@@ -97,12 +135,503 @@ pub enum SectionId {
     UsTn = 22,
 }
 
+impl SectionId {
+    /// Returns `true` if this section applies to a single US state.
+    ///
+    /// The national US sections ([`UsNat`](Self::UsNat) and [`UspV1`](Self::UspV1)) are not
+    /// state sections.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    ///
+    /// assert!(SectionId::UsCa.is_us_state());
+    /// assert!(!SectionId::UsNat.is_us_state());
+    /// assert!(!SectionId::TcfEuV2.is_us_state());
+    /// ```
+    pub fn is_us_state(&self) -> bool {
+        matches!(
+            self,
+            SectionId::UsCa
+                | SectionId::UsVa
+                | SectionId::UsCo
+                | SectionId::UsUt
+                | SectionId::UsCt
+                | SectionId::UsFl
+                | SectionId::UsMt
+                | SectionId::UsOr
+                | SectionId::UsTx
+                | SectionId::UsDe
+                | SectionId::UsIa
+                | SectionId::UsNe
+                | SectionId::UsNh
+                | SectionId::UsNj
+                | SectionId::UsTn
+        )
+    }
+
+    /// Returns `true` if this section is a Transparency and Consent Framework section,
+    /// for either the EU or Canada.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    ///
+    /// assert!(SectionId::TcfCaV1.is_tcf());
+    /// assert!(!SectionId::UspV1.is_tcf());
+    /// ```
+    pub fn is_tcf(&self) -> bool {
+        matches!(
+            self,
+            SectionId::TcfEuV1 | SectionId::TcfEuV2 | SectionId::TcfCaV1
+        )
+    }
+
+    /// Returns `true` if this section is deprecated by the GPP specification.
+    ///
+    /// The deprecated sections are TCF EU v1, superseded by TCF EU v2, and US Privacy v1,
+    /// superseded by the US national and state sections.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    ///
+    /// assert!(SectionId::UspV1.is_deprecated());
+    /// assert!(!SectionId::UsNat.is_deprecated());
+    /// ```
+    pub fn is_deprecated(&self) -> bool {
+        matches!(self, SectionId::TcfEuV1 | SectionId::UspV1)
+    }
+
+    /// Returns the description of the wire format of this section, field by field.
+    ///
+    /// Fields are listed in the order in which they are encoded, starting with the version of
+    /// each segment. Fields of an optional segment are preceded by its segment type. When a
+    /// segment has several versions, the fields of each version are listed under a `vN`
+    /// prefix, after the shared version field.
+    ///
+    /// Returns `None` for the header and signal integrity sections, which are not decoded as
+    /// sections.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    ///
+    /// let schema = SectionId::UsCa.schema().unwrap();
+    ///
+    /// assert_eq!(schema[0].name, "core.version");
+    /// assert_eq!(schema[1].name, "core.v1.sale_opt_out_notice");
+    /// assert_eq!(schema[1].rust_type, "Notice");
+    /// assert_eq!(schema[1].bits, Some(2));
+    /// ```
+    pub fn schema(&self) -> Option<Vec<FieldSchema>> {
+        fn schema_of<T: Schema>() -> Option<Vec<FieldSchema>> {
+            let mut out = vec![];
+            T::push_schema("", &mut out);
+            Some(out)
+        }
+
+        match self {
+            SectionId::TcfEuV1 => schema_of::<TcfEuV1>(),
+            SectionId::TcfEuV2 => schema_of::<TcfEuV2>(),
+            SectionId::TcfCaV1 => schema_of::<TcfCaV1>(),
+            SectionId::UspV1 => schema_of::<UspV1>(),
+            SectionId::UsNat => schema_of::<UsNat>(),
+            SectionId::UsCa => schema_of::<UsCa>(),
+            SectionId::UsVa => schema_of::<UsVa>(),
+            SectionId::UsCo => schema_of::<UsCo>(),
+            SectionId::UsUt => schema_of::<UsUt>(),
+            SectionId::UsCt => schema_of::<UsCt>(),
+            SectionId::UsFl => schema_of::<UsFl>(),
+            SectionId::UsMt => schema_of::<UsMt>(),
+            SectionId::UsOr => schema_of::<UsOr>(),
+            SectionId::UsTx => schema_of::<UsTx>(),
+            SectionId::UsDe => schema_of::<UsDe>(),
+            SectionId::UsIa => schema_of::<UsIa>(),
+            SectionId::UsNe => schema_of::<UsNe>(),
+            SectionId::UsNh => schema_of::<UsNh>(),
+            SectionId::UsNj => schema_of::<UsNj>(),
+            SectionId::UsTn => schema_of::<UsTn>(),
+            SectionId::GppHeader | SectionId::GppSignalIntegrity => None,
+        }
+    }
+
+    /// Returns the legal framework covered by this section, if any.
+    ///
+    /// The header and signal integrity sections do not cover any framework.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{Framework, SectionId};
+    ///
+    /// assert_eq!(SectionId::TcfEuV2.framework(), Some(Framework::GdprTcf));
+    /// assert_eq!(
+    ///     SectionId::UsCa.framework(),
+    ///     Some(Framework::UsStatePrivacy(SectionId::UsCa))
+    /// );
+    /// assert_eq!(SectionId::GppHeader.framework(), None);
+    /// ```
+    pub fn framework(&self) -> Option<Framework> {
+        match self {
+            SectionId::TcfEuV1 | SectionId::TcfEuV2 => Some(Framework::GdprTcf),
+            SectionId::TcfCaV1 => Some(Framework::CanadaTcf),
+            SectionId::UspV1 => Some(Framework::UsPrivacy),
+            SectionId::UsNat => Some(Framework::UsNationalPrivacy),
+            id if id.is_us_state() => Some(Framework::UsStatePrivacy(*id)),
+            _ => None,
+        }
+    }
+}
+
+/// A legal framework covered by one or more sections.
+///
+/// Created with the method [`SectionId::framework`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum Framework {
+    /// The GDPR, through the EU Transparency and Consent Framework (TCF EU v1 and v2).
+    GdprTcf,
+    /// Canadian privacy laws, through the Canadian Transparency and Consent Framework.
+    CanadaTcf,
+    /// The CCPA, through the legacy US Privacy string.
+    UsPrivacy,
+    /// US privacy laws, through the US national section.
+    UsNationalPrivacy,
+    /// The privacy law of a single US state, through the section of that state.
+    UsStatePrivacy(SectionId),
+}
+
 pub trait DecodableSection: FromStr<Err = SectionDecodeError> {
     const ID: SectionId;
+
+    /// Parses a section, using the given buffer as scratch space for Base64 decoding.
+    ///
+    /// The buffer is cleared before use, and its allocation can be reused across calls.
+    /// The default implementation ignores the buffer.
+    fn from_str_with_buffer(s: &str, buf: &mut Vec<u8>) -> Result<Self, SectionDecodeError> {
+        let _ = buf;
+        s.parse()
+    }
+}
+
+/// A section which can be encoded back into its string representation.
+pub trait EncodableSection: DecodableSection {
+    /// Encodes this section into a string which can be embedded in a GPP string.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    fn encode(&self) -> Result<String, SectionEncodeError>;
+
+    /// Encodes this section into the shortest string which decodes to the same section.
+    ///
+    /// Sets of IDs are always written with their shortest encoding. Unlike
+    /// [`encode`](Self::encode), segments are not padded to a whole byte: they are written with
+    /// as few base64 characters as needed to hold their bits.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    fn encode_compact(&self) -> Result<String, SectionEncodeError>;
+
+    /// Returns the number of bits this section occupies once encoded.
+    ///
+    /// This is the sum of the bit lengths of all segments, before they are padded to whole
+    /// bytes and encoded in base64. No output is produced to compute it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    fn raw_len_bits(&self) -> Result<usize, SectionEncodeError>;
+}
+
+/// A section made of a mandatory core segment followed by optional segments.
+pub trait SegmentedSection: DecodableSection {
+    /// Parses only the core segment of the given string.
+    ///
+    /// The optional segments are not decoded, and the matching fields of the returned
+    /// section are left unset. Instead, the types of the optional segments present in the
+    /// string are returned alongside the section.
+    ///
+    /// This is useful when only the core segment is of interest, since optional segments
+    /// such as large vendor lists can be costly to decode.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SegmentedSection;
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let (tcf, segment_types) =
+    ///     TcfEuV2::parse_core_only("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA")
+    ///         .unwrap();
+    ///
+    /// assert_eq!(tcf.core.cmp_id, 27);
+    /// assert_eq!(tcf.publisher_purposes, None);
+    /// assert!(segment_types.contains(&3));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the core segment cannot be decoded, or if the type of
+    /// an optional segment cannot be read or appears twice.
+    fn parse_core_only(s: &str) -> Result<(Self, BTreeSet<u8>), SectionDecodeError>;
 }
 
 pub type IdSet = BTreeSet<u16>;
 
+/// Extension methods testing the membership of several identifiers of an [`IdSet`] at once.
+///
+/// The identifiers can be given as a slice, an array or a range.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::{IdSet, IdSetExt};
+///
+/// let purpose_consents = IdSet::from([1, 2, 3, 4]);
+///
+/// assert!(purpose_consents.contains_all(&[1, 3, 4]));
+/// assert!(purpose_consents.contains_all(2..=4));
+/// assert!(!purpose_consents.contains_all([4, 5]));
+/// assert!(purpose_consents.contains_any([4, 5]));
+/// assert!(!purpose_consents.contains_any(7..10));
+/// ```
+pub trait IdSetExt {
+    /// Returns `true` if the set contains all the given identifiers.
+    ///
+    /// Returns `true` if no identifier is given.
+    fn contains_all<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>;
+
+    /// Returns `true` if the set contains at least one of the given identifiers.
+    ///
+    /// Returns `false` if no identifier is given.
+    fn contains_any<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>;
+}
+
+impl IdSetExt for IdSet {
+    fn contains_all<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>,
+    {
+        ids.into_iter().all(|id| self.contains(id.borrow()))
+    }
+
+    fn contains_any<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>,
+    {
+        ids.into_iter().any(|id| self.contains(id.borrow()))
+    }
+}
+
+/// A read-only set of identifiers optimized for membership tests.
+///
+/// Decoded sections hold identifiers in [`IdSet`]s, whose lookups walk a tree. Vendor sets
+/// of TCF sections may contain hundreds of identifiers, and are typically queried many times
+/// once decoded. This set stores one bit per possible identifier up to the highest one, which
+/// makes [`contains`](Self::contains) a constant time operation, at the cost of one bit of
+/// memory per identifier below the highest one.
+///
+/// It is built from an [`IdSet`], once the section is decoded.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::{DenseIdSet, IdSet};
+///
+/// let vendor_consents = IdSet::from([2, 6, 8, 755]);
+/// let dense = DenseIdSet::from(&vendor_consents);
+///
+/// assert!(dense.contains(755));
+/// assert!(!dense.contains(7));
+/// assert_eq!(dense.len(), 4);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct DenseIdSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl DenseIdSet {
+    /// Returns `true` if the set contains the given identifier.
+    pub fn contains(&self, id: u16) -> bool {
+        let id = usize::from(id);
+        self.words
+            .get(id / 64)
+            .is_some_and(|w| w & (1 << (id % 64)) != 0)
+    }
+
+    /// Returns the number of identifiers in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no identifier.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over the identifiers of the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..=u16::MAX)
+            .zip(
+                self.words
+                    .iter()
+                    .flat_map(|&w| (0..64).map(move |i| w & (1 << i) != 0)),
+            )
+            .filter_map(|(id, present)| present.then_some(id))
+    }
+}
+
+impl From<&IdSet> for DenseIdSet {
+    fn from(ids: &IdSet) -> Self {
+        let mut words = vec![0u64; ids.last().map_or(0, |&id| usize::from(id) / 64 + 1)];
+        for &id in ids {
+            let id = usize::from(id);
+            words[id / 64] |= 1 << (id % 64);
+        }
+
+        Self {
+            words,
+            len: ids.len(),
+        }
+    }
+}
+
+impl From<&DenseIdSet> for IdSet {
+    fn from(ids: &DenseIdSet) -> Self {
+        ids.iter().collect()
+    }
+}
+
+impl IdSetExt for DenseIdSet {
+    fn contains_all<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>,
+    {
+        ids.into_iter().all(|id| self.contains(*id.borrow()))
+    }
+
+    fn contains_any<I>(&self, ids: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<u16>,
+    {
+        ids.into_iter().any(|id| self.contains(*id.borrow()))
+    }
+}
+
+/// A date and time stored in a section, with a precision of one decisecond.
+///
+/// Sections encode timestamps as a number of deciseconds since the Unix epoch. The original
+/// value is kept so that encoding a decoded section reproduces it exactly, and
+/// [`seconds`](Timestamp::seconds) returns the usual Unix timestamp.
+///
+/// A timestamp can be compared directly with a number of seconds.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::Timestamp;
+///
+/// let t = Timestamp::from_deciseconds(16504128005);
+///
+/// assert_eq!(t.seconds(), 1650412800);
+/// assert_eq!(t.deciseconds(), 16504128005);
+/// assert_eq!(t.to_string(), "1650412800.5");
+/// assert_ne!(t, 1650412800);
+/// assert_eq!(Timestamp::from_seconds(1650412800), 1650412800);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    /// Creates a timestamp from a number of seconds since the Unix epoch.
+    ///
+    /// Negative values are clamped to the epoch.
+    pub fn from_seconds(seconds: i64) -> Self {
+        Self(u64::try_from(seconds).unwrap_or(0).saturating_mul(10))
+    }
+
+    /// Creates a timestamp from a number of deciseconds since the Unix epoch.
+    pub fn from_deciseconds(deciseconds: u64) -> Self {
+        Self(deciseconds)
+    }
+
+    /// Returns the number of whole seconds since the Unix epoch.
+    pub fn seconds(&self) -> i64 {
+        (self.0 / 10) as i64
+    }
+
+    /// Returns the number of deciseconds since the Unix epoch, as encoded in the section.
+    pub fn deciseconds(&self) -> u64 {
+        self.0
+    }
+}
+
+impl PartialEq<i64> for Timestamp {
+    fn eq(&self, other: &i64) -> bool {
+        *self == Timestamp::from_seconds(*other)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 % 10 {
+            0 => write!(f, "{}", self.seconds()),
+            d => write!(f, "{}.{d}", self.seconds()),
+        }
+    }
+}
+
+// timestamps are serialized as seconds, which is their usual representation
+#[cfg(feature = "serde")]
+impl serde::Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.seconds())
+    }
+}
+
+impl FromDataReader for Timestamp {
+    type Err = io::Error;
+
+    fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
+        Ok(Self(r.read_datetime_deciseconds()?))
+    }
+}
+
+impl ToDataWriter for Timestamp {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_datetime_deciseconds(&self.0)
+    }
+}
+
+impl Fields for Timestamp {
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        out.push((prefix.to_string(), self.to_string()));
+    }
+}
+
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum SectionDecodeError {
@@ -122,8 +651,8 @@ pub enum SectionDecodeError {
     },
     #[error("invalid section version (expected {expected}, found {found})")]
     InvalidSectionVersion { expected: u8, found: u8 },
-    #[error("unable to decode segment")]
-    DecodeSegment(#[from] DecodeError),
+    #[error("unable to decode segment {index}: {source}")]
+    DecodeSegment { index: usize, source: DecodeError },
     #[error("invalid segment version ({segment_version})")]
     UnknownSegmentVersion { segment_version: u8 },
     #[error("unknown segment type {segment_type}")]
@@ -134,9 +663,43 @@ pub enum SectionDecodeError {
     MissingCoreSegment,
     #[error("invalid field value (expected {expected}, found {found})")]
     InvalidFieldValue { expected: String, found: String },
+    #[error("empty section {0}")]
+    EmptySection(SectionId),
+    #[error("trailing data after the last field of a segment ({bits} bits)")]
+    TrailingData { bits: u64 },
+    #[error("empty segment at index {index}")]
+    EmptySegment { index: usize },
+}
+
+impl From<DecodeError> for SectionDecodeError {
+    /// Wraps an error of the core segment, which is the first segment of a section.
+    fn from(source: DecodeError) -> Self {
+        Self::DecodeSegment { index: 0, source }
+    }
+}
+
+/// The error type for section encoding operations.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SectionEncodeError {
+    /// An integer field value does not fit in its declared bit width.
+    #[error("integer {value} does not fit in {bits} bits")]
+    IntegerOutOfRange { bits: u32, value: u64 },
+    /// A string field contains a character outside its alphabet, such as a country code
+    /// which is not made of uppercase letters.
+    #[error("invalid character {character:?} in string {s:?}")]
+    InvalidCharacter { character: char, s: String },
+    /// A field value cannot be represented in the encoded section.
+    #[error("invalid field value (expected {expected}, found {found})")]
+    InvalidFieldValue { expected: String, found: String },
+    /// An I/O error occurred while writing the section.
+    #[error("unable to write section")]
+    Write(#[from] io::Error),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub enum Section {
     TcfEuV1(TcfEuV1),
@@ -186,36 +749,905 @@ impl Section {
             Section::UsTn(_) => SectionId::UsTn,
         }
     }
+
+    /// Clears the fields which identify the CMP and the time at which the section was created.
+    ///
+    /// The creation and last update timestamps, the CMP ID and version, and the consent
+    /// screen of TCF sections are set to zero. Other sections have no such fields and are left
+    /// unchanged. The consent choices are kept, so that a redacted section can still be used
+    /// to reproduce decoding issues.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let mut section = Section::TcfEuV2("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA".parse().unwrap());
+    /// section.redact();
+    ///
+    /// let Section::TcfEuV2(tcf) = section else { unreachable!() };
+    /// assert_eq!(tcf.core.cmp_id, 0);
+    /// assert_eq!(tcf.core.created.seconds(), 0);
+    /// ```
+    pub fn redact(&mut self) {
+        macro_rules! redact {
+            ($s:expr) => {{
+                $s.created = Timestamp::default();
+                $s.last_updated = Timestamp::default();
+                $s.cmp_id = 0;
+                $s.cmp_version = 0;
+                $s.consent_screen = 0;
+            }};
+        }
+
+        match self {
+            Section::TcfEuV1(s) => redact!(s),
+            Section::TcfEuV2(s) => redact!(s.core),
+            Section::TcfCaV1(s) => redact!(s.core),
+            _ => {}
+        }
+    }
+
+    /// Encodes this section into its string representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let section = Section::UsCa("BVqqqqpY.YA".parse().unwrap());
+    ///
+    /// assert_eq!(section.encode().unwrap(), "BVqqqqpY.YA");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    pub fn encode(&self) -> Result<String, SectionEncodeError> {
+        match self {
+            Section::TcfEuV1(s) => s.encode(),
+            Section::TcfEuV2(s) => s.encode(),
+            Section::TcfCaV1(s) => s.encode(),
+            Section::UspV1(s) => Ok(s.encode()),
+            Section::UsNat(s) => s.encode(),
+            Section::UsCa(s) => s.encode(),
+            Section::UsVa(s) => s.encode(),
+            Section::UsCo(s) => s.encode(),
+            Section::UsUt(s) => s.encode(),
+            Section::UsCt(s) => s.encode(),
+            Section::UsFl(s) => s.encode(),
+            Section::UsMt(s) => s.encode(),
+            Section::UsOr(s) => s.encode(),
+            Section::UsTx(s) => s.encode(),
+            Section::UsDe(s) => s.encode(),
+            Section::UsIa(s) => s.encode(),
+            Section::UsNe(s) => s.encode(),
+            Section::UsNh(s) => s.encode(),
+            Section::UsNj(s) => s.encode(),
+            Section::UsTn(s) => s.encode(),
+        }
+    }
+
+    /// Encodes this section into the shortest string which decodes to the same section.
+    ///
+    /// See [`EncodableSection::encode_compact`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let section = Section::UsCa("BVqqqqpYAAAA.YA".parse().unwrap());
+    ///
+    /// assert_eq!(section.encode_compact().unwrap(), "BVqqqqpY.Y");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    pub fn encode_compact(&self) -> Result<String, SectionEncodeError> {
+        match self {
+            Section::TcfEuV1(s) => s.encode_compact(),
+            Section::TcfEuV2(s) => s.encode_compact(),
+            Section::TcfCaV1(s) => s.encode_compact(),
+            Section::UspV1(s) => s.encode_compact(),
+            Section::UsNat(s) => s.encode_compact(),
+            Section::UsCa(s) => s.encode_compact(),
+            Section::UsVa(s) => s.encode_compact(),
+            Section::UsCo(s) => s.encode_compact(),
+            Section::UsUt(s) => s.encode_compact(),
+            Section::UsCt(s) => s.encode_compact(),
+            Section::UsFl(s) => s.encode_compact(),
+            Section::UsMt(s) => s.encode_compact(),
+            Section::UsOr(s) => s.encode_compact(),
+            Section::UsTx(s) => s.encode_compact(),
+            Section::UsDe(s) => s.encode_compact(),
+            Section::UsIa(s) => s.encode_compact(),
+            Section::UsNe(s) => s.encode_compact(),
+            Section::UsNh(s) => s.encode_compact(),
+            Section::UsNj(s) => s.encode_compact(),
+            Section::UsTn(s) => s.encode_compact(),
+        }
+    }
+
+    /// Returns the number of bits this section occupies once encoded.
+    ///
+    /// See [`EncodableSection::raw_len_bits`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let section = Section::UsCa("BVqqqqpY.YA".parse().unwrap());
+    ///
+    /// assert_eq!(section.raw_len_bits().unwrap(), 49);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    pub fn raw_len_bits(&self) -> Result<usize, SectionEncodeError> {
+        match self {
+            Section::TcfEuV1(s) => s.raw_len_bits(),
+            Section::TcfEuV2(s) => s.raw_len_bits(),
+            Section::TcfCaV1(s) => s.raw_len_bits(),
+            Section::UspV1(s) => s.raw_len_bits(),
+            Section::UsNat(s) => s.raw_len_bits(),
+            Section::UsCa(s) => s.raw_len_bits(),
+            Section::UsVa(s) => s.raw_len_bits(),
+            Section::UsCo(s) => s.raw_len_bits(),
+            Section::UsUt(s) => s.raw_len_bits(),
+            Section::UsCt(s) => s.raw_len_bits(),
+            Section::UsFl(s) => s.raw_len_bits(),
+            Section::UsMt(s) => s.raw_len_bits(),
+            Section::UsOr(s) => s.raw_len_bits(),
+            Section::UsTx(s) => s.raw_len_bits(),
+            Section::UsDe(s) => s.raw_len_bits(),
+            Section::UsIa(s) => s.raw_len_bits(),
+            Section::UsNe(s) => s.raw_len_bits(),
+            Section::UsNh(s) => s.raw_len_bits(),
+            Section::UsNj(s) => s.raw_len_bits(),
+            Section::UsTn(s) => s.raw_len_bits(),
+        }
+    }
+
+    /// Checks the internal consistency of this section.
+    ///
+    /// Only US sections currently define consistency rules, see
+    /// [`validate_mspa_modes`](us_common::validate_mspa_modes). Other sections are always valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let section = Section::UsCa("BVqqqqpY.YA".parse().unwrap());
+    ///
+    /// assert!(section.validate().is_empty());
+    /// ```
+    pub fn validate(&self) -> Vec<us_common::ValidationError> {
+        match self {
+            Section::UsNat(s) => match &s.core {
+                usnat::Core::V1(core) => core.validate(),
+                usnat::Core::V2(core) => core.validate(),
+            },
+            Section::UsCa(s) => match &s.core {
+                usca::Core::V1(core) => core.validate(),
+            },
+            Section::UsVa(s) => s.core.validate(),
+            Section::UsCo(s) => s.core.validate(),
+            Section::UsUt(s) => s.core.validate(),
+            Section::UsCt(s) => s.core.validate(),
+            Section::UsFl(s) => s.core.validate(),
+            Section::UsMt(s) => s.core.validate(),
+            Section::UsOr(s) => s.core.validate(),
+            Section::UsTx(s) => s.core.validate(),
+            Section::UsDe(s) => s.core.validate(),
+            Section::UsIa(s) => s.core.validate(),
+            Section::UsNe(s) => s.core.validate(),
+            Section::UsNh(s) => s.core.validate(),
+            Section::UsNj(s) => s.core.validate(),
+            Section::UsTn(s) => s.core.validate(),
+            Section::TcfEuV1(_) | Section::TcfEuV2(_) | Section::TcfCaV1(_) | Section::UspV1(_) => {
+                vec![]
+            }
+        }
+    }
+
+    /// Serializes this section into a JSON value.
+    ///
+    /// The value is an object with a single key, the name of the section, mapped to the
+    /// section's fields.
+    ///
+    /// Fields are named after the fields of the Rust types, such as `mspa_covered_transaction`.
+    /// With the `serde-iab-names` feature, they are named in PascalCase instead, such as
+    /// `MspaCoveredTransaction`, which matches the field names of the IAB specification.
+    /// Nested structures, such as the `core` segment of sections, are kept in both cases.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+
+    /// Compares this section with another one and returns the list of fields which differ.
+    ///
+    /// Fields are identified by their path within the section, such as
+    /// `core.purpose_consents`, and their values are given in their debug representation.
+    ///
+    /// If both sections are of different types, fields which only exist in one of them
+    /// are reported with a missing old or new value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{FieldDiff, Section};
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let a = GPPString::parse_str("DBABTA~1YNN").unwrap();
+    /// let b = GPPString::parse_str("DBABTA~1YYN").unwrap();
+    ///
+    /// let diff = a.decode_all_sections()[0]
+    ///     .as_ref()
+    ///     .unwrap()
+    ///     .diff(b.decode_all_sections()[0].as_ref().unwrap());
+    ///
+    /// assert_eq!(
+    ///     diff,
+    ///     vec![FieldDiff {
+    ///         field: "opt_out_sale".to_string(),
+    ///         old: Some("No".to_string()),
+    ///         new: Some("Yes".to_string()),
+    ///     }]
+    /// );
+    /// ```
+    pub fn diff(&self, other: &Section) -> Vec<FieldDiff> {
+        let old = self.fields();
+        let new = other.fields();
+        let new_values = new
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<FnvHashMap<_, _>>();
+        let old_values = old
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<FnvHashMap<_, _>>();
+
+        let changed_or_removed =
+            old.iter()
+                .filter_map(|(field, old_value)| match new_values.get(field.as_str()) {
+                    Some(new_value) if new_value == old_value => None,
+                    new_value => Some(FieldDiff {
+                        field: field.clone(),
+                        old: Some(old_value.clone()),
+                        new: new_value.map(|v| v.to_string()),
+                    }),
+                });
+        let added = new
+            .iter()
+            .filter(|(field, _)| !old_values.contains_key(field.as_str()))
+            .map(|(field, new_value)| FieldDiff {
+                field: field.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+
+        changed_or_removed.chain(added).collect()
+    }
+
+    /// Renders the decoded fields of this section as an aligned table, one field per line.
+    ///
+    /// Nested fields are listed with their full path, with components separated by dots.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    ///
+    /// let section = Section::UspV1("1YNN".parse().unwrap());
+    ///
+    /// assert_eq!(
+    ///     section.pretty(),
+    ///     "opt_out_notice           Yes\n\
+    ///      opt_out_sale             No\n\
+    ///      lspa_covered_transaction No\n"
+    /// );
+    /// ```
+    pub fn pretty(&self) -> String {
+        let fields = self.fields();
+        let width = fields.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+
+        fields
+            .iter()
+            .map(|(k, v)| format!("{k:width$} {v}\n"))
+            .collect()
+    }
+
+    fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = vec![];
+        self.push_fields("", &mut fields);
+        fields
+    }
+
+    /// Decodes a section string, and tells which bits hold each of its fields.
+    ///
+    /// Fields are listed in the order in which they are encoded, segment by segment, with the
+    /// names used by [`pretty`](Self::pretty) and [`diff`](Self::diff). Unlike
+    /// [`SectionId::schema`], each field comes with its actual location and width, which also
+    /// covers variable-length fields, along with its value.
+    ///
+    /// Names are the ones of the schema, except for core segments which exist in several
+    /// versions. The schema lists the fields of each version under a `vN` component, such as
+    /// `core.v1.sale_opt_out_notice`, while a decoded section holds a single version, whose
+    /// fields are named without it, such as `core.sale_opt_out_notice`.
+    ///
+    /// This is meant for debugging sections which decode to unexpected values, or for learning
+    /// the wire format of a section.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the section cannot be decoded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{Section, SectionId};
+    ///
+    /// let spans = Section::explain(SectionId::UsCa, "BVqqqqpY.YA").unwrap();
+    ///
+    /// assert_eq!(spans[1].name, "core.sale_opt_out_notice");
+    /// assert_eq!((spans[1].segment, spans[1].start, spans[1].len), (0, 6, 2));
+    /// assert_eq!(spans[1].bits, "01");
+    /// assert_eq!(spans[1].value.as_deref(), Some("Provided"));
+    ///
+    /// let gpc = spans.last().unwrap();
+    /// assert_eq!(gpc.name, "gpc");
+    /// assert_eq!((gpc.segment, gpc.start, gpc.len), (1, 2, 1));
+    /// ```
+    pub fn explain(id: SectionId, s: &str) -> Result<Vec<FieldSpan>, SectionDecodeError> {
+        let section = decode_section(id, s, &mut Vec::new(), DecodeOptions::default())?;
+        let mut spans = match id {
+            SectionId::TcfEuV1 => TcfEuV1::read_spans(s)?,
+            SectionId::TcfEuV2 => TcfEuV2::read_spans(s)?,
+            SectionId::TcfCaV1 => TcfCaV1::read_spans(s)?,
+            SectionId::UspV1 => UspV1::read_spans(s)?,
+            SectionId::UsNat => UsNat::read_spans(s)?,
+            SectionId::UsCa => UsCa::read_spans(s)?,
+            SectionId::UsVa => UsVa::read_spans(s)?,
+            SectionId::UsCo => UsCo::read_spans(s)?,
+            SectionId::UsUt => UsUt::read_spans(s)?,
+            SectionId::UsCt => UsCt::read_spans(s)?,
+            SectionId::UsFl => UsFl::read_spans(s)?,
+            SectionId::UsMt => UsMt::read_spans(s)?,
+            SectionId::UsOr => UsOr::read_spans(s)?,
+            SectionId::UsTx => UsTx::read_spans(s)?,
+            SectionId::UsDe => UsDe::read_spans(s)?,
+            SectionId::UsIa => UsIa::read_spans(s)?,
+            SectionId::UsNe => UsNe::read_spans(s)?,
+            SectionId::UsNh => UsNh::read_spans(s)?,
+            SectionId::UsNj => UsNj::read_spans(s)?,
+            SectionId::UsTn => UsTn::read_spans(s)?,
+            id => return Err(SectionDecodeError::UnsupportedSectionId(id)),
+        };
+
+        // fields holding other fields are only kept if they have a value of their own
+        let values = section.fields().into_iter().collect::<FnvHashMap<_, _>>();
+        let is_parent = |span: &FieldSpan| {
+            spans.iter().any(|other| {
+                other.segment == span.segment
+                    && other
+                        .name
+                        .strip_prefix(span.name.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        };
+        let keep = spans
+            .iter()
+            .map(|span| values.contains_key(&span.name) || !is_parent(span))
+            .collect::<Vec<_>>();
+        let mut keep = keep.into_iter();
+        spans.retain(|_| keep.next().unwrap_or(false));
+
+        for span in &mut spans {
+            if let Some(value) = values.get(&span.name) {
+                span.value = Some(value.clone());
+            } else if span.value.is_none() && span.len <= 64 {
+                span.value = u64::from_str_radix(&span.bits, 2)
+                    .ok()
+                    .map(|v| v.to_string());
+            }
+        }
+        spans.sort_by_key(|span| (span.segment, span.start));
+
+        Ok(spans)
+    }
+}
+
+/// A decoded section, usable as a trait object.
+///
+/// The [`Section`] enum lists every supported section, which requires a `match` to get to the
+/// section itself. This trait exposes the operations common to all sections instead, so that
+/// sections of different types can be held together, for example as
+/// `Vec<Box<dyn DecodedSection>>`. It is implemented by every section type, and by [`Section`].
+///
+/// The trait requires [`Send`] and [`Sync`], so that trait objects can be shared across threads.
+/// The concrete type of a section can be recovered with [`as_any`](Self::as_any).
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::DecodedSection;
+/// use iab_gpp::sections::SectionId;
+/// use iab_gpp::sections::usca::UsCa;
+/// use iab_gpp::sections::uspv1::UspV1;
+///
+/// let sections: Vec<Box<dyn DecodedSection>> = vec![
+///     Box::new("BVqqqqpY.YA".parse::<UsCa>().unwrap()),
+///     Box::new("1YNN".parse::<UspV1>().unwrap()),
+/// ];
+///
+/// assert_eq!(sections[0].id(), SectionId::UsCa);
+/// assert_eq!(sections[1].encode_section().unwrap(), "1YNN");
+/// assert!(sections[0].as_any().downcast_ref::<UsCa>().is_some());
+/// ```
+pub trait DecodedSection: Debug + Send + Sync + 'static {
+    /// Returns the identifier of this section.
+    fn id(&self) -> SectionId;
+
+    /// Checks the internal consistency of this section.
+    ///
+    /// See [`Section::validate`].
+    fn validate(&self) -> Vec<us_common::ValidationError>;
+
+    /// Encodes this section into a string which can be embedded in a GPP string.
+    ///
+    /// This is [`EncodableSection::encode`], under a name which does not clash with it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionEncodeError`] if a field value cannot be represented in the encoded
+    /// section.
+    fn encode_section(&self) -> Result<String, SectionEncodeError>;
+
+    /// Serializes this section into a JSON value.
+    ///
+    /// Section types are serialized as an object of their fields. [`Section`] is serialized
+    /// as described in [`Section::to_json`].
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error>;
+
+    /// Returns this section as [`Any`](std::any::Any), to downcast it to its concrete type.
+    fn as_any(&self) -> &dyn std::any::Any;
+}
+
+/// Implements [`DecodedSection`] for section types, given the expression validating them.
+macro_rules! impl_decoded_section {
+    ($($t:ty => |$s:ident| $validate:expr),+ $(,)?) => {
+        $(impl DecodedSection for $t {
+            fn id(&self) -> SectionId {
+                <Self as DecodableSection>::ID
+            }
+
+            fn validate(&self) -> Vec<us_common::ValidationError> {
+                let $s = self;
+                $validate
+            }
+
+            fn encode_section(&self) -> Result<String, SectionEncodeError> {
+                EncodableSection::encode(self)
+            }
+
+            #[cfg(feature = "serde")]
+            fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+                serde_json::to_value(self)
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+        })+
+    };
+}
+
+impl_decoded_section!(
+    TcfEuV1 => |_s| vec![],
+    TcfEuV2 => |_s| vec![],
+    TcfCaV1 => |_s| vec![],
+    UspV1 => |_s| vec![],
+    UsNat => |s| match &s.core {
+        usnat::Core::V1(core) => core.validate(),
+        usnat::Core::V2(core) => core.validate(),
+    },
+    UsCa => |s| match &s.core {
+        usca::Core::V1(core) => core.validate(),
+    },
+    UsVa => |s| s.core.validate(),
+    UsCo => |s| s.core.validate(),
+    UsUt => |s| s.core.validate(),
+    UsCt => |s| s.core.validate(),
+    UsFl => |s| s.core.validate(),
+    UsMt => |s| s.core.validate(),
+    UsOr => |s| s.core.validate(),
+    UsTx => |s| s.core.validate(),
+    UsDe => |s| s.core.validate(),
+    UsIa => |s| s.core.validate(),
+    UsNe => |s| s.core.validate(),
+    UsNh => |s| s.core.validate(),
+    UsNj => |s| s.core.validate(),
+    UsTn => |s| s.core.validate(),
+);
+
+impl DecodedSection for Section {
+    fn id(&self) -> SectionId {
+        Section::id(self)
+    }
+
+    fn validate(&self) -> Vec<us_common::ValidationError> {
+        Section::validate(self)
+    }
+
+    fn encode_section(&self) -> Result<String, SectionEncodeError> {
+        Section::encode(self)
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        Section::to_json(self)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+impl Fields for Section {
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        match self {
+            Section::TcfEuV1(s) => s.push_fields(prefix, out),
+            Section::TcfEuV2(s) => s.push_fields(prefix, out),
+            Section::TcfCaV1(s) => s.push_fields(prefix, out),
+            Section::UspV1(s) => s.push_fields(prefix, out),
+            Section::UsNat(s) => s.push_fields(prefix, out),
+            Section::UsCa(s) => s.push_fields(prefix, out),
+            Section::UsVa(s) => s.push_fields(prefix, out),
+            Section::UsCo(s) => s.push_fields(prefix, out),
+            Section::UsUt(s) => s.push_fields(prefix, out),
+            Section::UsCt(s) => s.push_fields(prefix, out),
+            Section::UsFl(s) => s.push_fields(prefix, out),
+            Section::UsMt(s) => s.push_fields(prefix, out),
+            Section::UsOr(s) => s.push_fields(prefix, out),
+            Section::UsTx(s) => s.push_fields(prefix, out),
+            Section::UsDe(s) => s.push_fields(prefix, out),
+            Section::UsIa(s) => s.push_fields(prefix, out),
+            Section::UsNe(s) => s.push_fields(prefix, out),
+            Section::UsNh(s) => s.push_fields(prefix, out),
+            Section::UsNj(s) => s.push_fields(prefix, out),
+            Section::UsTn(s) => s.push_fields(prefix, out),
+        }
+    }
+}
+
+/// A difference between the values of a field in two decoded sections.
+///
+/// Created with the method [`diff`](Section::diff).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FieldDiff {
+    /// Path of the field within the section, with nested fields separated by dots.
+    pub field: String,
+    /// Value of the field in the original section, if present.
+    pub old: Option<String>,
+    /// Value of the field in the other section, if present.
+    pub new: Option<String>,
+}
+
+/// A trait listing the decoded values of a type as a flat list of `(path, value)` pairs.
+///
+/// It is automatically implemented by the `FromDataReader` and `GPPSection` derive macros.
+pub(crate) trait Fields {
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>);
+}
+
+pub(crate) fn field_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+/// Implements [`Fields`] for leaf values using their debug representation.
+macro_rules! impl_fields_with_debug {
+    ($($t:ty),+) => {
+        $(impl Fields for $t {
+            fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+                out.push((prefix.to_string(), format!("{self:?}")));
+            }
+        })+
+    };
+}
+
+impl_fields_with_debug!(
+    bool,
+    u8,
+    u16,
+    i64,
+    String,
+    IdSet,
+    us_common::Notice,
+    us_common::OptOut,
+    us_common::Consent,
+    us_common::MspaMode,
+    uspv1::Notice,
+    uspv1::OptOut,
+    uspv1::Covered
+);
+
+impl<T> Fields for Vec<T>
+where
+    T: Debug,
+{
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        out.push((prefix.to_string(), format!("{self:?}")));
+    }
+}
+
+impl<T> Fields for Option<T>
+where
+    T: Fields,
+{
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        match self {
+            Some(v) => v.push_fields(prefix, out),
+            None => out.push((prefix.to_string(), "None".to_string())),
+        }
+    }
+}
+
+/// The description of a field in the wire format of a section.
+///
+/// Created with the method [`schema`](SectionId::schema).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct FieldSchema {
+    /// Path of the field within the section, with nested fields separated by dots.
+    pub name: String,
+    /// Rust type of the decoded value.
+    pub rust_type: String,
+    /// Encoding of the field, named after the [`DataReader`] method
+    /// reading it, along with its arguments, such as `fixed_bitfield(24)`.
+    ///
+    /// Fields with a custom encoding are named after the field instead.
+    pub encoding: String,
+    /// Width of the field in bits, or `None` if it depends on the field value.
+    pub bits: Option<u32>,
+}
+
+impl FieldSchema {
+    pub(crate) fn new(name: &str, rust_type: &str, encoding: &str, bits: Option<u32>) -> Self {
+        Self {
+            name: name.to_string(),
+            rust_type: rust_type.to_string(),
+            encoding: encoding.to_string(),
+            bits,
+        }
+    }
+}
+
+/// A trait describing the wire format of a type as a flat list of [`FieldSchema`]s.
+///
+/// It is automatically implemented by the `FromDataReader` and `GPPSection` derive macros.
+pub(crate) trait Schema {
+    fn push_schema(prefix: &str, out: &mut Vec<FieldSchema>);
 }
 
-pub(crate) fn decode_section(id: SectionId, s: &str) -> Result<Section, SectionDecodeError> {
+/// Implements [`Schema`] for leaf values read with a single fixed-size encoding.
+macro_rules! impl_schema_leaf {
+    ($($t:ty => $encoding:literal, $bits:literal),+ $(,)?) => {
+        $(impl crate::sections::Schema for $t {
+            fn push_schema(prefix: &str, out: &mut Vec<crate::sections::FieldSchema>) {
+                out.push(crate::sections::FieldSchema::new(
+                    prefix,
+                    stringify!($t),
+                    $encoding,
+                    Some($bits),
+                ));
+            }
+        })+
+    };
+}
+
+pub(crate) use impl_schema_leaf;
+
+impl_schema_leaf!(
+    bool => "bool", 1,
+    u8 => "fixed_integer(6)", 6,
+    u16 => "fixed_integer(12)", 12,
+    Timestamp => "datetime_deciseconds", 36,
+);
+
+impl<T> Schema for Option<T>
+where
+    T: Schema,
+{
+    fn push_schema(prefix: &str, out: &mut Vec<FieldSchema>) {
+        T::push_schema(prefix, out);
+    }
+}
+
+/// The location of a field in an encoded section, along with its value.
+///
+/// Created with the method [`Section::explain`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct FieldSpan {
+    /// Path of the field within the section, with nested fields separated by dots.
+    pub name: String,
+    /// Index of the segment holding the field, starting with 0 for the core segment.
+    pub segment: usize,
+    /// Offset of the first bit of the field within its segment.
+    pub start: u64,
+    /// Width of the field in bits.
+    pub len: u64,
+    /// Bits of the field, as a string of `0` and `1` characters.
+    pub bits: String,
+    /// Decoded value of the field, as listed by [`Section::pretty`].
+    ///
+    /// Fields which are not part of the decoded section, such as segment versions and types,
+    /// hold the unsigned integer value of their bits, or `None` if it does not fit in 64 bits.
+    pub value: Option<String>,
+}
+
+impl FieldSpan {
+    pub(crate) fn new(
+        name: String,
+        segment: usize,
+        range: std::ops::Range<u64>,
+        bytes: &[u8],
+    ) -> Self {
+        let bits = range
+            .clone()
+            .map(|i| match bytes[(i / 8) as usize] >> (7 - i % 8) & 1 {
+                0 => '0',
+                _ => '1',
+            })
+            .collect();
+        Self {
+            name,
+            segment,
+            start: range.start,
+            len: range.end - range.start,
+            bits,
+            value: None,
+        }
+    }
+}
+
+/// A section whose fields can be located in its encoded form, for [`Section::explain`].
+///
+/// It is automatically implemented by the `GPPSection` derive macro.
+pub(crate) trait ExplainSection {
+    /// Returns the location of every field read from the given section string, including
+    /// fields holding other fields. Values are only set for fields which are not part of the
+    /// decoded section, and are not encoded as integers.
+    fn read_spans(s: &str) -> Result<Vec<FieldSpan>, SectionDecodeError>;
+}
+
+/// Options applied when decoding sections, set from the options a GPP string was parsed with.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct DecodeOptions {
+    pub lenient: bool,
+    pub strict_trailing_data: bool,
+    pub allow_empty_segments: bool,
+    pub strict_enums: bool,
+    pub standard_base64: bool,
+}
+
+/// A section which can be decoded with [`DecodeOptions`].
+pub(crate) trait DecodeWithOptions: Sized {
+    fn decode_with_options(
+        s: &str,
+        buf: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<Self, SectionDecodeError>;
+}
+
+pub(crate) fn decode_section(
+    id: SectionId,
+    s: &str,
+    buf: &mut Vec<u8>,
+    options: DecodeOptions,
+) -> Result<Section, SectionDecodeError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("decode_section", ?id, len = s.len()).entered();
+
+    let section = decode_section_str(id, s, buf, options);
+
+    #[cfg(feature = "tracing")]
+    if let Err(e) = &section {
+        tracing::debug!(error = %e, "section decoding failed");
+    }
+
+    section
+}
+
+fn decode_section_str(
+    id: SectionId,
+    s: &str,
+    buf: &mut Vec<u8>,
+    options: DecodeOptions,
+) -> Result<Section, SectionDecodeError> {
+    if s.is_empty() {
+        return Err(SectionDecodeError::EmptySection(id));
+    }
+
     Ok(match id {
-        SectionId::TcfEuV1 => Section::TcfEuV1(s.parse()?),
-        SectionId::TcfEuV2 => Section::TcfEuV2(s.parse()?),
-        SectionId::TcfCaV1 => Section::TcfCaV1(s.parse()?),
-        SectionId::UspV1 => Section::UspV1(s.parse()?),
-        SectionId::UsNat => Section::UsNat(s.parse()?),
-        SectionId::UsCa => Section::UsCa(s.parse()?),
-        SectionId::UsVa => Section::UsVa(s.parse()?),
-        SectionId::UsCo => Section::UsCo(s.parse()?),
-        SectionId::UsUt => Section::UsUt(s.parse()?),
-        SectionId::UsCt => Section::UsCt(s.parse()?),
-        SectionId::UsFl => Section::UsFl(s.parse()?),
-        SectionId::UsMt => Section::UsMt(s.parse()?),
-        SectionId::UsOr => Section::UsOr(s.parse()?),
-        SectionId::UsTx => Section::UsTx(s.parse()?),
-        SectionId::UsDe => Section::UsDe(s.parse()?),
-        SectionId::UsIa => Section::UsIa(s.parse()?),
-        SectionId::UsNe => Section::UsNe(s.parse()?),
-        SectionId::UsNh => Section::UsNh(s.parse()?),
-        SectionId::UsNj => Section::UsNj(s.parse()?),
-        SectionId::UsTn => Section::UsTn(s.parse()?),
+        SectionId::TcfEuV1 => Section::TcfEuV1(parse_section(s, buf, options)?),
+        SectionId::TcfEuV2 if options.lenient => {
+            Section::TcfEuV2(tcfeuv2::parse_lenient(s, buf, options)?)
+        }
+        SectionId::TcfEuV2 => Section::TcfEuV2(parse_section(s, buf, options)?),
+        SectionId::TcfCaV1 => Section::TcfCaV1(parse_section(s, buf, options)?),
+        SectionId::UspV1 => Section::UspV1(parse_section(s, buf, options)?),
+        SectionId::UsNat => Section::UsNat(parse_section(s, buf, options)?),
+        SectionId::UsCa => Section::UsCa(parse_section(s, buf, options)?),
+        SectionId::UsVa => Section::UsVa(parse_section(s, buf, options)?),
+        SectionId::UsCo => Section::UsCo(parse_section(s, buf, options)?),
+        SectionId::UsUt => Section::UsUt(parse_section(s, buf, options)?),
+        SectionId::UsCt => Section::UsCt(parse_section(s, buf, options)?),
+        SectionId::UsFl => Section::UsFl(parse_section(s, buf, options)?),
+        SectionId::UsMt => Section::UsMt(parse_section(s, buf, options)?),
+        SectionId::UsOr => Section::UsOr(parse_section(s, buf, options)?),
+        SectionId::UsTx => Section::UsTx(parse_section(s, buf, options)?),
+        SectionId::UsDe => Section::UsDe(parse_section(s, buf, options)?),
+        SectionId::UsIa => Section::UsIa(parse_section(s, buf, options)?),
+        SectionId::UsNe => Section::UsNe(parse_section(s, buf, options)?),
+        SectionId::UsNh => Section::UsNh(parse_section(s, buf, options)?),
+        SectionId::UsNj => Section::UsNj(parse_section(s, buf, options)?),
+        SectionId::UsTn => Section::UsTn(parse_section(s, buf, options)?),
         id => Err(SectionDecodeError::UnsupportedSectionId(id))?,
     })
 }
 
+fn parse_section<T: DecodeWithOptions>(
+    s: &str,
+    buf: &mut Vec<u8>,
+    options: DecodeOptions,
+) -> Result<T, SectionDecodeError> {
+    T::decode_with_options(s, buf, options)
+}
+
+// encoders may pad segments up to a whole group of 4 base64 characters
+const MAX_PADDING_BITS: u64 = 24;
+
+/// Checks that a segment was fully read, except for padding bits, which must be zero.
+fn check_trailing_data(
+    r: &mut DataReader,
+    options: DecodeOptions,
+) -> Result<(), SectionDecodeError> {
+    if !options.strict_trailing_data {
+        return Ok(());
+    }
+
+    let bits = r.remaining_bits();
+    if bits >= MAX_PADDING_BITS || (bits > 0 && r.read_fixed_integer::<u32>(bits as u32)? != 0) {
+        return Err(SectionDecodeError::TrailingData { bits });
+    }
+
+    Ok(())
+}
+
 pub(crate) trait Base64EncodedStr<T> {
     fn parse_base64_str(&self) -> Result<T, SectionDecodeError>;
+    fn parse_base64_str_with_options(
+        &self,
+        buf: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<T, SectionDecodeError>;
+    fn read_base64_spans(&self) -> Result<Vec<FieldSpan>, SectionDecodeError>;
 }
 
 impl<T> Base64EncodedStr<T> for str
@@ -223,8 +1655,29 @@ where
     T: FromDataReader<Err = SectionDecodeError>,
 {
     fn parse_base64_str(&self) -> Result<T, SectionDecodeError> {
-        let r = self.decode_base64_url()?;
-        DataReader::new(&r).parse()
+        self.parse_base64_str_with_options(&mut Vec::new(), DecodeOptions::default())
+    }
+
+    fn parse_base64_str_with_options(
+        &self,
+        buf: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<T, SectionDecodeError> {
+        self.decode_base64_into(buf, options.standard_base64)?;
+        let mut r = DataReader::new(buf).with_strict_enums(options.strict_enums);
+        let output = r.parse()?;
+        check_trailing_data(&mut r, options)?;
+        Ok(output)
+    }
+
+    fn read_base64_spans(&self) -> Result<Vec<FieldSpan>, SectionDecodeError> {
+        let buf = self.decode_base64_url()?;
+        let mut r = DataReader::new(&buf).with_spans();
+        r.parse::<T>()?;
+        Ok(r.take_spans()
+            .into_iter()
+            .map(|(name, range)| FieldSpan::new(name, 0, range, &buf))
+            .collect())
     }
 }
 
@@ -235,6 +1688,13 @@ where
 /// This guarantees a given segment cannot appear twice.
 pub(crate) trait SegmentedStr<T> {
     fn parse_segmented_str(&self) -> Result<T, SectionDecodeError>;
+    fn parse_segmented_str_with_options(
+        &self,
+        buf: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<T, SectionDecodeError>;
+    fn parse_core_segment(&self) -> Result<(T, BTreeSet<u8>), SectionDecodeError>;
+    fn read_segmented_spans(&self) -> Result<Vec<FieldSpan>, SectionDecodeError>;
 }
 
 impl<T> SegmentedStr<T> for str
@@ -242,39 +1702,155 @@ where
     T: OptionalSegmentParser,
 {
     fn parse_segmented_str(&self) -> Result<T, SectionDecodeError> {
+        self.parse_segmented_str_with_options(&mut Vec::new(), DecodeOptions::default())
+    }
+
+    fn parse_segmented_str_with_options(
+        &self,
+        buf: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<T, SectionDecodeError> {
         let mut sections_iter = self.split('.');
 
         // first mandatory section is the core segment
-        let core = sections_iter
+        sections_iter
             .next()
             .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.to_string()))?
-            .decode_base64_url()?;
-        let mut r = DataReader::new(&core);
-        let mut output = r.parse()?;
-        let mut segments = BTreeSet::new();
+            .decode_base64_into(buf, options.standard_base64)?;
+        let mut r = DataReader::new(buf).with_strict_enums(options.strict_enums);
+        let mut output = r
+            .parse()
+            .map_err(|e| match e {
+                // a string starting with an optional segment is missing its core segment,
+                // rather than holding a core segment of an unknown version
+                SectionDecodeError::UnknownSegmentVersion { .. }
+                    if T::read_segment_type(&mut DataReader::new(buf))
+                        .is_ok_and(|t| T::SEGMENT_TYPES.contains(&t)) =>
+                {
+                    SectionDecodeError::MissingCoreSegment
+                }
+                e => e,
+            })
+            .inspect_err(|e| trace_segment_error("core", buf.len(), &r, e))?;
+        check_trailing_data(&mut r, options)?;
+        let mut segments = Vec::new();
 
-        // parse each optional segment and fill the output
-        for s in sections_iter {
-            let b = s.decode_base64_url()?;
-            let mut r = DataReader::new(&b);
+        // parse each optional segment and fill the output, reusing the same buffer
+        for (index, s) in (1..).zip(sections_iter) {
+            if s.is_empty() {
+                if options.allow_empty_segments {
+                    continue;
+                }
+                return Err(SectionDecodeError::EmptySegment { index });
+            }
+            s.decode_base64_into(buf, options.standard_base64)
+                .map_err(|source| SectionDecodeError::DecodeSegment { index, source })?;
+            let b = &*buf;
+            let mut r = DataReader::new(b).with_strict_enums(options.strict_enums);
 
             let segment_type = T::read_segment_type(&mut r)?;
-            T::parse_optional_segment(segment_type, &mut r, &mut output)?;
+            #[cfg(feature = "tracing")]
+            tracing::trace!(segment_type, len = b.len(), "parsing optional segment");
+            T::parse_optional_segment(segment_type, &mut r, &mut output)
+                .inspect_err(|e| trace_segment_error("optional", b.len(), &r, e))?;
+            check_trailing_data(&mut r, options)?;
 
             // already present, duplicate segments is an error
-            if !segments.insert(segment_type) {
+            if segments.contains(&segment_type) {
                 return Err(SectionDecodeError::DuplicateSegmentType { segment_type });
             }
+            segments.push(segment_type);
         }
 
+        output.set_segment_order(&segments);
         Ok(output)
     }
+
+    fn parse_core_segment(&self) -> Result<(T, BTreeSet<u8>), SectionDecodeError> {
+        let mut sections_iter = self.split('.');
+
+        let core = sections_iter
+            .next()
+            .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.to_string()))?
+            .decode_base64_url()?;
+        let output = DataReader::new(&core).parse()?;
+        let mut segments = BTreeSet::new();
+
+        // only decode the first characters of each optional segment, which are enough
+        // to hold the segment type
+        for (index, s) in (1..).zip(sections_iter) {
+            if s.is_empty() {
+                return Err(SectionDecodeError::EmptySegment { index });
+            }
+            let b = s
+                .get(..2)
+                .unwrap_or(s)
+                .decode_base64_url()
+                .map_err(|source| SectionDecodeError::DecodeSegment { index, source })?;
+            let segment_type = T::read_segment_type(&mut DataReader::new(&b))?;
+
+            if !segments.insert(segment_type) {
+                return Err(SectionDecodeError::DuplicateSegmentType { segment_type });
+            }
+        }
+
+        Ok((output, segments))
+    }
+
+    fn read_segmented_spans(&self) -> Result<Vec<FieldSpan>, SectionDecodeError> {
+        let mut spans = Vec::new();
+        let mut output = None;
+
+        // optional segments are parsed into the output of the core segment, as when decoding
+        for (index, s) in self.split('.').enumerate() {
+            if s.is_empty() {
+                continue;
+            }
+            let buf = s
+                .decode_base64_url()
+                .map_err(|source| SectionDecodeError::DecodeSegment { index, source })?;
+            let mut r = DataReader::new(&buf).with_spans();
+            match &mut output {
+                None => output = Some(r.parse::<T>()?),
+                Some(output) => {
+                    let segment_type = T::read_segment_type(&mut r)?;
+                    T::parse_optional_segment(segment_type, &mut r, output)?;
+                }
+            }
+            spans.extend(
+                r.take_spans()
+                    .into_iter()
+                    .map(|(name, range)| FieldSpan::new(name, index, range, &buf)),
+            );
+        }
+
+        Ok(spans)
+    }
+}
+
+/// Records the location of a segment decoding failure.
+#[cfg(feature = "tracing")]
+fn trace_segment_error(segment: &str, len: usize, r: &DataReader, e: &SectionDecodeError) {
+    tracing::debug!(
+        segment,
+        len,
+        position = r.position(),
+        error = %e,
+        "segment decoding failed"
+    );
 }
 
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+fn trace_segment_error(_: &str, _: usize, _: &DataReader, _: &SectionDecodeError) {}
+
 /// A trait representing an operation to parse optional segments for a Base64-URL encoded string
 pub(crate) trait OptionalSegmentParser:
     Sized + FromDataReader<Err = SectionDecodeError>
 {
+    /// The types of the optional segments of the section.
+    const SEGMENT_TYPES: &'static [u8] = &[];
+
     fn read_segment_type(r: &mut DataReader) -> Result<u8, SectionDecodeError> {
         Ok(r.read_fixed_integer(3)?)
     }
@@ -284,4 +1860,244 @@ pub(crate) trait OptionalSegmentParser:
         r: &mut DataReader,
         into: &mut Self,
     ) -> Result<(), SectionDecodeError>;
+
+    /// Records the types of the optional segments, in the order they were read.
+    ///
+    /// This is a no-op, unless the section has a field marked with `#[gpp(segment_order)]`.
+    fn set_segment_order(&mut self, order: &[u8]) {
+        let _ = order;
+    }
+}
+
+/// The types of the optional segments of a decoded section, in the order they were found.
+///
+/// Segments are encoded in this order, so that re-encoding a decoded section gives back the
+/// original string. Segments missing from the list follow, sorted by type.
+///
+/// The order is a detail of the encoding rather than a decoded value: it is ignored when
+/// comparing and hashing sections.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub(crate) struct SegmentOrder(Vec<u8>);
+
+impl SegmentOrder {
+    /// Sorts encoded segments, given along with their type, following this order.
+    pub(crate) fn sort<T>(&self, segments: &mut [(u8, T)]) {
+        segments.sort_by_key(|(segment_type, _)| {
+            self.0
+                .iter()
+                .position(|t| t == segment_type)
+                .unwrap_or(usize::MAX)
+        });
+    }
+}
+
+impl From<&[u8]> for SegmentOrder {
+    fn from(order: &[u8]) -> Self {
+        Self(order.to_vec())
+    }
+}
+
+impl PartialEq for SegmentOrder {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SegmentOrder {}
+
+impl PartialOrd for SegmentOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SegmentOrder {
+    fn cmp(&self, _: &Self) -> std::cmp::Ordering {
+        std::cmp::Ordering::Equal
+    }
+}
+
+impl std::hash::Hash for SegmentOrder {
+    fn hash<H: std::hash::Hasher>(&self, _: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(SectionId::UsVa, "BVVVVWY", "BVVVVWY__" ; "usva")]
+    #[test_case(SectionId::UsCa, "BVqqqqpY.YA", "BVqqqqpY__.YA" ; "usca core")]
+    #[test_case(SectionId::UsCa, "BVqqqqpY.YA", "BVqqqqpY.YA__" ; "usca gpc")]
+    fn forward_compatible_trailing_fields(id: SectionId, s: &str, extended: &str) {
+        let mut buf = Vec::new();
+        let options = DecodeOptions::default();
+        let expected = decode_section(id, s, &mut buf, options).unwrap();
+        assert_eq!(
+            decode_section(id, extended, &mut buf, options).unwrap(),
+            expected
+        );
+
+        let options = DecodeOptions {
+            strict_trailing_data: true,
+            ..options
+        };
+        assert!(matches!(
+            decode_section(id, extended, &mut buf, options),
+            Err(SectionDecodeError::TrailingData { .. })
+        ));
+    }
+
+    #[test_case(SectionId::UsVa, "CVVVVWY" ; "usva")]
+    #[test_case(SectionId::UsCa, "CVqqqqpY.YA" ; "usca")]
+    fn forward_compatible_unknown_version(id: SectionId, s: &str) {
+        assert!(matches!(
+            decode_section(id, s, &mut Vec::new(), DecodeOptions::default()),
+            Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 2 })
+        ));
+    }
+
+    #[test_case(SectionId::UsVa, "BVV*WY" => "unable to decode segment 0: invalid byte 42 at offset 3" ; "single segment")]
+    #[test_case(SectionId::UsCa, "BVq*qqpY.YA" => "unable to decode segment 0: invalid byte 42 at offset 3" ; "core segment")]
+    #[test_case(SectionId::UsCa, "BVqqqqpY.Y*" => "unable to decode segment 1: invalid byte 42 at offset 1" ; "optional segment")]
+    #[test_case(SectionId::TcfEuV2, "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.I/" => "unable to decode segment 1: standard Base64 character '/' at offset 1, expected the URL-safe alphabet" ; "standard alphabet")]
+    fn decode_segment_error_display(id: SectionId, s: &str) -> String {
+        let e = decode_section(id, s, &mut Vec::new(), DecodeOptions::default()).unwrap_err();
+        assert!(matches!(e, SectionDecodeError::DecodeSegment { .. }));
+        e.to_string()
+    }
+
+    #[test_case(SectionId::UsVa, "BVVVVWY" ; "usva")]
+    #[test_case(SectionId::UsCa, "BVqqqqpY.YA" ; "usca")]
+    #[test_case(SectionId::UsNat, "CAAAAAAAAAWA.Q" ; "usnat v2")]
+    #[test_case(SectionId::UspV1, "1YN-" ; "uspv1")]
+    #[test_case(SectionId::TcfEuV2, "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" ; "tcfeuv2 disclosed vendors")]
+    #[test_case(SectionId::TcfCaV1, "BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA" ; "tcfcav1")]
+    fn explain_covers_fields(id: SectionId, s: &str) {
+        let spans = Section::explain(id, s).unwrap();
+
+        // fields follow each other, without gaps or overlaps
+        for w in spans.windows(2) {
+            if w[0].segment == w[1].segment {
+                assert_eq!(w[0].start + w[0].len, w[1].start, "{:?}", w[1]);
+            } else {
+                assert_eq!(w[1].start, 0, "{:?}", w[1]);
+            }
+        }
+
+        // every field of the decoded section is located, except for missing optional segments
+        let section = decode_section(id, s, &mut Vec::new(), DecodeOptions::default()).unwrap();
+        for (name, value) in section.fields().into_iter().filter(|(_, v)| v != "None") {
+            let span = spans.iter().find(|span| span.name == name);
+            assert_eq!(
+                span.and_then(|span| span.value.clone()),
+                Some(value),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn explain_names() {
+        let s = "BVqqqqpY.YA";
+        let spans = Section::explain(SectionId::UsCa, s).unwrap();
+        let section = Section::UsCa(s.parse().unwrap());
+        let schema = SectionId::UsCa.schema().unwrap();
+
+        // explain uses the names of the decoded fields, without the version of the core
+        assert_eq!(spans[1].name, "core.sale_opt_out_notice");
+        assert_eq!(section.fields()[0].0, "core.sale_opt_out_notice");
+        assert_eq!(schema[1].name, "core.v1.sale_opt_out_notice");
+
+        // other names are shared with the schema
+        for span in spans.iter().filter(|span| !span.name.starts_with("core.")) {
+            assert!(schema.iter().any(|f| f.name == span.name), "{}", span.name);
+        }
+        for span in spans.iter().filter(|span| span.name.starts_with("core.")) {
+            let name = span.name.replacen("core.", "core.v1.", 1);
+            assert!(
+                span.name == "core.version" || schema.iter().any(|f| f.name == name),
+                "{}",
+                span.name
+            );
+        }
+    }
+
+    #[test]
+    fn explain_optional_segments() {
+        let s = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.YAAAAAAAAA";
+        let spans = Section::explain(SectionId::TcfEuV2, s).unwrap();
+        let names = spans
+            .iter()
+            .filter(|span| span.segment == 1)
+            .map(|span| span.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "publisher_purposes.segment_type",
+                "publisher_purposes.consents",
+                "publisher_purposes.legitimate_interests",
+                "publisher_purposes.custom_consents.n",
+                "publisher_purposes.custom_consents",
+                "publisher_purposes.custom_legitimate_interests",
+            ]
+        );
+
+        let segment_type = &spans[spans.len() - 6];
+        assert_eq!(segment_type.bits, "011");
+        assert_eq!(segment_type.value.as_deref(), Some("3"));
+    }
+
+    #[test_case(SectionId::UsCa, "" => matches SectionDecodeError::EmptySection(SectionId::UsCa) ; "empty")]
+    #[test_case(SectionId::UsCa, "BVqq*qpY" => matches SectionDecodeError::DecodeSegment { .. } ; "invalid character")]
+    #[test_case(SectionId::GppHeader, "DBABMA" => matches SectionDecodeError::UnsupportedSectionId(SectionId::GppHeader) ; "header")]
+    fn explain_error(id: SectionId, s: &str) -> SectionDecodeError {
+        Section::explain(id, s).unwrap_err()
+    }
+
+    #[test_case(&[] ; "empty")]
+    #[test_case(&[1, 2, 3] ; "first word")]
+    #[test_case(&[0, 63, 64, 127, 128] ; "word boundaries")]
+    #[test_case(&[5, 1000, u16::MAX] ; "sparse")]
+    fn dense_id_set(ids: &[u16]) {
+        let ids = IdSet::from_iter(ids.iter().copied());
+        let dense = DenseIdSet::from(&ids);
+
+        assert_eq!(dense.len(), ids.len());
+        assert_eq!(dense.is_empty(), ids.is_empty());
+        for id in [0, 1, 4, 63, 64, 65, 999, 1000, u16::MAX] {
+            assert_eq!(dense.contains(id), ids.contains(&id), "id {id}");
+        }
+        assert_eq!(IdSet::from(&dense), ids);
+    }
+
+    #[test]
+    fn decoded_section_trait_object() {
+        let sections: Vec<Box<dyn DecodedSection>> = vec![
+            Box::new("BVqqqqpY.YA".parse::<UsCa>().unwrap()),
+            Box::new("1YNN".parse::<UspV1>().unwrap()),
+            Box::new(Section::UsVa("BVVVVWY".parse().unwrap())),
+        ];
+
+        let ids = sections.iter().map(|s| s.id()).collect::<Vec<_>>();
+        assert_eq!(ids, [SectionId::UsCa, SectionId::UspV1, SectionId::UsVa]);
+
+        let encoded = sections
+            .iter()
+            .map(|s| s.encode_section().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(encoded, ["BVqqqqpY.YA", "1YNN", "BVVVVWY"]);
+
+        assert!(sections.iter().all(|s| s.validate().is_empty()));
+        assert!(sections[0].as_any().downcast_ref::<UsCa>().is_some());
+        assert!(sections[2].as_any().downcast_ref::<Section>().is_some());
+    }
+
+    #[test]
+    fn decoded_section_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+        assert_send_sync::<dyn DecodedSection>();
+    }
 }
@@ -1,10 +1,13 @@
-use crate::core::{DataReader, GenericRange};
-use crate::sections::{IdSet, SectionDecodeError};
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use crate::core::{DataReader, DataWriter, GenericRange, ToDataWriter};
+use crate::sections::{IdSet, SectionDecodeError, SectionEncodeError, SegmentOrder, Timestamp};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments)]
 pub struct TcfCaV1 {
@@ -13,16 +16,88 @@ pub struct TcfCaV1 {
     pub disclosed_vendors: Option<IdSet>,
     #[gpp(optional_segment_type = 3)]
     pub publisher_purposes: Option<PublisherPurposes>,
+    #[gpp(segment_order)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    segment_order: SegmentOrder,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl TcfCaV1 {
+    /// Drops the order in which the optional segments were found when decoding this section.
+    ///
+    /// A decoded section is encoded with its optional segments in their original order, so
+    /// that re-encoding it gives back the same string. Once canonicalized, the optional
+    /// segments are encoded in the canonical order, sorted by segment type, whatever the
+    /// string it was decoded from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::EncodableSection;
+    /// use iab_gpp::sections::tcfcav1::TcfCaV1;
+    ///
+    /// let s = "BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAAAA.YAAAAAAAAAA.IAAA";
+    /// let mut tcf = s.parse::<TcfCaV1>().unwrap();
+    /// assert_eq!(tcf.encode().unwrap(), s);
+    ///
+    /// tcf.canonicalize();
+    /// assert_eq!(tcf.encode().unwrap(), "BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAAAA.IAAA.YAAAAAAAAAA");
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.segment_order = SegmentOrder::default();
+    }
+
+    /// Returns whether express consent was given for the given purpose.
+    pub fn has_purpose_express_consent(&self, purpose_id: u8) -> bool {
+        self.core
+            .purpose_express_consents
+            .contains(&(purpose_id as u16))
+    }
+
+    /// Returns whether implied consent was given for the given purpose.
+    pub fn has_purpose_implied_consent(&self, purpose_id: u8) -> bool {
+        self.core
+            .purpose_implied_consents
+            .contains(&(purpose_id as u16))
+    }
+
+    /// Returns whether express consent was given to the given vendor.
+    pub fn has_vendor_express_consent(&self, vendor_id: u16) -> bool {
+        self.core.vendor_express_consents.contains(&vendor_id)
+    }
+
+    /// Returns whether implied consent was given to the given vendor.
+    pub fn has_vendor_implied_consent(&self, vendor_id: u16) -> bool {
+        self.core.vendor_implied_consents.contains(&vendor_id)
+    }
+
+    /// Returns whether either express or implied consent was given to the given vendor.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfcav1::TcfCaV1;
+    ///
+    /// let mut tcf: TcfCaV1 = "BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA".parse().unwrap();
+    /// tcf.core.vendor_implied_consents = [12].into();
+    ///
+    /// assert!(tcf.vendor_has_any_consent(12));
+    /// assert!(!tcf.has_vendor_express_consent(12));
+    /// assert!(!tcf.vendor_has_any_consent(13));
+    /// ```
+    pub fn vendor_has_any_consent(&self, vendor_id: u16) -> bool {
+        self.has_vendor_express_consent(vendor_id) || self.has_vendor_implied_consent(vendor_id)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
-    #[gpp(datetime_as_unix_timestamp)]
-    pub created: i64,
-    #[gpp(datetime_as_unix_timestamp)]
-    pub last_updated: i64,
+    pub created: Timestamp,
+    pub last_updated: Timestamp,
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
@@ -42,7 +117,10 @@ pub struct Core {
     #[gpp(optimized_range)]
     pub vendor_implied_consents: IdSet,
     /// Introduced in TCF CA v1.1
-    #[gpp(parse_with = parse_publisher_restrictions)]
+    #[gpp(
+        parse_with = parse_publisher_restrictions,
+        write_with = write_publisher_restrictions
+    )]
     pub pub_restrictions: Vec<PublisherRestriction>,
 }
 
@@ -61,7 +139,25 @@ fn parse_publisher_restrictions(
         .collect())
 }
 
-#[derive(Debug, Eq, PartialEq)]
+fn write_publisher_restrictions(
+    w: &mut DataWriter,
+    restrictions: &[PublisherRestriction],
+) -> Result<(), SectionEncodeError> {
+    let ranges = restrictions
+        .iter()
+        .map(|r| GenericRange {
+            key: r.purpose_id,
+            range_type: r.restriction_type as u8,
+            ids: r.restricted_vendor_ids.clone(),
+        })
+        .collect::<Vec<_>>();
+    w.write_n_array_of_ranges(6, 2, &ranges)
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PublisherRestriction {
     pub purpose_id: u8,
     pub restriction_type: RestrictionType,
@@ -79,7 +175,9 @@ impl From<GenericRange<u8, u8>> for PublisherRestriction {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum RestrictionType {
     NotAllowed = 0,
     RequireExpressConsent = 1,
@@ -87,7 +185,10 @@ pub enum RestrictionType {
     Undefined = 3,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct PublisherPurposes {
     #[gpp(fixed_bitfield(24))]
@@ -100,19 +201,73 @@ pub struct PublisherPurposes {
     pub custom_purpose_implied_consents: IdSet,
 }
 
+impl ToDataWriter for PublisherPurposes {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_bitfield(24, &self.purpose_express_consents)?;
+        w.write_fixed_bitfield(24, &self.purpose_implied_consents)?;
+
+        // the number of custom purposes is not kept when decoding, use the highest
+        // custom purpose instead
+        let n = self
+            .custom_purpose_express_consents
+            .last()
+            .max(self.custom_purpose_implied_consents.last())
+            .copied()
+            .unwrap_or(0);
+        w.write_fixed_integer(6, n)?;
+        w.write_fixed_bitfield(n as usize, &self.custom_purpose_express_consents)?;
+        w.write_fixed_bitfield(n as usize, &self.custom_purpose_implied_consents)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::EncodableSection;
     use std::str::FromStr;
     use test_case::test_case;
 
+    fn consents() -> TcfCaV1 {
+        let mut tcf = TcfCaV1::from_str("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA").unwrap();
+        tcf.core.purpose_express_consents = [1, 2].into();
+        tcf.core.purpose_implied_consents = [2, 3].into();
+        tcf.core.vendor_express_consents = [10, 20].into();
+        tcf.core.vendor_implied_consents = [20, 30].into();
+        tcf
+    }
+
+    #[test_case(1 => (true, false) ; "express only")]
+    #[test_case(2 => (true, true) ; "both")]
+    #[test_case(3 => (false, true) ; "implied only")]
+    #[test_case(4 => (false, false) ; "none")]
+    fn purpose_consent(purpose_id: u8) -> (bool, bool) {
+        let tcf = consents();
+        (
+            tcf.has_purpose_express_consent(purpose_id),
+            tcf.has_purpose_implied_consent(purpose_id),
+        )
+    }
+
+    #[test_case(10 => (true, false, true) ; "express only")]
+    #[test_case(20 => (true, true, true) ; "both")]
+    #[test_case(30 => (false, true, true) ; "implied only")]
+    #[test_case(40 => (false, false, false) ; "none")]
+    fn vendor_consent(vendor_id: u16) -> (bool, bool, bool) {
+        let tcf = consents();
+        (
+            tcf.has_vendor_express_consent(vendor_id),
+            tcf.has_vendor_implied_consent(vendor_id),
+            tcf.vendor_has_any_consent(vendor_id),
+        )
+    }
+
     #[test]
     fn basic() {
         let actual = TcfCaV1::from_str("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA").unwrap();
         let expected = TcfCaV1 {
             core: Core {
-                created: 1650412800,
-                last_updated: 1650412800,
+                created: Timestamp::from_seconds(1650412800),
+                last_updated: Timestamp::from_seconds(1650412800),
                 cmp_id: 31,
                 cmp_version: 640,
                 consent_screen: 1,
@@ -129,6 +284,7 @@ mod tests {
             },
             disclosed_vendors: None,
             publisher_purposes: None,
+            segment_order: SegmentOrder::default(),
         };
 
         assert_eq!(actual, expected);
@@ -140,8 +296,8 @@ mod tests {
             TcfCaV1::from_str("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA").unwrap();
         let expected = TcfCaV1 {
             core: Core {
-                created: 1650412800,
-                last_updated: 1650412800,
+                created: Timestamp::from_seconds(1650412800),
+                last_updated: Timestamp::from_seconds(1650412800),
                 cmp_id: 31,
                 cmp_version: 640,
                 consent_screen: 1,
@@ -163,11 +319,21 @@ mod tests {
                 custom_purpose_express_consents: Default::default(),
                 custom_purpose_implied_consents: Default::default(),
             }),
+            segment_order: SegmentOrder::default(),
         };
 
         assert_eq!(actual, expected);
     }
 
+    #[test_case("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA" ; "core only")]
+    #[test_case("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA" ; "with publisher purposes")]
+    fn encode(s: &str) {
+        let expected = TcfCaV1::from_str(s).unwrap();
+        let actual = TcfCaV1::from_str(&expected.encode().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
     #[test_case("BPX" => matches SectionDecodeError::Read(_) ; "decode error")]
     #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
     fn error(s: &str) -> SectionDecodeError {
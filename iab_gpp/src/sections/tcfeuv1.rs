@@ -1,16 +1,17 @@
-use crate::core::DataReader;
-use crate::sections::{IdSet, SectionDecodeError};
+use crate::core::{DataReader, DataWriter};
+use crate::sections::{IdSet, SectionDecodeError, SectionEncodeError, Timestamp};
 use iab_gpp_derive::GPPSection;
 use std::collections::BTreeSet;
 
 // See https://github.com/InteractiveAdvertisingBureau/GDPR-Transparency-and-Consent-Framework/blob/master/Consent%20string%20and%20vendor%20list%20formats%20v1.1%20Final.md
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[gpp(section_version = 1)]
 pub struct TcfEuV1 {
-    #[gpp(datetime_as_unix_timestamp)]
-    pub created: i64,
-    #[gpp(datetime_as_unix_timestamp)]
-    pub last_updated: i64,
+    pub created: Timestamp,
+    pub last_updated: Timestamp,
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
@@ -19,7 +20,7 @@ pub struct TcfEuV1 {
     pub vendor_list_version: u16,
     #[gpp(fixed_bitfield(24))]
     pub purposes_allowed: IdSet,
-    #[gpp(parse_with = parse_vendor_consents)]
+    #[gpp(parse_with = parse_vendor_consents, write_with = write_vendor_consents)]
     pub vendor_consents: IdSet,
 }
 
@@ -45,9 +46,38 @@ fn parse_vendor_consents(r: &mut DataReader) -> Result<IdSet, SectionDecodeError
     })
 }
 
+fn write_vendor_consents(w: &mut DataWriter, ids: &IdSet) -> Result<(), SectionEncodeError> {
+    let max_vendor_id = ids.last().copied().unwrap_or(0);
+    w.write_fixed_integer(16, max_vendor_id)?;
+
+    // a range lists either the vendors who consent, or those who don't when
+    // consent is given by default, pick whichever is shorter
+    let others = (1..=max_vendor_id)
+        .filter(|id| !ids.contains(id))
+        .collect::<IdSet>();
+    let (default_consent, range) = if others.len() < ids.len() {
+        (true, &others)
+    } else {
+        (false, ids)
+    };
+
+    // use the range only if shorter than the bitfield
+    let mut range_writer = DataWriter::new();
+    range_writer.write_integer_range(range)?;
+    if range_writer.position() + 1 < max_vendor_id as u64 {
+        w.write_bool(true)?;
+        w.write_bool(default_consent)?;
+        w.write_integer_range(range)
+    } else {
+        w.write_bool(false)?;
+        w.write_fixed_bitfield(max_vendor_id as usize, ids)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::EncodableSection;
     use std::str::FromStr;
     use test_case::test_case;
 
@@ -55,8 +85,8 @@ mod tests {
     fn success() {
         let actual = TcfEuV1::from_str("BOEFEAyOEFEAyAHABDENAI4AAAB9vABAASA").unwrap();
         let expected = TcfEuV1 {
-            created: 1510082155,
-            last_updated: 1510082155,
+            created: Timestamp::from_deciseconds(15100821554),
+            last_updated: Timestamp::from_deciseconds(15100821554),
             cmp_id: 7,
             cmp_version: 1,
             consent_screen: 3,
@@ -69,6 +99,12 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn encode() {
+        let s = "BOEFEAyOEFEAyAHABDENAI4AAAB9vABAASA";
+        assert_eq!(TcfEuV1::from_str(s).unwrap().encode().unwrap(), s);
+    }
+
     #[test_case("BO5a1L7O5a1L7AAABBENC2-AAAAtH" => matches SectionDecodeError::Read(_) ; "missing data")]
     #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
     #[test_case("DOEFEAyOEFEAyAHABDENAI4AAAB9vABAASA" => matches SectionDecodeError::UnknownSegmentVersion { segment_version: 3 } ; "unknown segment version")]
@@ -1,10 +1,17 @@
-use crate::core::{DataReader, Range};
-use crate::sections::{IdSet, SectionDecodeError};
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use crate::core::{base64, DataReader, DataWriter, DecodeExt, FromDataReader, Range, ToDataWriter};
+use crate::sections::{
+    field_path, DecodeOptions, DecodeWithOptions, FieldSchema, Fields, IdSet, Schema,
+    SectionDecodeError, SectionEncodeError, SegmentOrder, Timestamp,
+};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use std::io;
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments)]
 pub struct TcfEuV2 {
@@ -13,22 +20,32 @@ pub struct TcfEuV2 {
     pub disclosed_vendors: Option<IdSet>,
     #[gpp(optional_segment_type = 3)]
     pub publisher_purposes: Option<PublisherPurposes>,
+    #[gpp(segment_order)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    segment_order: SegmentOrder,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 2)]
 pub struct Core {
-    #[gpp(datetime_as_unix_timestamp)]
-    pub created: i64,
-    #[gpp(datetime_as_unix_timestamp)]
-    pub last_updated: i64,
+    pub created: Timestamp,
+    pub last_updated: Timestamp,
     pub cmp_id: u16,
     pub cmp_version: u16,
+    /// The CMP specific identifier of the screen on which consent was given.
+    ///
+    /// Its meaning is defined by each CMP, and cannot be interpreted without knowing it.
     pub consent_screen: u8,
     #[gpp(string(2))]
     pub consent_language: String,
     pub vendor_list_version: u16,
+    /// The version of the TCF policies the string was created under.
+    ///
+    /// Use [`Core::policy_version`] to get a validated [`PolicyVersion`].
     pub policy_version: u8,
     pub is_service_specific: bool,
     pub use_non_standard_stacks: bool,
@@ -45,10 +62,186 @@ pub struct Core {
     pub vendor_consents: IdSet,
     #[gpp(optimized_integer_range)]
     pub vendor_legitimate_interests: IdSet,
-    #[gpp(parse_with = parse_publisher_restrictions)]
+    #[gpp(
+        parse_with = parse_publisher_restrictions,
+        write_with = write_publisher_restrictions
+    )]
     pub publisher_restrictions: Vec<PublisherRestriction>,
 }
 
+impl TcfEuV2 {
+    /// Drops the order in which the optional segments were found when decoding this section.
+    ///
+    /// A decoded section is encoded with its optional segments in their original order, so
+    /// that re-encoding it gives back the same string. Once canonicalized, the optional
+    /// segments are encoded in the canonical order, sorted by segment type, whatever the
+    /// string it was decoded from.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::EncodableSection;
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let s = "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.IAAA";
+    /// let mut tcf = s.parse::<TcfEuV2>().unwrap();
+    /// assert_eq!(tcf.encode().unwrap(), s);
+    ///
+    /// tcf.canonicalize();
+    /// assert_eq!(tcf.encode().unwrap(), "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.IAAA.ZAAgH9794ulA");
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.segment_order = SegmentOrder::default();
+    }
+
+    /// Returns the legal basis under which the given purpose is established, if any.
+    ///
+    /// Consent takes precedence over legitimate interest when both are set for the purpose.
+    ///
+    /// Purpose 1 (store and/or access information on a device) can only be established by
+    /// consent, so its legitimate interest bit is ignored. Note that when
+    /// [`purpose_one_treatment`](Core::purpose_one_treatment) is set, purpose 1 was not
+    /// disclosed to the user, and its consent bit is expected to be unset. This method does not
+    /// interpret that flag: callers must apply the rules relevant to the publisher's country.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::{LegalBasis, TcfEuV2};
+    ///
+    /// let tcf: TcfEuV2 = "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA".parse().unwrap();
+    ///
+    /// assert_eq!(tcf.purpose_basis(1), Some(LegalBasis::Consent));
+    /// assert_eq!(tcf.purpose_basis(4), None);
+    /// ```
+    pub fn purpose_basis(&self, purpose_id: u8) -> Option<LegalBasis> {
+        let id = purpose_id as u16;
+        if self.core.purpose_consents.contains(&id) {
+            Some(LegalBasis::Consent)
+        } else if purpose_id != 1 && self.core.purpose_legitimate_interests.contains(&id) {
+            Some(LegalBasis::LegitimateInterest)
+        } else {
+            None
+        }
+    }
+}
+
+impl Core {
+    /// Returns the version of the TCF policies the string was created under.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let tcf: TcfEuV2 = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA".parse().unwrap();
+    /// assert_eq!(tcf.core.policy_version().unwrap().get(), 2);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SectionDecodeError::InvalidFieldValue`] if the version is outside of the
+    /// range of supported TCF policy versions.
+    pub fn policy_version(&self) -> Result<PolicyVersion, SectionDecodeError> {
+        PolicyVersion::try_from(self.policy_version)
+    }
+
+    /// Sorts publisher restrictions and merges the ones which target the same purpose with the
+    /// same restriction type.
+    ///
+    /// Restrictions are ordered by purpose ID, then by restriction type. The vendor IDs of
+    /// merged restrictions are combined. After normalization, sections with the same
+    /// restrictions listed in different orders compare equal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::{PublisherRestriction, RestrictionType, TcfEuV2};
+    ///
+    /// let mut tcf: TcfEuV2 = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA".parse().unwrap();
+    /// tcf.core.publisher_restrictions = vec![
+    ///     PublisherRestriction {
+    ///         purpose_id: 2,
+    ///         restriction_type: RestrictionType::NotAllowed,
+    ///         restricted_vendor_ids: [1, 2].into(),
+    ///     },
+    ///     PublisherRestriction {
+    ///         purpose_id: 1,
+    ///         restriction_type: RestrictionType::RequireConsent,
+    ///         restricted_vendor_ids: [3].into(),
+    ///     },
+    ///     PublisherRestriction {
+    ///         purpose_id: 2,
+    ///         restriction_type: RestrictionType::NotAllowed,
+    ///         restricted_vendor_ids: [5].into(),
+    ///     },
+    /// ];
+    /// tcf.core.normalize_publisher_restrictions();
+    ///
+    /// assert_eq!(tcf.core.publisher_restrictions.len(), 2);
+    /// assert_eq!(tcf.core.publisher_restrictions[0].purpose_id, 1);
+    /// assert_eq!(
+    ///     tcf.core.publisher_restrictions[1].restricted_vendor_ids,
+    ///     [1, 2, 5].into()
+    /// );
+    /// ```
+    pub fn normalize_publisher_restrictions(&mut self) {
+        self.publisher_restrictions.sort();
+        self.publisher_restrictions.dedup_by(|next, prev| {
+            let same_target = next.purpose_id == prev.purpose_id
+                && next.restriction_type == prev.restriction_type;
+            if same_target {
+                prev.restricted_vendor_ids
+                    .append(&mut next.restricted_vendor_ids);
+            }
+            same_target
+        });
+    }
+}
+
+/// The legal basis under which a TCF purpose is established.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum LegalBasis {
+    Consent,
+    LegitimateInterest,
+}
+
+/// A version of the TCF policies, within the range of versions defined for TCF v2.
+///
+/// Version 2 corresponds to TCF v2.0, and version 4 to TCF v2.2.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PolicyVersion(u8);
+
+impl PolicyVersion {
+    /// The first policy version of TCF v2.
+    pub const MIN: u8 = 2;
+    /// The latest policy version known to this crate.
+    pub const MAX: u8 = 5;
+
+    /// Returns the version number.
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for PolicyVersion {
+    type Error = SectionDecodeError;
+
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        if (Self::MIN..=Self::MAX).contains(&version) {
+            Ok(Self(version))
+        } else {
+            Err(SectionDecodeError::InvalidFieldValue {
+                expected: format!("a policy version from {} to {}", Self::MIN, Self::MAX),
+                found: version.to_string(),
+            })
+        }
+    }
+}
+
 fn parse_publisher_restrictions(
     r: &mut DataReader,
 ) -> Result<Vec<PublisherRestriction>, SectionDecodeError> {
@@ -58,7 +251,74 @@ fn parse_publisher_restrictions(
         .collect())
 }
 
-#[derive(Debug, Eq, PartialEq)]
+// number of bits of the core segment up to and including the purpose one treatment flag,
+// which must all be present for a truncated segment to be decoded leniently
+const LENIENT_MIN_CORE_BITS: usize = 201;
+
+// enough zero bytes to complete any truncated field, the largest being a range
+// of 4095 entries, and to read all the following fields as empty
+const LENIENT_PADDING_BYTES: usize = 17 * 1024;
+
+/// Parses a TCF EU v2 section, completing a truncated core segment with zero bits.
+///
+/// The core segment must at least contain all fields up to the purpose one treatment flag.
+/// Missing fields are decoded as if they were zero: no vendor consents, no vendor legitimate
+/// interests, no publisher restrictions, and a publisher country code of `AA`.
+///
+/// The trailing data of a completed core segment is never checked, as it is made of padding.
+pub(crate) fn parse_lenient(
+    s: &str,
+    buf: &mut Vec<u8>,
+    options: DecodeOptions,
+) -> Result<TcfEuV2, SectionDecodeError> {
+    let e = match TcfEuV2::decode_with_options(s, buf, options) {
+        Err(SectionDecodeError::Read(e)) if e.kind() == io::ErrorKind::UnexpectedEof => e,
+        r => return r,
+    };
+
+    let (core, segments) = match s.split_once('.') {
+        Some((core, segments)) => (core, Some(segments)),
+        None => (s, None),
+    };
+    if core.len() * 6 < LENIENT_MIN_CORE_BITS {
+        return Err(SectionDecodeError::Read(e));
+    }
+
+    let mut bytes = Vec::new();
+    core.decode_base64_into(&mut bytes, options.standard_base64)?;
+    bytes.resize(bytes.len() + LENIENT_PADDING_BYTES, 0);
+    let mut padded = base64::encode(&bytes);
+    if let Some(segments) = segments {
+        padded.push('.');
+        padded.push_str(segments);
+    }
+
+    let options = DecodeOptions {
+        strict_trailing_data: false,
+        ..options
+    };
+    TcfEuV2::decode_with_options(&padded, buf, options)
+}
+
+fn write_publisher_restrictions(
+    w: &mut DataWriter,
+    restrictions: &[PublisherRestriction],
+) -> Result<(), SectionEncodeError> {
+    let ranges = restrictions
+        .iter()
+        .cloned()
+        .map(Range::from)
+        .collect::<Vec<_>>();
+    w.write_array_of_ranges(&ranges)
+}
+
+/// A restriction set by the publisher on the legal basis of a purpose, for a list of vendors.
+///
+/// Restrictions are ordered by purpose ID, then by restriction type.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct PublisherRestriction {
     pub purpose_id: u8,
     pub restriction_type: RestrictionType,
@@ -76,7 +336,19 @@ impl From<Range> for PublisherRestriction {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive)]
+impl From<PublisherRestriction> for Range {
+    fn from(r: PublisherRestriction) -> Self {
+        Self {
+            key: r.purpose_id,
+            range_type: r.restriction_type as u8,
+            ids: r.restricted_vendor_ids,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum RestrictionType {
     NotAllowed = 0,
     RequireConsent = 1,
@@ -84,32 +356,226 @@ pub enum RestrictionType {
     Undefined = 3,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct PublisherPurposes {
-    #[gpp(fixed_bitfield(24))]
     pub consents: IdSet,
-    #[gpp(fixed_bitfield(24))]
     pub legitimate_interests: IdSet,
-    #[gpp(fixed_bitfield(n as usize), where(n = fixed_integer(6)))]
+    /// The number of custom purposes, which is the length of the two custom bitfields.
+    ///
+    /// It may be greater than the highest custom purpose ID. It is raised to that ID when
+    /// encoding if it is lower.
+    pub num_custom_purposes: u8,
     pub custom_consents: IdSet,
-    #[gpp(fixed_bitfield(n as usize))]
     pub custom_legitimate_interests: IdSet,
 }
 
+impl FromDataReader for PublisherPurposes {
+    type Err = SectionDecodeError;
+
+    fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
+        r.enter_field("consents");
+        let consents = r.read_fixed_bitfield(24)?;
+        r.exit_field();
+        r.enter_field("legitimate_interests");
+        let legitimate_interests = r.read_fixed_bitfield(24)?;
+        r.exit_field();
+
+        // the number of custom purposes gives the length of the two following bitfields,
+        // check it against the remaining input rather than failing on a truncated read
+        r.enter_field("custom_consents.n");
+        let num_custom_purposes = r.read_fixed_integer::<u8>(6)?;
+        r.exit_field();
+        let n = num_custom_purposes as usize;
+        let available = r.remaining_bits() / 2;
+        if n as u64 > available {
+            return Err(SectionDecodeError::InvalidFieldValue {
+                expected: format!("at most {available} custom purposes"),
+                found: n.to_string(),
+            });
+        }
+
+        r.enter_field("custom_consents");
+        let custom_consents = r.read_fixed_bitfield(n)?;
+        r.exit_field();
+        r.enter_field("custom_legitimate_interests");
+        let custom_legitimate_interests = r.read_fixed_bitfield(n)?;
+        r.exit_field();
+
+        Ok(Self {
+            consents,
+            legitimate_interests,
+            num_custom_purposes,
+            custom_consents,
+            custom_legitimate_interests,
+        })
+    }
+}
+
+impl ToDataWriter for PublisherPurposes {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_bitfield(24, &self.consents)?;
+        w.write_fixed_bitfield(24, &self.legitimate_interests)?;
+
+        // keep the decoded number of custom purposes, unless a custom purpose is beyond it
+        let n = self
+            .custom_consents
+            .last()
+            .max(self.custom_legitimate_interests.last())
+            .copied()
+            .unwrap_or(0)
+            .max(self.num_custom_purposes as u16);
+        w.write_fixed_integer(6, n)?;
+        w.write_fixed_bitfield(n as usize, &self.custom_consents)?;
+        w.write_fixed_bitfield(n as usize, &self.custom_legitimate_interests)
+    }
+}
+
+impl Fields for PublisherPurposes {
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        self.consents
+            .push_fields(&field_path(prefix, "consents"), out);
+        self.legitimate_interests
+            .push_fields(&field_path(prefix, "legitimate_interests"), out);
+        // the number of custom purposes is named as in the schema
+        let custom_consents = field_path(prefix, "custom_consents");
+        self.num_custom_purposes
+            .push_fields(&field_path(&custom_consents, "n"), out);
+        self.custom_consents.push_fields(&custom_consents, out);
+        self.custom_legitimate_interests
+            .push_fields(&field_path(prefix, "custom_legitimate_interests"), out);
+    }
+}
+
+impl Schema for PublisherPurposes {
+    fn push_schema(prefix: &str, out: &mut Vec<FieldSchema>) {
+        for name in ["consents", "legitimate_interests"] {
+            out.push(FieldSchema::new(
+                &field_path(prefix, name),
+                "IdSet",
+                "fixed_bitfield(24)",
+                Some(24),
+            ));
+        }
+        let custom_consents = field_path(prefix, "custom_consents");
+        out.push(FieldSchema::new(
+            &field_path(&custom_consents, "n"),
+            "u8",
+            "fixed_integer(6)",
+            Some(6),
+        ));
+        out.push(FieldSchema::new(
+            &custom_consents,
+            "IdSet",
+            "fixed_bitfield(n)",
+            None,
+        ));
+        out.push(FieldSchema::new(
+            &field_path(prefix, "custom_legitimate_interests"),
+            "IdSet",
+            "fixed_bitfield(n)",
+            None,
+        ));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::{EncodableSection, SegmentedSection};
+    use std::collections::BTreeSet;
     use std::str::FromStr;
     use test_case::test_case;
 
+    fn restriction(
+        purpose_id: u8,
+        restriction_type: RestrictionType,
+        ids: &[u16],
+    ) -> PublisherRestriction {
+        PublisherRestriction {
+            purpose_id,
+            restriction_type,
+            restricted_vendor_ids: ids.iter().copied().collect(),
+        }
+    }
+
+    #[test_case(RestrictionType::NotAllowed ; "not allowed")]
+    #[test_case(RestrictionType::RequireConsent ; "require consent")]
+    #[test_case(RestrictionType::Undefined ; "undefined")]
+    fn restriction_range_round_trip(restriction_type: RestrictionType) {
+        let r = restriction(7, restriction_type, &[1, 2, 3, 42]);
+        let range = Range::from(r.clone());
+        assert_eq!(range.key, 7);
+        assert_eq!(range.range_type, restriction_type as u8);
+        assert_eq!(PublisherRestriction::from(range), r);
+    }
+
+    #[test]
+    fn write_publisher_restrictions_round_trip() {
+        let restrictions = vec![
+            restriction(1, RestrictionType::NotAllowed, &[5, 6, 7, 8, 100]),
+            restriction(2, RestrictionType::RequireLegitimateInterest, &[12]),
+        ];
+        let mut w = DataWriter::new();
+        write_publisher_restrictions(&mut w, &restrictions).unwrap();
+        let bytes = w.into_bytes();
+        let mut r = DataReader::new(&bytes);
+        assert_eq!(parse_publisher_restrictions(&mut r).unwrap(), restrictions);
+    }
+
+    #[test_case(2 => matches Ok(v) if v.get() == 2 ; "tcf 2.0")]
+    #[test_case(4 => matches Ok(v) if v.get() == 4 ; "tcf 2.2")]
+    #[test_case(5 => matches Ok(_) ; "latest")]
+    #[test_case(0 => matches Err(SectionDecodeError::InvalidFieldValue { .. }) ; "zero")]
+    #[test_case(1 => matches Err(SectionDecodeError::InvalidFieldValue { .. }) ; "tcf v1")]
+    #[test_case(63 => matches Err(SectionDecodeError::InvalidFieldValue { .. }) ; "too large")]
+    fn policy_version(version: u8) -> Result<PolicyVersion, SectionDecodeError> {
+        let mut tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        tcf.core.policy_version = version;
+        tcf.core.policy_version()
+    }
+
+    #[test]
+    fn normalize_publisher_restrictions() {
+        let mut a = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        let mut b = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        a.core.publisher_restrictions = vec![
+            restriction(3, RestrictionType::RequireConsent, &[1, 2]),
+            restriction(1, RestrictionType::NotAllowed, &[4]),
+            restriction(3, RestrictionType::NotAllowed, &[5]),
+        ];
+        b.core.publisher_restrictions = vec![
+            restriction(3, RestrictionType::RequireConsent, &[2]),
+            restriction(3, RestrictionType::NotAllowed, &[5]),
+            restriction(1, RestrictionType::NotAllowed, &[4]),
+            restriction(3, RestrictionType::RequireConsent, &[1]),
+        ];
+        assert_ne!(a, b);
+
+        a.core.normalize_publisher_restrictions();
+        b.core.normalize_publisher_restrictions();
+
+        assert_eq!(a, b);
+        assert_eq!(
+            a.core.publisher_restrictions,
+            vec![
+                restriction(1, RestrictionType::NotAllowed, &[4]),
+                restriction(3, RestrictionType::NotAllowed, &[5]),
+                restriction(3, RestrictionType::RequireConsent, &[1, 2]),
+            ]
+        );
+    }
+
     #[test]
     fn core_only() {
         let actual = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
         let expected = TcfEuV2 {
             core: Core {
-                created: 1650492000,
-                last_updated: 1650492000,
+                created: Timestamp::from_seconds(1650492000),
+                last_updated: Timestamp::from_seconds(1650492000),
                 cmp_id: 31,
                 cmp_version: 640,
                 consent_screen: 1,
@@ -129,6 +595,7 @@ mod tests {
             },
             disclosed_vendors: None,
             publisher_purposes: None,
+            segment_order: SegmentOrder::default(),
         };
         assert_eq!(actual, expected);
     }
@@ -139,8 +606,8 @@ mod tests {
 
         let expected = TcfEuV2 {
             core: Core {
-                created: 1582243059,
-                last_updated: 1582243059,
+                created: Timestamp::from_deciseconds(15822430593),
+                last_updated: Timestamp::from_deciseconds(15822430593),
                 cmp_id: 27,
                 cmp_version: 0,
                 consent_screen: 0,
@@ -169,6 +636,7 @@ mod tests {
                 .into(),
             ),
             publisher_purposes: None,
+            segment_order: SegmentOrder::default(),
         };
 
         assert_eq!(actual, expected);
@@ -182,8 +650,8 @@ mod tests {
 
         let expected = TcfEuV2 {
             core: Core {
-                created: 1582243059,
-                last_updated: 1582243059,
+                created: Timestamp::from_deciseconds(15822430593),
+                last_updated: Timestamp::from_deciseconds(15822430593),
                 cmp_id: 27,
                 cmp_version: 0,
                 consent_screen: 0,
@@ -208,9 +676,11 @@ mod tests {
                     1, 2, 3, 4, 5, 6, 7, 9, 10, 11, 12, 14, 15, 16, 17, 18, 19, 21, 22, 23, 24,
                 ]
                 .into(),
+                num_custom_purposes: 5,
                 custom_consents: [1, 2, 4].into(),
                 custom_legitimate_interests: [2, 4].into(),
             }),
+            segment_order: SegmentOrder::default(),
         };
 
         assert_eq!(actual, expected);
@@ -221,8 +691,8 @@ mod tests {
     fn with_all_segments(s: &str) {
         let expected = TcfEuV2 {
             core: Core {
-                created: 1582243059,
-                last_updated: 1582243059,
+                created: Timestamp::from_deciseconds(15822430593),
+                last_updated: Timestamp::from_deciseconds(15822430593),
                 cmp_id: 27,
                 cmp_version: 0,
                 consent_screen: 0,
@@ -256,22 +726,239 @@ mod tests {
                     1, 2, 3, 4, 5, 6, 7, 9, 10, 11, 12, 14, 15, 16, 17, 18, 19, 21, 22, 23, 24,
                 ]
                 .into(),
+                num_custom_purposes: 5,
                 custom_consents: [1, 2, 4].into(),
                 custom_legitimate_interests: [2, 4].into(),
             }),
+            segment_order: SegmentOrder::default(),
         };
 
         let actual = TcfEuV2::from_str(s).unwrap();
         assert_eq!(actual, expected);
     }
 
+    #[test_case(1 => Some(LegalBasis::Consent) ; "consent only")]
+    #[test_case(2 => Some(LegalBasis::Consent) ; "consent over legitimate interest")]
+    #[test_case(7 => Some(LegalBasis::LegitimateInterest) ; "legitimate interest only")]
+    #[test_case(4 => None ; "none")]
+    fn purpose_basis(purpose_id: u8) -> Option<LegalBasis> {
+        let mut tcf = TcfEuV2::from_str("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA").unwrap();
+        tcf.core.purpose_legitimate_interests = [2, 7].into();
+        tcf.purpose_basis(purpose_id)
+    }
+
+    #[test]
+    fn purpose_one_legitimate_interest_ignored() {
+        let mut tcf = TcfEuV2::from_str("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA").unwrap();
+        tcf.core.purpose_consents = [2].into();
+        tcf.core.purpose_legitimate_interests = [1].into();
+        assert_eq!(tcf.purpose_basis(1), None);
+    }
+
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA" => BTreeSet::new() ; "core only")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA" => BTreeSet::from([3]) ; "publisher purposes")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => BTreeSet::from([1, 3]) ; "all segments")]
+    fn parse_core_only(s: &str) -> BTreeSet<u8> {
+        let (actual, segment_types) = TcfEuV2::parse_core_only(s).unwrap();
+        let full = TcfEuV2::from_str(s).unwrap();
+
+        assert_eq!(actual.core, full.core);
+        assert_eq!(actual.disclosed_vendors, None);
+        assert_eq!(actual.publisher_purposes, None);
+        segment_types
+    }
+
+    #[test]
+    fn parse_core_only_duplicate_segment() {
+        let r = TcfEuV2::parse_core_only(
+            "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.ZAAgH9794ulA",
+        );
+        assert!(matches!(
+            r,
+            Err(SectionDecodeError::DuplicateSegmentType { segment_type: 3 })
+        ));
+    }
+
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" ; "core only")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" ; "all segments")]
+    fn encode(s: &str) {
+        let expected = TcfEuV2::from_str(s).unwrap();
+        let actual = TcfEuV2::from_str(&expected.encode().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn encode_publisher_purposes_count() {
+        // the number of custom purposes is 5, with custom purpose 5 unset
+        let s = "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA";
+        let tcf = TcfEuV2::from_str(s).unwrap();
+        assert_eq!(
+            tcf.publisher_purposes.as_ref().unwrap().num_custom_purposes,
+            5
+        );
+
+        assert_eq!(tcf.encode().unwrap(), s);
+    }
+
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" ; "publisher purposes first")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA" ; "disclosed vendors first")]
+    fn encode_segment_order(s: &str) {
+        let mut tcf = TcfEuV2::from_str(s).unwrap();
+        assert_eq!(tcf.encode().unwrap(), s);
+
+        tcf.canonicalize();
+        assert_eq!(tcf.encode().unwrap(), "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA");
+    }
+
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAA" ; "shortest core")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAA" ; "missing publisher country")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACC" ; "truncated vendor legitimate interests")]
+    fn parse_lenient_truncated(s: &str) {
+        assert!(matches!(
+            TcfEuV2::from_str(s),
+            Err(SectionDecodeError::Read(_))
+        ));
+
+        let actual = parse_lenient(s, &mut Vec::new(), DecodeOptions::default()).unwrap();
+        assert_eq!(actual.core.cmp_id, 27);
+        assert_eq!(actual.core.purpose_consents, [1, 2, 3].into());
+        assert_eq!(actual.core.publisher_country_code, "AA");
+        assert!(actual.core.publisher_restrictions.is_empty());
+    }
+
+    #[test]
+    fn parse_lenient_complete() {
+        let s = "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA";
+        assert_eq!(
+            parse_lenient(s, &mut Vec::new(), DecodeOptions::default()).unwrap(),
+            TcfEuV2::from_str(s).unwrap()
+        );
+    }
+
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAA" ; "missing purposes")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAA" ; "missing purpose one treatment")]
+    fn parse_lenient_too_short(s: &str) {
+        assert!(matches!(
+            parse_lenient(s, &mut Vec::new(), DecodeOptions::default()),
+            Err(SectionDecodeError::Read(_))
+        ));
+    }
+
     #[test_case("CPX" => matches SectionDecodeError::Read(_) ; "decode error")]
     #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
-    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "disclosed vendors only")]
-    #[test_case("ZAAgH9794ulA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "publisher purposes only")]
-    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "disclosed vendors and publisher purposes")]
-    #[test_case("ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "publisher purposes and disclosed vendors")]
+    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::MissingCoreSegment ; "disclosed vendors only")]
+    #[test_case("ZAAgH9794ulA" => matches SectionDecodeError::MissingCoreSegment ; "publisher purposes only")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.YAAAAAAAH4" => matches SectionDecodeError::InvalidFieldValue { .. } ; "too many custom purposes")]
+    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA" => matches SectionDecodeError::MissingCoreSegment ; "disclosed vendors and publisher purposes")]
+    #[test_case("ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::MissingCoreSegment ; "publisher purposes and disclosed vendors")]
+    #[test_case("DPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches SectionDecodeError::UnknownSegmentVersion { segment_version: 3 } ; "unknown core version")]
     fn error(s: &str) -> SectionDecodeError {
         TcfEuV2::from_str(s).unwrap_err()
     }
+
+    mod round_trip {
+        use super::*;
+        use proptest::collection::{btree_set, vec};
+        use proptest::prelude::*;
+
+        prop_compose! {
+            fn core()(
+                (created, last_updated) in (0..1u64 << 36, 0..1u64 << 36),
+                (cmp_id, cmp_version, vendor_list_version) in (0..4096u16, 0..4096u16, 0..4096u16),
+                (consent_screen, policy_version) in (0..64u8, 0..64u8),
+                (consent_language, publisher_country_code) in ("[A-Z]{2}", "[A-Z]{2}"),
+                flags in any::<[bool; 3]>(),
+                special_feature_optins in btree_set(1..=12u16, 0..=12),
+                purposes in (btree_set(1..=24u16, 0..=24), btree_set(1..=24u16, 0..=24)),
+                vendors in (btree_set(1..=2000u16, 0..50), btree_set(1..=2000u16, 0..50)),
+                publisher_restrictions in vec(publisher_restriction(), 0..5),
+            ) -> Core {
+                Core {
+                    created: Timestamp::from_deciseconds(created),
+                    last_updated: Timestamp::from_deciseconds(last_updated),
+                    cmp_id,
+                    cmp_version,
+                    consent_screen,
+                    consent_language,
+                    vendor_list_version,
+                    policy_version,
+                    is_service_specific: flags[0],
+                    use_non_standard_stacks: flags[1],
+                    special_feature_optins,
+                    purpose_consents: purposes.0,
+                    purpose_legitimate_interests: purposes.1,
+                    purpose_one_treatment: flags[2],
+                    publisher_country_code,
+                    vendor_consents: vendors.0,
+                    vendor_legitimate_interests: vendors.1,
+                    publisher_restrictions,
+                }
+            }
+        }
+
+        prop_compose! {
+            fn publisher_restriction()(
+                purpose_id in 0..64u8,
+                restriction_type in prop_oneof![
+                    Just(RestrictionType::NotAllowed),
+                    Just(RestrictionType::RequireConsent),
+                    Just(RestrictionType::RequireLegitimateInterest),
+                    Just(RestrictionType::Undefined),
+                ],
+                restricted_vendor_ids in btree_set(1..=2000u16, 0..20),
+            ) -> PublisherRestriction {
+                PublisherRestriction {
+                    purpose_id,
+                    restriction_type,
+                    restricted_vendor_ids,
+                }
+            }
+        }
+
+        prop_compose! {
+            fn publisher_purposes()(
+                consents in btree_set(1..=24u16, 0..=24),
+                legitimate_interests in btree_set(1..=24u16, 0..=24),
+                num_custom_purposes in 0..64u8,
+                custom_consents in btree_set(1..=63u16, 0..10),
+                custom_legitimate_interests in btree_set(1..=63u16, 0..10),
+            ) -> PublisherPurposes {
+                // the encoder raises the number of custom purposes to the highest custom ID
+                let highest = custom_consents
+                    .last()
+                    .max(custom_legitimate_interests.last())
+                    .map_or(0, |&id| id as u8);
+                PublisherPurposes {
+                    consents,
+                    legitimate_interests,
+                    num_custom_purposes: num_custom_purposes.max(highest),
+                    custom_consents,
+                    custom_legitimate_interests,
+                }
+            }
+        }
+
+        prop_compose! {
+            fn tcfeuv2()(
+                core in core(),
+                disclosed_vendors in proptest::option::of(btree_set(1..=2000u16, 0..50)),
+                publisher_purposes in proptest::option::of(publisher_purposes()),
+            ) -> TcfEuV2 {
+                TcfEuV2 {
+                    core,
+                    disclosed_vendors,
+                    publisher_purposes,
+                    segment_order: SegmentOrder::default(),
+                }
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn encode_decode(tcf in tcfeuv2()) {
+                prop_assert_eq!(TcfEuV2::from_str(&tcf.encode()?)?, tcf);
+            }
+        }
+    }
 }
@@ -1,69 +1,237 @@
-use crate::core::{DataReader, FromDataReader};
-use crate::sections::SectionDecodeError;
+use crate::core::{DataReader, DataWriter, FromDataReader, ToDataWriter};
+use crate::sections::{SectionDecodeError, SectionEncodeError};
 use num_derive::{FromPrimitive, ToPrimitive};
-use num_traits::FromPrimitive;
-use std::io;
+use thiserror::Error;
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Notice {
+    #[default]
     NotApplicable = 0,
     Provided = 1,
     NotProvided = 2,
 }
 
 impl FromDataReader for Notice {
-    type Err = io::Error;
+    type Err = SectionDecodeError;
 
     fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
-        Ok(Self::from_u8(r.read_fixed_integer(2)?).unwrap_or(Self::NotApplicable))
+        r.read_enum(2, Self::NotApplicable)
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+impl ToDataWriter for Notice {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(2, *self as u8)
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum OptOut {
+    #[default]
     NotApplicable = 0,
     OptedOut = 1,
     DidNotOptOut = 2,
 }
 
 impl FromDataReader for OptOut {
-    type Err = io::Error;
+    type Err = SectionDecodeError;
 
     fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
-        Ok(Self::from_u8(r.read_fixed_integer(2)?).unwrap_or(Self::NotApplicable))
+        r.read_enum(2, Self::NotApplicable)
+    }
+}
+
+impl ToDataWriter for OptOut {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(2, *self as u8)
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Consent {
+    #[default]
     NotApplicable = 0,
     NoConsent = 1,
     Consent = 2,
 }
 
 impl FromDataReader for Consent {
-    type Err = io::Error;
+    type Err = SectionDecodeError;
 
     fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
-        Ok(Self::from_u8(r.read_fixed_integer(2)?).unwrap_or(Self::NotApplicable))
+        r.read_enum(2, Self::NotApplicable)
     }
 }
 
-#[derive(Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+impl ToDataWriter for Consent {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(2, *self as u8)
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromPrimitive, ToPrimitive,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum MspaMode {
+    #[default]
     NotApplicable = 0,
     Yes = 1,
     No = 2,
 }
 
 impl FromDataReader for MspaMode {
-    type Err = io::Error;
+    type Err = SectionDecodeError;
 
     fn from_data_reader(r: &mut DataReader) -> Result<Self, Self::Err> {
-        Ok(Self::from_u8(r.read_fixed_integer(2)?).unwrap_or(Self::NotApplicable))
+        r.read_enum(2, Self::NotApplicable)
     }
 }
 
+impl ToDataWriter for MspaMode {
+    fn to_data_writer(&self, w: &mut DataWriter) -> Result<(), SectionEncodeError> {
+        w.write_fixed_integer(2, *self as u8)
+    }
+}
+
+/// An inconsistency between the MSPA fields of a US section.
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// A transaction cannot be in both MSPA Service Provider Mode and Opt-Out Option Mode.
+    #[error("service provider mode and opt-out option mode cannot both be enabled")]
+    ConflictingMspaModes,
+    /// In MSPA Service Provider Mode, opt-out notices are not applicable.
+    #[error("notice {notice} must be not applicable in service provider mode (found {value:?})")]
+    NoticeInServiceProviderMode { notice: String, value: Notice },
+    /// The section could not be decoded, so its consistency could not be checked.
+    #[error("section cannot be decoded: {0}")]
+    Undecodable(String),
+}
+
+/// Checks that the MSPA modes of a US section are consistent with each other and with the
+/// given opt-out notices.
+///
+/// The following rules are checked:
+/// - Service Provider Mode and Opt-Out Option Mode cannot both be enabled
+/// - when Service Provider Mode is enabled, all opt-out notices must be not applicable
+///
+/// Notices are given as pairs of field names and values, the name being used for reporting.
+/// All inconsistencies are returned, an empty result meaning the modes are valid.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::us_common::{validate_mspa_modes, MspaMode, Notice, ValidationError};
+///
+/// let errors = validate_mspa_modes(
+///     MspaMode::Yes,
+///     MspaMode::No,
+///     &[("sale_opt_out_notice", Notice::Provided)],
+/// );
+///
+/// assert_eq!(
+///     errors,
+///     vec![ValidationError::NoticeInServiceProviderMode {
+///         notice: "sale_opt_out_notice".to_string(),
+///         value: Notice::Provided,
+///     }]
+/// );
+/// ```
+pub fn validate_mspa_modes(
+    service_provider: MspaMode,
+    opt_out_option: MspaMode,
+    notices: &[(&str, Notice)],
+) -> Vec<ValidationError> {
+    let mut errors = vec![];
+
+    if service_provider == MspaMode::Yes && opt_out_option == MspaMode::Yes {
+        errors.push(ValidationError::ConflictingMspaModes);
+    }
+
+    if service_provider == MspaMode::Yes {
+        errors.extend(
+            notices
+                .iter()
+                .filter(|(_, value)| *value != Notice::NotApplicable)
+                .map(
+                    |&(notice, value)| ValidationError::NoticeInServiceProviderMode {
+                        notice: notice.to_string(),
+                        value,
+                    },
+                ),
+        );
+    }
+
+    errors
+}
+
+/// Implements an `items` method returning the name and value of each field of a
+/// sensitive data structure, in the order defined by the specification.
+macro_rules! impl_sensitive_data_items {
+    ($t:ty, $v:ty, [$($field:ident),+ $(,)?]) => {
+        impl $t {
+            /// Returns the name and value of each field, in the order defined by the
+            /// specification.
+            ///
+            /// The position of each entry matches the position of the corresponding category
+            /// in the list documented by the IAB.
+            pub fn items(&self) -> [(&'static str, $v); [$(stringify!($field)),+].len()] {
+                [$((stringify!($field), self.$field)),+]
+            }
+        }
+    };
+}
+
+pub(crate) use impl_sensitive_data_items;
+
+/// Implements accessors to the fields of a sensitive data structure by their category index,
+/// as numbered by the IAB specification.
+///
+/// Indices start at 1 and follow the order of the `items` method.
+macro_rules! impl_sensitive_data_categories {
+    ($t:ty, $v:ty) => {
+        impl $t {
+            /// Returns the value of the sensitive data category with the given index, as
+            /// numbered by the IAB specification, starting at 1.
+            ///
+            /// Returns `None` if the index does not match any category of this version.
+            pub fn get(&self, index: u8) -> Option<$v> {
+                let i = usize::from(index.checked_sub(1)?);
+                self.items().get(i).map(|&(_, v)| v)
+            }
+
+            /// Returns an iterator over the index and value of each sensitive data category, as
+            /// numbered by the IAB specification, starting at 1.
+            pub fn categories(&self) -> impl Iterator<Item = (u8, $v)> {
+                (1..).zip(self.items()).map(|(i, (_, v))| (i, v))
+            }
+        }
+    };
+}
+
+pub(crate) use impl_sensitive_data_categories;
+
+crate::sections::impl_schema_leaf!(
+    Notice => "fixed_integer(2)", 2,
+    OptOut => "fixed_integer(2)", 2,
+    Consent => "fixed_integer(2)", 2,
+    MspaMode => "fixed_integer(2)", 2,
+);
+
 pub(crate) fn parse_mspa_covered_transaction(
     r: &mut DataReader,
 ) -> Result<bool, SectionDecodeError> {
@@ -77,3 +245,59 @@ pub(crate) fn parse_mspa_covered_transaction(
         }),
     }
 }
+
+pub(crate) fn write_mspa_covered_transaction(
+    w: &mut DataWriter,
+    value: &bool,
+) -> Result<(), SectionEncodeError> {
+    w.write_fixed_integer(2, if *value { 1u8 } else { 2 })
+}
+
+/// Property test strategies for the fields shared by US sections.
+#[cfg(test)]
+pub(crate) mod strategies {
+    use num_traits::FromPrimitive;
+    use proptest::prelude::*;
+
+    /// Generates the values of `n` two-bit fields, such as notices, opt-outs, consents and
+    /// MSPA modes, which all have the valid values 0, 1 and 2.
+    pub(crate) fn values(n: usize) -> impl Strategy<Value = Vec<u8>> {
+        proptest::collection::vec(0..=2u8, n)
+    }
+
+    /// Takes the next generated value as a field of type `T`.
+    pub(crate) fn next<T: FromPrimitive>(values: &mut impl Iterator<Item = u8>) -> T {
+        values
+            .next()
+            .and_then(T::from_u8)
+            .expect("valid field value")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(MspaMode::NotApplicable, MspaMode::NotApplicable, Notice::Provided => Vec::<ValidationError>::new() ; "not applicable")]
+    #[test_case(MspaMode::No, MspaMode::Yes, Notice::Provided => Vec::<ValidationError>::new() ; "opt-out option mode")]
+    #[test_case(MspaMode::Yes, MspaMode::No, Notice::NotApplicable => Vec::<ValidationError>::new() ; "service provider mode")]
+    #[test_case(MspaMode::Yes, MspaMode::Yes, Notice::NotApplicable => vec![ValidationError::ConflictingMspaModes] ; "both modes")]
+    #[test_case(MspaMode::Yes, MspaMode::No, Notice::NotProvided => vec![
+        ValidationError::NoticeInServiceProviderMode {
+            notice: "sale_opt_out_notice".to_string(),
+            value: Notice::NotProvided,
+        },
+    ] ; "notice in service provider mode")]
+    fn mspa_modes(
+        service_provider: MspaMode,
+        opt_out_option: MspaMode,
+        notice: Notice,
+    ) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            service_provider,
+            opt_out_option,
+            &[("sale_opt_out_notice", notice)],
+        )
+    }
+}
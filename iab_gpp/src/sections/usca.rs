@@ -1,9 +1,13 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_items, parse_mspa_covered_transaction, validate_mspa_modes,
+    write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut, ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsCa {
@@ -12,12 +16,73 @@ pub struct UsCa {
     pub gpc: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl UsCa {
+    /// Returns the version of the core segment.
+    ///
+    /// See [`Core::version`].
+    pub fn version(&self) -> u8 {
+        self.core.version()
+    }
+}
+
+/// The core segment of the US California section, in one of its versions.
+///
+/// Only version 1 is currently defined. The segment is nonetheless dispatched on its version,
+/// as for the [US national section](crate::sections::usnat::Core), so that a future version can
+/// be decoded alongside version 1.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
-#[gpp(section_version = 1)]
+pub enum Core {
+    #[gpp(version = 1)]
+    V1(CoreV1),
+}
+
+impl Core {
+    /// Returns the version of this core segment.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::usca::UsCa;
+    ///
+    /// let usca = "BVqqqqpY.YA".parse::<UsCa>().unwrap();
+    ///
+    /// assert_eq!(usca.core.version(), 1);
+    /// assert!(usca.core.as_v1().is_some());
+    /// ```
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1(_) => 1,
+        }
+    }
+
+    /// Returns the version 1 core segment, or `None` if this is another version.
+    pub fn as_v1(&self) -> Option<&CoreV1> {
+        match self {
+            Self::V1(core) => Some(core),
+        }
+    }
+}
+
+impl Default for Core {
+    /// Returns the latest version of the core segment, with all fields not applicable.
+    fn default() -> Self {
+        Self::V1(CoreV1::default())
+    }
+}
+
 /// The core sub-section must always be present. Where terms are capitalized in the ‘description’
 /// field they are defined terms in Cal. Civ. Code 1798.140.
-pub struct Core {
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[non_exhaustive]
+pub struct CoreV1 {
     pub sale_opt_out_notice: Notice,
     pub sharing_opt_out_notice: Notice,
     pub sensitive_data_limit_use_notice: Notice,
@@ -26,13 +91,37 @@ pub struct Core {
     pub sensitive_data_processing: SensitiveDataProcessing,
     pub known_child_sensitive_data_consents: KnownChildSensitiveDataConsents,
     pub personal_data_consent: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl CoreV1 {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                ("sharing_opt_out_notice", self.sharing_opt_out_notice),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     /// Opt-Out of the Use or Disclosure of the Consumer's Sensitive Personal Information Which
@@ -53,27 +142,78 @@ pub struct SensitiveDataProcessing {
     pub sex_life_or_sexual_orientation: OptOut,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    SensitiveDataProcessing,
+    OptOut,
+    [
+        identification_documents,
+        financial_data,
+        precise_geolocation,
+        origin_beliefs_or_union,
+        mail_email_or_text_messages,
+        genetic_data,
+        biometric_unique_identification,
+        health_data,
+        sex_life_or_sexual_orientation
+    ]
+);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsents {
     pub sell_personal_information: Consent,
     pub share_personal_information: Consent,
 }
 
+impl_sensitive_data_items!(
+    KnownChildSensitiveDataConsents,
+    Consent,
+    [sell_personal_information, share_personal_information]
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::sections::SectionDecodeError;
+    use crate::sections::{EncodableSection, SectionDecodeError, SectionId};
     use std::str::FromStr;
     use test_case::test_case;
 
+    #[test]
+    fn sensitive_data_items() {
+        let sdp = SensitiveDataProcessing {
+            identification_documents: OptOut::OptedOut,
+            financial_data: OptOut::DidNotOptOut,
+            precise_geolocation: OptOut::NotApplicable,
+            origin_beliefs_or_union: OptOut::NotApplicable,
+            mail_email_or_text_messages: OptOut::NotApplicable,
+            genetic_data: OptOut::NotApplicable,
+            biometric_unique_identification: OptOut::NotApplicable,
+            health_data: OptOut::NotApplicable,
+            sex_life_or_sexual_orientation: OptOut::OptedOut,
+        };
+        let items = sdp.items();
+
+        assert_eq!(items.len(), 9);
+        assert_eq!(items[0], ("identification_documents", OptOut::OptedOut));
+        assert_eq!(items[1], ("financial_data", OptOut::DidNotOptOut));
+        assert_eq!(
+            items[8],
+            ("sex_life_or_sexual_orientation", OptOut::OptedOut)
+        );
+    }
+
     #[test]
     fn parse() {
         let test_cases = [
             (
                 "BAAAAACA",
                 UsCa {
-                    core: Core {
+                    core: Core::V1(CoreV1 {
                         sale_opt_out_notice: Notice::NotApplicable,
                         sharing_opt_out_notice: Notice::NotApplicable,
                         sensitive_data_limit_use_notice: Notice::NotApplicable,
@@ -98,14 +238,14 @@ mod tests {
                         mspa_covered_transaction: false,
                         mspa_opt_out_option_mode: MspaMode::NotApplicable,
                         mspa_service_provider_mode: MspaMode::NotApplicable,
-                    },
+                    }),
                     gpc: None,
                 },
             ),
             (
                 "BVVVVVVY",
                 UsCa {
-                    core: Core {
+                    core: Core::V1(CoreV1 {
                         sale_opt_out_notice: Notice::Provided,
                         sharing_opt_out_notice: Notice::Provided,
                         sensitive_data_limit_use_notice: Notice::Provided,
@@ -130,14 +270,14 @@ mod tests {
                         mspa_covered_transaction: true,
                         mspa_opt_out_option_mode: MspaMode::Yes,
                         mspa_service_provider_mode: MspaMode::No,
-                    },
+                    }),
                     gpc: None,
                 },
             ),
             (
                 "BVqqqqpY.YA",
                 UsCa {
-                    core: Core {
+                    core: Core::V1(CoreV1 {
                         sale_opt_out_notice: Notice::Provided,
                         sharing_opt_out_notice: Notice::Provided,
                         sensitive_data_limit_use_notice: Notice::Provided,
@@ -162,7 +302,7 @@ mod tests {
                         mspa_covered_transaction: true,
                         mspa_opt_out_option_mode: MspaMode::Yes,
                         mspa_service_provider_mode: MspaMode::No,
-                    },
+                    }),
                     gpc: Some(true),
                 },
             ),
@@ -174,11 +314,113 @@ mod tests {
         }
     }
 
+    #[test]
+    fn default_is_not_applicable() {
+        let usca = UsCa::default();
+
+        assert_eq!(usca.encode().unwrap(), "BAAAAACA");
+        assert_eq!(usca, UsCa::from_str("BAAAAACA").unwrap());
+    }
+
+    #[test_case("BAAAAACA" ; "not applicable")]
+    #[test_case("BVqqqqpY.YA" ; "with gpc")]
+    fn version(s: &str) {
+        let usca = UsCa::from_str(s).unwrap();
+        assert_eq!(usca.version(), 1);
+        assert!(matches!(usca.core, Core::V1(_)));
+        assert!(usca.core.as_v1().is_some());
+    }
+
+    #[test_case("BAAAAACA" ; "not applicable")]
+    #[test_case("BVVVVVVY" ; "core only")]
+    #[test_case("BVqqqqpY.YA" ; "with gpc")]
+    fn encode(s: &str) {
+        assert_eq!(UsCa::from_str(s).unwrap().encode().unwrap(), s);
+    }
+
+    #[test_case("BVVVVVVY" => 46 ; "core only")]
+    #[test_case("BVqqqqpY.YA" => 49 ; "with gpc")]
+    fn raw_len_bits(s: &str) -> usize {
+        UsCa::from_str(s).unwrap().raw_len_bits().unwrap()
+    }
+
+    #[test]
+    fn schema() {
+        let schema = SectionId::UsCa.schema().unwrap();
+        let names = schema.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names[0], "core.version");
+        assert_eq!(names[names.len() - 2..], ["gpc.segment_type", "gpc"]);
+
+        // the MSPA covered transaction has a custom encoding of 2 bits
+        let variable = schema
+            .iter()
+            .filter(|f| f.bits.is_none())
+            .collect::<Vec<_>>();
+        assert_eq!(variable.len(), 1);
+        assert_eq!(variable[0].name, "core.v1.mspa_covered_transaction");
+        let bits = schema.iter().filter_map(|f| f.bits).sum::<u32>() + 2;
+        assert_eq!(bits as usize, raw_len_bits("BVqqqqpY.YA"));
+    }
+
     #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
     #[test_case("123" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "decode error")]
     #[test_case("CVVVVVVVVWA.YA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version")]
     #[test_case("BVVVVVVVVWA.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("BVVVVVVVVWA." => matches SectionDecodeError::EmptySegment { index: 1 } ; "empty segment")]
+    #[test_case("YA" => matches SectionDecodeError::MissingCoreSegment ; "gpc only")]
+    #[test_case("BAAAAAAA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BAAAAADA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
     fn error(s: &str) -> SectionDecodeError {
         UsCa::from_str(s).unwrap_err()
     }
+
+    mod round_trip {
+        use super::*;
+        use crate::sections::us_common::strategies::{next, values};
+        use proptest::prelude::*;
+
+        prop_compose! {
+            fn usca()(
+                values in values(19),
+                mspa_covered_transaction in any::<bool>(),
+                gpc in any::<Option<bool>>(),
+            ) -> UsCa {
+                let v = &mut values.into_iter();
+                let core = CoreV1 {
+                    sale_opt_out_notice: next(v),
+                    sharing_opt_out_notice: next(v),
+                    sensitive_data_limit_use_notice: next(v),
+                    sale_opt_out: next(v),
+                    sharing_opt_out: next(v),
+                    sensitive_data_processing: SensitiveDataProcessing {
+                        identification_documents: next(v),
+                        financial_data: next(v),
+                        precise_geolocation: next(v),
+                        origin_beliefs_or_union: next(v),
+                        mail_email_or_text_messages: next(v),
+                        genetic_data: next(v),
+                        biometric_unique_identification: next(v),
+                        health_data: next(v),
+                        sex_life_or_sexual_orientation: next(v),
+                    },
+                    known_child_sensitive_data_consents: KnownChildSensitiveDataConsents {
+                        sell_personal_information: next(v),
+                        share_personal_information: next(v),
+                    },
+                    personal_data_consent: next(v),
+                    mspa_covered_transaction,
+                    mspa_opt_out_option_mode: next(v),
+                    mspa_service_provider_mode: next(v),
+                };
+                UsCa { core: Core::V1(core), gpc }
+            }
+        }
+
+        proptest! {
+            #[test]
+            fn encode_decode(usca in usca()) {
+                prop_assert_eq!(UsCa::from_str(&usca.encode()?)?, usca);
+            }
+        }
+    }
 }
@@ -1,9 +1,13 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_items, parse_mspa_covered_transaction, validate_mspa_modes,
+    write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut, ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsCo {
@@ -12,7 +16,12 @@ pub struct UsCo {
     pub gpc: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -23,13 +32,40 @@ pub struct Core {
     pub targeted_advertising_opt_out: OptOut,
     pub sensitive_data_processing: SensitiveDataProcessing,
     pub known_child_sensitive_data_consents: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl Core {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
@@ -41,6 +77,20 @@ pub struct SensitiveDataProcessing {
     pub biometric_unique_identification: Consent,
 }
 
+impl_sensitive_data_items!(
+    SensitiveDataProcessing,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_beliefs,
+        health_condition_or_diagnosis,
+        sex_life_or_sexual_orientation,
+        citizenship_data,
+        genetic_unique_identification,
+        biometric_unique_identification
+    ]
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +191,8 @@ mod tests {
     #[test_case("123" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "decode error")]
     #[test_case("CVVVVVg.YA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version")]
     #[test_case("BVVVVVg.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("BAAAAAA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BAAAAMA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
     fn error(s: &str) -> SectionDecodeError {
         UsCo::from_str(s).unwrap_err()
     }
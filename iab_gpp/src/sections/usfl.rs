@@ -1,15 +1,24 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_items, parse_mspa_covered_transaction, validate_mspa_modes,
+    write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut, ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct UsFl {
     pub core: Core,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -21,13 +30,40 @@ pub struct Core {
     pub sensitive_data_processing: SensitiveDataProcessing,
     pub known_child_sensitive_data_consents: KnownChildSensitiveDataConsents,
     pub additional_data_processing_consent: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl Core {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
@@ -40,10 +76,50 @@ pub struct SensitiveDataProcessing {
     pub precise_geolocation_data: Consent,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    SensitiveDataProcessing,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_beliefs,
+        health_data,
+        sex_life_or_sexual_orientation,
+        citizenship_or_immigration_status,
+        genetic_unique_identification,
+        biometric_unique_identification,
+        precise_geolocation_data
+    ]
+);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsents {
     pub under_13: Consent,
     pub from_13_to_16: Consent,
     pub from_16_to_18: Consent,
 }
+
+impl_sensitive_data_items!(
+    KnownChildSensitiveDataConsents,
+    Consent,
+    [under_13, from_13_to_16, from_16_to_18]
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::SectionDecodeError;
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test_case("BAAAAAAA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BAAAAADA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
+    fn error(s: &str) -> SectionDecodeError {
+        UsFl::from_str(s).unwrap_err()
+    }
+}
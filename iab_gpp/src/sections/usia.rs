@@ -1,9 +1,13 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_items, parse_mspa_covered_transaction, validate_mspa_modes,
+    write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut, ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsIa {
@@ -12,7 +16,12 @@ pub struct UsIa {
     pub gpc: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -24,13 +33,44 @@ pub struct Core {
     pub targeted_advertising_opt_out: OptOut,
     pub sensitive_data_processing: SensitiveDataProcessing,
     pub known_child_sensitive_data_consents: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl Core {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+                (
+                    "sensitive_data_optout_notice",
+                    self.sensitive_data_optout_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
@@ -42,3 +82,79 @@ pub struct SensitiveDataProcessing {
     pub biometric_unique_identification: Consent,
     pub precise_geolocation_data: Consent,
 }
+
+impl_sensitive_data_items!(
+    SensitiveDataProcessing,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_beliefs,
+        health_data,
+        sexual_orientation,
+        citizenship_status,
+        genetic_unique_identification,
+        biometric_unique_identification,
+        precise_geolocation_data
+    ]
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::{EncodableSection, SectionDecodeError};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test]
+    fn parse() {
+        let actual = UsIa::from_str("BVVVVVWA.YA").unwrap();
+        let expected = UsIa {
+            core: Core {
+                processing_notice: Notice::Provided,
+                sale_opt_out_notice: Notice::Provided,
+                targeted_advertising_opt_out_notice: Notice::Provided,
+                sensitive_data_optout_notice: Notice::Provided,
+                sale_opt_out: OptOut::OptedOut,
+                targeted_advertising_opt_out: OptOut::OptedOut,
+                sensitive_data_processing: SensitiveDataProcessing {
+                    racial_or_ethnic_origin: Consent::NoConsent,
+                    religious_beliefs: Consent::NoConsent,
+                    health_data: Consent::NoConsent,
+                    sexual_orientation: Consent::NoConsent,
+                    citizenship_status: Consent::NoConsent,
+                    genetic_unique_identification: Consent::NoConsent,
+                    biometric_unique_identification: Consent::NoConsent,
+                    precise_geolocation_data: Consent::NoConsent,
+                },
+                known_child_sensitive_data_consents: Consent::NoConsent,
+                mspa_covered_transaction: true,
+                mspa_opt_out_option_mode: MspaMode::Yes,
+                mspa_service_provider_mode: MspaMode::No,
+            },
+            gpc: Some(true),
+        };
+
+        assert_eq!(actual, expected);
+        assert!(actual.core.validate().is_empty());
+    }
+
+    #[test]
+    fn default_is_not_applicable() {
+        assert_eq!(UsIa::default().encode().unwrap(), "BAAAAAgA");
+    }
+
+    #[test_case("BVVVVVWA" ; "core only")]
+    #[test_case("BVVVVVWA.YA" ; "with gpc")]
+    fn encode(s: &str) {
+        assert_eq!(UsIa::from_str(s).unwrap().encode().unwrap(), s);
+    }
+
+    #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
+    #[test_case("CVVVVVWA.YA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version")]
+    #[test_case("BVVVVVWA.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("BAAAAAAA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BAAAAAwA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
+    fn error(s: &str) -> SectionDecodeError {
+        UsIa::from_str(s).unwrap_err()
+    }
+}
@@ -1,9 +1,14 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_categories, impl_sensitive_data_items, parse_mspa_covered_transaction,
+    validate_mspa_modes, write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsNat {
@@ -12,7 +17,30 @@ pub struct UsNat {
     pub gpc: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl UsNat {
+    /// Returns the version of the core segment, 1 or 2.
+    ///
+    /// See [`Core::version`].
+    pub fn version(&self) -> u8 {
+        self.core.version()
+    }
+}
+
+/// The core segment of the US national section, in one of its versions.
+///
+/// Both versions share most of their fields. Version 2 differs from version 1 as follows:
+///
+/// - its sensitive data processing consents hold four extra categories: `general_health_data`,
+///   `crime_victim_status`, `national_origin` and `transgender_or_nonbinary_status`, and the
+///   `financial_data` category is renamed to `financial_account_data`,
+/// - its known child sensitive data consents are revised, with the extra
+///   `process_personal_data_from_16_to_17` consent.
+///
+/// Code which only needs one version can use [`as_v1`](Self::as_v1) or
+/// [`as_v2`](Self::as_v2) instead of matching on the enum.
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub enum Core {
     #[gpp(version = 1)]
@@ -21,7 +49,56 @@ pub enum Core {
     V2(CoreV2),
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl Core {
+    /// Returns the version of this core segment, 1 or 2.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::usnat::UsNat;
+    ///
+    /// let usnat = "CAAAAAAAAAWA.Q".parse::<UsNat>().unwrap();
+    ///
+    /// assert_eq!(usnat.core.version(), 2);
+    /// assert!(usnat.core.as_v2().is_some());
+    /// ```
+    pub fn version(&self) -> u8 {
+        match self {
+            Self::V1(_) => 1,
+            Self::V2(_) => 2,
+        }
+    }
+
+    /// Returns the version 1 core segment, or `None` if this is another version.
+    pub fn as_v1(&self) -> Option<&CoreV1> {
+        match self {
+            Self::V1(core) => Some(core),
+            _ => None,
+        }
+    }
+
+    /// Returns the version 2 core segment, or `None` if this is another version.
+    pub fn as_v2(&self) -> Option<&CoreV2> {
+        match self {
+            Self::V2(core) => Some(core),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Core {
+    /// Returns the latest version of the core segment, with all fields not applicable.
+    fn default() -> Self {
+        Self::V2(CoreV2::default())
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct CoreV1 {
     pub sharing_notice: Notice,
@@ -36,13 +113,45 @@ pub struct CoreV1 {
     pub sensitive_data_processing: SensitiveDataProcessingV1,
     pub known_child_sensitive_data_consents: KnownChildSensitiveDataConsentsV1,
     pub personal_data_consent: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl CoreV1 {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                ("sharing_opt_out_notice", self.sharing_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+                (
+                    "sensitive_data_processing_opt_out_notice",
+                    self.sensitive_data_processing_opt_out_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessingV1 {
     pub racial_or_ethnic_origin: Consent,
@@ -59,14 +168,51 @@ pub struct SensitiveDataProcessingV1 {
     pub mail_email_or_text_messages: Consent,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    SensitiveDataProcessingV1,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_or_philosophical_beliefs,
+        health_data,
+        sex_life_or_sexual_orientation,
+        citizenship_or_immigration_status,
+        genetic_unique_identification,
+        biometric_unique_identification,
+        precise_geolocation_data,
+        identification_documents,
+        financial_data,
+        union_membership,
+        mail_email_or_text_messages
+    ]
+);
+
+impl_sensitive_data_categories!(SensitiveDataProcessingV1, Consent);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsentsV1 {
     pub from_13_to_16: Consent,
     pub under_13: Consent,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    KnownChildSensitiveDataConsentsV1,
+    Consent,
+    [from_13_to_16, under_13]
+);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct CoreV2 {
     pub sharing_notice: Notice,
@@ -81,13 +227,45 @@ pub struct CoreV2 {
     pub sensitive_data_processing: SensitiveDataProcessingV2,
     pub known_child_sensitive_data_consents: KnownChildSensitiveDataConsentsV2,
     pub personal_data_consent: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl CoreV2 {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                ("sharing_opt_out_notice", self.sharing_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+                (
+                    "sensitive_data_processing_opt_out_notice",
+                    self.sensitive_data_processing_opt_out_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessingV2 {
     pub racial_or_ethnic_origin: Consent,
@@ -108,7 +286,37 @@ pub struct SensitiveDataProcessingV2 {
     pub transgender_or_nonbinary_status: Consent,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    SensitiveDataProcessingV2,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_or_philosophical_beliefs,
+        health_data,
+        sex_life_or_sexual_orientation,
+        citizenship_or_immigration_status,
+        genetic_unique_identification,
+        biometric_unique_identification,
+        precise_geolocation_data,
+        identification_documents,
+        financial_account_data,
+        union_membership,
+        mail_email_or_text_messages,
+        general_health_data,
+        crime_victim_status,
+        national_origin,
+        transgender_or_nonbinary_status
+    ]
+);
+
+impl_sensitive_data_categories!(SensitiveDataProcessingV2, Consent);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsentsV2 {
     pub process_sensitive_data_from_13_to_16: Consent,
@@ -116,13 +324,64 @@ pub struct KnownChildSensitiveDataConsentsV2 {
     pub process_personal_data_from_16_to_17: Consent,
 }
 
+impl_sensitive_data_items!(
+    KnownChildSensitiveDataConsentsV2,
+    Consent,
+    [
+        process_sensitive_data_from_13_to_16,
+        process_sensitive_data_under_13,
+        process_personal_data_from_16_to_17
+    ]
+);
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::sections::SectionDecodeError;
+    use crate::sections::{EncodableSection, SectionDecodeError};
     use std::str::FromStr;
     use test_case::test_case;
 
+    fn sensitive_data_processing_v2() -> SensitiveDataProcessingV2 {
+        SensitiveDataProcessingV2 {
+            racial_or_ethnic_origin: Consent::Consent,
+            religious_or_philosophical_beliefs: Consent::NotApplicable,
+            health_data: Consent::NotApplicable,
+            sex_life_or_sexual_orientation: Consent::NotApplicable,
+            citizenship_or_immigration_status: Consent::NotApplicable,
+            genetic_unique_identification: Consent::NotApplicable,
+            biometric_unique_identification: Consent::NoConsent,
+            precise_geolocation_data: Consent::NotApplicable,
+            identification_documents: Consent::NotApplicable,
+            financial_account_data: Consent::NotApplicable,
+            union_membership: Consent::NotApplicable,
+            mail_email_or_text_messages: Consent::NotApplicable,
+            general_health_data: Consent::NotApplicable,
+            crime_victim_status: Consent::NotApplicable,
+            national_origin: Consent::NotApplicable,
+            transgender_or_nonbinary_status: Consent::NoConsent,
+        }
+    }
+
+    #[test_case(0 => None ; "zero")]
+    #[test_case(1 => Some(Consent::Consent) ; "first")]
+    #[test_case(7 => Some(Consent::NoConsent) ; "biometric")]
+    #[test_case(16 => Some(Consent::NoConsent) ; "last")]
+    #[test_case(17 => None ; "out of range")]
+    fn sensitive_data_category(index: u8) -> Option<Consent> {
+        sensitive_data_processing_v2().get(index)
+    }
+
+    #[test]
+    fn sensitive_data_categories() {
+        let sdp = sensitive_data_processing_v2();
+        let categories = sdp.categories().collect::<Vec<_>>();
+
+        assert_eq!(categories.len(), 16);
+        assert_eq!(categories[0], (1, Consent::Consent));
+        assert_eq!(categories[15], (16, Consent::NoConsent));
+        assert!(categories.iter().all(|&(i, c)| sdp.get(i) == Some(c)));
+    }
+
     #[test]
     fn parse() {
         let test_cases = [
@@ -295,11 +554,147 @@ mod tests {
         }
     }
 
+    #[test]
+    fn default_is_latest_version() {
+        let usnat = UsNat::default();
+
+        assert!(matches!(usnat.core, Core::V2(_)));
+        assert_eq!(UsNat::from_str(&usnat.encode().unwrap()).unwrap(), usnat);
+    }
+
+    #[test_case("BAAAAAAAAQA" => (1, true, false) ; "v1")]
+    #[test_case("CAAAAAAAAAWA.Q" => (2, false, true) ; "v2")]
+    fn version(s: &str) -> (u8, bool, bool) {
+        let usnat = UsNat::from_str(s).unwrap();
+        assert_eq!(usnat.version(), usnat.core.version());
+        (
+            usnat.version(),
+            usnat.core.as_v1().is_some(),
+            usnat.core.as_v2().is_some(),
+        )
+    }
+
+    #[test_case("BAAAAAAAAQA" => "BAAAAAAAAQA" ; "v1 not applicable")]
+    #[test_case("BVVVVVVVVWA.YA" => "BVVVVVVVVWA.YA" ; "v1 with gpc")]
+    #[test_case("CAAAAAAAAAWA.Q" => "CAAAAAAAAAWA.QA" ; "v2 gpc segment padded")]
+    fn encode(s: &str) -> String {
+        UsNat::from_str(s).unwrap().encode().unwrap()
+    }
+
     #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
     #[test_case("123" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "decode error")]
     #[test_case("gqgkgAAAAEA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version")]
     #[test_case("BVVVVVVVVWA.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("BVVVVVVVVGA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BVVVVVVVV2A" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
     fn error(s: &str) -> SectionDecodeError {
         UsNat::from_str(s).unwrap_err()
     }
+
+    mod round_trip {
+        use super::*;
+        use crate::sections::us_common::strategies::{next, values};
+        use proptest::prelude::*;
+
+        prop_compose! {
+            fn core_v1()(
+                values in values(26),
+                mspa_covered_transaction in any::<bool>(),
+            ) -> CoreV1 {
+                let v = &mut values.into_iter();
+                CoreV1 {
+                    sharing_notice: next(v),
+                    sale_opt_out_notice: next(v),
+                    sharing_opt_out_notice: next(v),
+                    targeted_advertising_opt_out_notice: next(v),
+                    sensitive_data_processing_opt_out_notice: next(v),
+                    sensitive_data_limit_use_notice: next(v),
+                    sale_opt_out: next(v),
+                    sharing_opt_out: next(v),
+                    targeted_advertising_opt_out: next(v),
+                    sensitive_data_processing: SensitiveDataProcessingV1 {
+                        racial_or_ethnic_origin: next(v),
+                        religious_or_philosophical_beliefs: next(v),
+                        health_data: next(v),
+                        sex_life_or_sexual_orientation: next(v),
+                        citizenship_or_immigration_status: next(v),
+                        genetic_unique_identification: next(v),
+                        biometric_unique_identification: next(v),
+                        precise_geolocation_data: next(v),
+                        identification_documents: next(v),
+                        financial_data: next(v),
+                        union_membership: next(v),
+                        mail_email_or_text_messages: next(v),
+                    },
+                    known_child_sensitive_data_consents: KnownChildSensitiveDataConsentsV1 {
+                        from_13_to_16: next(v),
+                        under_13: next(v),
+                    },
+                    personal_data_consent: next(v),
+                    mspa_covered_transaction,
+                    mspa_opt_out_option_mode: next(v),
+                    mspa_service_provider_mode: next(v),
+                }
+            }
+        }
+
+        prop_compose! {
+            fn core_v2()(
+                values in values(31),
+                mspa_covered_transaction in any::<bool>(),
+            ) -> CoreV2 {
+                let v = &mut values.into_iter();
+                CoreV2 {
+                    sharing_notice: next(v),
+                    sale_opt_out_notice: next(v),
+                    sharing_opt_out_notice: next(v),
+                    targeted_advertising_opt_out_notice: next(v),
+                    sensitive_data_processing_opt_out_notice: next(v),
+                    sensitive_data_limit_use_notice: next(v),
+                    sale_opt_out: next(v),
+                    sharing_opt_out: next(v),
+                    targeted_advertising_opt_out: next(v),
+                    sensitive_data_processing: SensitiveDataProcessingV2 {
+                        racial_or_ethnic_origin: next(v),
+                        religious_or_philosophical_beliefs: next(v),
+                        health_data: next(v),
+                        sex_life_or_sexual_orientation: next(v),
+                        citizenship_or_immigration_status: next(v),
+                        genetic_unique_identification: next(v),
+                        biometric_unique_identification: next(v),
+                        precise_geolocation_data: next(v),
+                        identification_documents: next(v),
+                        financial_account_data: next(v),
+                        union_membership: next(v),
+                        mail_email_or_text_messages: next(v),
+                        general_health_data: next(v),
+                        crime_victim_status: next(v),
+                        national_origin: next(v),
+                        transgender_or_nonbinary_status: next(v),
+                    },
+                    known_child_sensitive_data_consents: KnownChildSensitiveDataConsentsV2 {
+                        process_sensitive_data_from_13_to_16: next(v),
+                        process_sensitive_data_under_13: next(v),
+                        process_personal_data_from_16_to_17: next(v),
+                    },
+                    personal_data_consent: next(v),
+                    mspa_covered_transaction,
+                    mspa_opt_out_option_mode: next(v),
+                    mspa_service_provider_mode: next(v),
+                }
+            }
+        }
+
+        fn usnat() -> impl Strategy<Value = UsNat> {
+            let core = prop_oneof![core_v1().prop_map(Core::V1), core_v2().prop_map(Core::V2)];
+            (core, any::<Option<bool>>()).prop_map(|(core, gpc)| UsNat { core, gpc })
+        }
+
+        proptest! {
+            #[test]
+            fn encode_decode(usnat in usnat()) {
+                prop_assert_eq!(UsNat::from_str(&usnat.encode()?)?, usnat);
+            }
+        }
+    }
 }
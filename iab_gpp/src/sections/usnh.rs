@@ -1,9 +1,13 @@
 use crate::sections::us_common::{
-    parse_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut,
+    impl_sensitive_data_items, parse_mspa_covered_transaction, validate_mspa_modes,
+    write_mspa_covered_transaction, Consent, MspaMode, Notice, OptOut, ValidationError,
 };
-use iab_gpp_derive::{FromDataReader, GPPSection};
+use iab_gpp_derive::{FromDataReader, GPPSection, ToDataWriter};
 
-#[derive(Debug, Eq, PartialEq, GPPSection)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, GPPSection)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsNh {
@@ -12,7 +16,12 @@ pub struct UsNh {
     pub gpc: Option<bool>,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -24,13 +33,40 @@ pub struct Core {
     pub sensitive_data_processing: SensitiveDataProcessing,
     pub known_child_sensitive_data_consents: KnownChildSensitiveDataConsents,
     pub additional_data_processing_consent: Consent,
-    #[gpp(parse_with = parse_mspa_covered_transaction)]
+    #[gpp(
+        parse_with = parse_mspa_covered_transaction,
+        write_with = write_mspa_covered_transaction
+    )]
     pub mspa_covered_transaction: bool,
     pub mspa_opt_out_option_mode: MspaMode,
     pub mspa_service_provider_mode: MspaMode,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl Core {
+    /// Checks the consistency of the MSPA modes with the opt-out notices.
+    ///
+    /// See [`validate_mspa_modes`] for the rules being checked.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        validate_mspa_modes(
+            self.mspa_service_provider_mode,
+            self.mspa_opt_out_option_mode,
+            &[
+                ("sale_opt_out_notice", self.sale_opt_out_notice),
+                (
+                    "targeted_advertising_opt_out_notice",
+                    self.targeted_advertising_opt_out_notice,
+                ),
+            ],
+        )
+    }
+}
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
@@ -43,10 +79,105 @@ pub struct SensitiveDataProcessing {
     pub precise_geolocation_data: Consent,
 }
 
-#[derive(Debug, Eq, PartialEq, FromDataReader)]
+impl_sensitive_data_items!(
+    SensitiveDataProcessing,
+    Consent,
+    [
+        racial_or_ethnic_origin,
+        religious_beliefs,
+        health_data,
+        sex_life_or_sexual_orientation,
+        citizenship_or_immigration_status,
+        genetic_unique_identification,
+        biometric_unique_identification,
+        precise_geolocation_data
+    ]
+);
+
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, FromDataReader, ToDataWriter,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsents {
     pub process_sensitive_data_from_known_child: Consent,
     pub sell_personal_data_from_13_to_16: Consent,
     pub process_personal_data_from_13_to_16: Consent,
 }
+
+impl_sensitive_data_items!(
+    KnownChildSensitiveDataConsents,
+    Consent,
+    [
+        process_sensitive_data_from_known_child,
+        sell_personal_data_from_13_to_16,
+        process_personal_data_from_13_to_16
+    ]
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::{EncodableSection, SectionDecodeError};
+    use std::str::FromStr;
+    use test_case::test_case;
+
+    #[test]
+    fn parse() {
+        let actual = UsNh::from_str("BVVVVVVY.YA").unwrap();
+        let expected = UsNh {
+            core: Core {
+                processing_notice: Notice::Provided,
+                sale_opt_out_notice: Notice::Provided,
+                targeted_advertising_opt_out_notice: Notice::Provided,
+                sale_opt_out: OptOut::OptedOut,
+                targeted_advertising_opt_out: OptOut::OptedOut,
+                sensitive_data_processing: SensitiveDataProcessing {
+                    racial_or_ethnic_origin: Consent::NoConsent,
+                    religious_beliefs: Consent::NoConsent,
+                    health_data: Consent::NoConsent,
+                    sex_life_or_sexual_orientation: Consent::NoConsent,
+                    citizenship_or_immigration_status: Consent::NoConsent,
+                    genetic_unique_identification: Consent::NoConsent,
+                    biometric_unique_identification: Consent::NoConsent,
+                    precise_geolocation_data: Consent::NoConsent,
+                },
+                known_child_sensitive_data_consents: KnownChildSensitiveDataConsents {
+                    process_sensitive_data_from_known_child: Consent::NoConsent,
+                    sell_personal_data_from_13_to_16: Consent::NoConsent,
+                    process_personal_data_from_13_to_16: Consent::NoConsent,
+                },
+                additional_data_processing_consent: Consent::NoConsent,
+                mspa_covered_transaction: true,
+                mspa_opt_out_option_mode: MspaMode::Yes,
+                mspa_service_provider_mode: MspaMode::No,
+            },
+            gpc: Some(true),
+        };
+
+        assert_eq!(actual, expected);
+        assert!(actual.core.validate().is_empty());
+    }
+
+    #[test]
+    fn default_is_not_applicable() {
+        assert_eq!(UsNh::default().encode().unwrap(), "BAAAAACA");
+    }
+
+    #[test_case("BVVVVVVY" ; "core only")]
+    #[test_case("BVVVVVVY.YA" ; "with gpc")]
+    fn encode(s: &str) {
+        assert_eq!(UsNh::from_str(s).unwrap().encode().unwrap(), s);
+    }
+
+    #[test_case("" => matches SectionDecodeError::Read(_) ; "empty string")]
+    #[test_case("CVVVVVVY.YA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version")]
+    #[test_case("BVVVVVVY.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("BAAAAAAA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 0")]
+    #[test_case("BAAAAADA" => matches SectionDecodeError::InvalidFieldValue { .. } ; "mspa covered transaction 3")]
+    fn error(s: &str) -> SectionDecodeError {
+        UsNh::from_str(s).unwrap_err()
+    }
+}
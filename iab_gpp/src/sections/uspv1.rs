@@ -1,113 +1,375 @@
-use crate::sections::{DecodableSection, SectionDecodeError, SectionId};
-use std::str::{Chars, FromStr};
+use crate::sections::char_reader::{CharFlag, CharReader};
+use crate::sections::{
+    field_path, DecodableSection, DecodeOptions, DecodeWithOptions, EncodableSection,
+    ExplainSection, FieldSchema, FieldSpan, Fields, Schema, SectionDecodeError, SectionEncodeError,
+    SectionId,
+};
+use std::str::FromStr;
 
 const USP_V1_VERSION: u8 = 1;
-const KIND: &str = "uspv1";
+pub(crate) const KIND: &str = "uspv1";
 
-#[derive(Debug, Eq, PartialEq)]
+macro_rules! usp_flag {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+        pub enum $name {
+            Yes,
+            No,
+            #[default]
+            NotApplicable,
+        }
+
+        impl CharFlag for $name {
+            fn from_char(c: char) -> Option<Self> {
+                match c {
+                    'Y' => Some(Self::Yes),
+                    'N' => Some(Self::No),
+                    '-' => Some(Self::NotApplicable),
+                    _ => None,
+                }
+            }
+
+            fn to_char(self) -> char {
+                match self {
+                    Self::Yes => 'Y',
+                    Self::No => 'N',
+                    Self::NotApplicable => '-',
+                }
+            }
+        }
+
+        #[cfg(feature = "compat")]
+        #[allow(deprecated)]
+        impl From<Flag> for $name {
+            fn from(flag: Flag) -> Self {
+                match flag {
+                    Flag::Yes => Self::Yes,
+                    Flag::No => Self::No,
+                    Flag::NotApplicable => Self::NotApplicable,
+                }
+            }
+        }
+
+        #[cfg(feature = "compat")]
+        #[allow(deprecated)]
+        impl From<$name> for Flag {
+            fn from(value: $name) -> Self {
+                match value {
+                    $name::Yes => Self::Yes,
+                    $name::No => Self::No,
+                    $name::NotApplicable => Self::NotApplicable,
+                }
+            }
+        }
+
+        #[cfg(feature = "compat")]
+        #[allow(deprecated)]
+        impl PartialEq<Flag> for $name {
+            fn eq(&self, other: &Flag) -> bool {
+                *self == Self::from(*other)
+            }
+        }
+    };
+}
+
+/// The value of any US Privacy flag, as used by all fields before they got their own types.
+///
+/// It converts to and from [`Notice`], [`OptOut`] and [`Covered`], and compares with them, so
+/// that code written against the former API keeps working while it is being migrated.
+#[cfg(feature = "compat")]
+#[deprecated(note = "use the Notice, OptOut and Covered types instead")]
+#[derive(Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Copy, Clone)]
 pub enum Flag {
     Yes,
     No,
     NotApplicable,
 }
 
-impl Flag {
-    fn from_char(c: char) -> Option<Self> {
-        match c {
-            'Y' => Some(Self::Yes),
-            'N' => Some(Self::No),
-            '-' => Some(Self::NotApplicable),
-            _ => None,
-        }
-    }
-}
+usp_flag!(
+    /// Whether explicit notice and opportunity to opt out of the sale of data was provided.
+    Notice
+);
+usp_flag!(
+    /// Whether the user opted out of the sale of their personal information.
+    OptOut
+);
+usp_flag!(
+    /// Whether the publisher is a signatory to the IAB Limited Service Provider Agreement
+    /// and the transaction is covered by it.
+    Covered
+);
 
 // See https://github.com/InteractiveAdvertisingBureau/USPrivacy/blob/master/CCPA/US%20Privacy%20String.md#us-privacy-string-format
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde-iab-names", serde(rename_all = "PascalCase"))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct UspV1 {
-    pub opt_out_notice: Flag,
-    pub opt_out_sale: Flag,
-    pub lspa_covered_transaction: Flag,
+    pub opt_out_notice: Notice,
+    pub opt_out_sale: OptOut,
+    pub lspa_covered_transaction: Covered,
+}
+
+impl UspV1 {
+    /// Returns a builder for a section where all flags are initially not applicable.
+    pub fn builder() -> UspV1Builder {
+        UspV1Builder::default()
+    }
+
+    /// Encodes this section into its 4-character string representation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::uspv1::{Notice, OptOut, UspV1};
+    ///
+    /// let usp = UspV1::builder()
+    ///     .opt_out_notice(Notice::Yes)
+    ///     .opt_out_sale(OptOut::No)
+    ///     .build();
+    ///
+    /// assert_eq!(usp.encode(), "1YN-");
+    /// ```
+    pub fn encode(&self) -> String {
+        let mut s = String::with_capacity(4);
+        s.push((b'0' + USP_V1_VERSION) as char);
+        s.push(self.opt_out_notice.to_char());
+        s.push(self.opt_out_sale.to_char());
+        s.push(self.lspa_covered_transaction.to_char());
+        s
+    }
+}
+
+/// A builder for [`UspV1`] sections.
+///
+/// Created with the method [`builder`](UspV1::builder).
+#[derive(Clone, Debug, Default)]
+pub struct UspV1Builder {
+    opt_out_notice: Notice,
+    opt_out_sale: OptOut,
+    lspa_covered_transaction: Covered,
+}
+
+impl UspV1Builder {
+    pub fn opt_out_notice(mut self, notice: impl Into<Notice>) -> Self {
+        self.opt_out_notice = notice.into();
+        self
+    }
+
+    pub fn opt_out_sale(mut self, opt_out: impl Into<OptOut>) -> Self {
+        self.opt_out_sale = opt_out.into();
+        self
+    }
+
+    pub fn lspa_covered_transaction(mut self, covered: impl Into<Covered>) -> Self {
+        self.lspa_covered_transaction = covered.into();
+        self
+    }
+
+    pub fn build(self) -> UspV1 {
+        UspV1 {
+            opt_out_notice: self.opt_out_notice,
+            opt_out_sale: self.opt_out_sale,
+            lspa_covered_transaction: self.lspa_covered_transaction,
+        }
+    }
 }
 
 impl DecodableSection for UspV1 {
     const ID: SectionId = SectionId::UspV1;
 }
 
+impl DecodeWithOptions for UspV1 {
+    fn decode_with_options(
+        s: &str,
+        _: &mut Vec<u8>,
+        options: DecodeOptions,
+    ) -> Result<Self, SectionDecodeError> {
+        let usp = s.parse()?;
+
+        let trailing_chars = s.chars().count().saturating_sub(4);
+        if options.strict_trailing_data && trailing_chars > 0 {
+            return Err(SectionDecodeError::TrailingData {
+                bits: trailing_chars as u64 * 8,
+            });
+        }
+
+        Ok(usp)
+    }
+}
+
+// each field is a single character of 8 bits
+impl ExplainSection for UspV1 {
+    fn read_spans(s: &str) -> Result<Vec<FieldSpan>, SectionDecodeError> {
+        let names = [
+            "version",
+            "opt_out_notice",
+            "opt_out_sale",
+            "lspa_covered_transaction",
+        ];
+        Ok(names
+            .iter()
+            .zip(s.char_indices())
+            .map(|(name, (i, c))| {
+                let start = i as u64 * 8;
+                let len = c.len_utf8() as u64 * 8;
+                let mut span =
+                    FieldSpan::new(name.to_string(), 0, start..start + len, s.as_bytes());
+                // the version is a digit rather than an integer
+                if *name == "version" {
+                    span.value = Some(c.to_string());
+                }
+                span
+            })
+            .collect())
+    }
+}
+
+impl EncodableSection for UspV1 {
+    fn encode(&self) -> Result<String, SectionEncodeError> {
+        Ok(UspV1::encode(self))
+    }
+
+    /// Returns the same string as [`encode`](Self::encode), which has a fixed length.
+    fn encode_compact(&self) -> Result<String, SectionEncodeError> {
+        Ok(UspV1::encode(self))
+    }
+
+    /// Returns the bit length of the 4 characters of the section, which is not base64 encoded.
+    fn raw_len_bits(&self) -> Result<usize, SectionEncodeError> {
+        Ok(4 * 8)
+    }
+}
+
+impl Fields for UspV1 {
+    fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+        self.opt_out_notice
+            .push_fields(&field_path(prefix, "opt_out_notice"), out);
+        self.opt_out_sale
+            .push_fields(&field_path(prefix, "opt_out_sale"), out);
+        self.lspa_covered_transaction
+            .push_fields(&field_path(prefix, "lspa_covered_transaction"), out);
+    }
+}
+
+// each flag is a single character rather than a bit field
+impl Schema for UspV1 {
+    fn push_schema(prefix: &str, out: &mut Vec<FieldSchema>) {
+        out.push(FieldSchema::new(
+            &field_path(prefix, "version"),
+            "u8",
+            "char",
+            Some(8),
+        ));
+        for (name, rust_type) in [
+            ("opt_out_notice", "Notice"),
+            ("opt_out_sale", "OptOut"),
+            ("lspa_covered_transaction", "Covered"),
+        ] {
+            out.push(FieldSchema::new(
+                &field_path(prefix, name),
+                rust_type,
+                "char",
+                Some(8),
+            ));
+        }
+    }
+}
+
 impl FromStr for UspV1 {
     type Err = SectionDecodeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut chars = s.chars();
-
-        let version = chars
-            .next()
-            .ok_or(SectionDecodeError::UnexpectedEndOfString(s.to_string()))?;
-        let version = version
-            .to_digit(10)
-            .ok_or(SectionDecodeError::InvalidCharacter {
-                character: version,
-                kind: KIND,
-                s: s.to_string(),
-            })? as u8;
-        if version != USP_V1_VERSION {
-            return Err(SectionDecodeError::InvalidSectionVersion {
-                expected: USP_V1_VERSION,
-                found: version,
-            });
-        }
+        let mut r = CharReader::new(KIND, s);
+        r.read_version(USP_V1_VERSION)?;
 
         Ok(Self {
-            opt_out_notice: parse_next_char(&mut chars, s)?,
-            opt_out_sale: parse_next_char(&mut chars, s)?,
-            lspa_covered_transaction: parse_next_char(&mut chars, s)?,
+            opt_out_notice: r.read_flag()?,
+            opt_out_sale: r.read_flag()?,
+            lspa_covered_transaction: r.read_flag()?,
         })
     }
 }
 
-fn parse_next_char(chars: &mut Chars, original_str: &str) -> Result<Flag, SectionDecodeError> {
-    let char = chars
-        .next()
-        .ok_or(SectionDecodeError::UnexpectedEndOfString(
-            original_str.to_string(),
-        ))?;
-
-    Flag::from_char(char).ok_or(SectionDecodeError::InvalidCharacter {
-        character: char,
-        kind: KIND,
-        s: original_str.to_string(),
-    })
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use test_case::test_case;
 
     #[test_case("1YN-" => UspV1 {
-        opt_out_notice: Flag::Yes,
-        opt_out_sale: Flag::No,
-        lspa_covered_transaction: Flag::NotApplicable,
+        opt_out_notice: Notice::Yes,
+        opt_out_sale: OptOut::No,
+        lspa_covered_transaction: Covered::NotApplicable,
     } ; "mix")]
     #[test_case("1NNN" => UspV1 {
-        opt_out_notice: Flag::No,
-        opt_out_sale: Flag::No,
-        lspa_covered_transaction: Flag::No,
+        opt_out_notice: Notice::No,
+        opt_out_sale: OptOut::No,
+        lspa_covered_transaction: Covered::No,
     } ; "all no")]
     #[test_case("1YYY" => UspV1 {
-        opt_out_notice: Flag::Yes,
-        opt_out_sale: Flag::Yes,
-        lspa_covered_transaction: Flag::Yes,
+        opt_out_notice: Notice::Yes,
+        opt_out_sale: OptOut::Yes,
+        lspa_covered_transaction: Covered::Yes,
     } ; "all yes")]
     fn parse(s: &str) -> UspV1 {
         UspV1::from_str(s).unwrap()
     }
 
+    #[test_case("1YN-" ; "mix")]
+    #[test_case("1NNN" ; "all no")]
+    #[test_case("1---" ; "all not applicable")]
+    fn encode_round_trip(s: &str) {
+        assert_eq!(UspV1::from_str(s).unwrap().encode(), s);
+    }
+
+    #[test]
+    fn builder() {
+        let usp = UspV1::builder()
+            .lspa_covered_transaction(Covered::Yes)
+            .build();
+        assert_eq!(
+            usp,
+            UspV1 {
+                opt_out_notice: Notice::NotApplicable,
+                opt_out_sale: OptOut::NotApplicable,
+                lspa_covered_transaction: Covered::Yes,
+            }
+        );
+        assert_eq!(usp.encode(), "1--Y");
+    }
+
+    #[cfg(feature = "compat")]
+    #[test]
+    #[allow(deprecated)]
+    fn compat_flag() {
+        let usp = UspV1::builder()
+            .opt_out_notice(Flag::Yes)
+            .opt_out_sale(Flag::No)
+            .build();
+        assert_eq!(usp.encode(), "1YN-");
+        assert_eq!(usp.opt_out_notice, Flag::Yes);
+        assert_eq!(usp.lspa_covered_transaction, Flag::NotApplicable);
+        assert_eq!(Flag::from(usp.opt_out_sale), Flag::No);
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(UspV1::default(), UspV1::builder().build());
+        assert_eq!(UspV1::default().encode(), "1---");
+    }
+
     #[test_case("ZYN-" => matches SectionDecodeError::InvalidCharacter { character: 'Z', .. } ; "invalid version character")]
     #[test_case("2YN-" => matches SectionDecodeError::InvalidSectionVersion {
         expected: USP_V1_VERSION,
         found: 2
     } ; "invalid version number")]
+    #[test_case("2YNN" => matches SectionDecodeError::InvalidSectionVersion {
+        expected: USP_V1_VERSION,
+        found: 2
+    } ; "version 2")]
     #[test_case("" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "empty string")]
     #[test_case("1" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "header only")]
     #[test_case("1N" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "missing characters")]
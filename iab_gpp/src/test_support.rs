@@ -0,0 +1,102 @@
+//! Helpers for writing tests of section decoders and encoders.
+//!
+//! Fixtures are written as strings of binary digits, in which any other character, such as a
+//! space separating fields, is ignored. This is the format used by the tests of this crate.
+//!
+//! This module is only available with the `test-util` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use iab_gpp::assert_bits_eq;
+//! use iab_gpp::codec::{DataReader, DataWriter};
+//! use iab_gpp::test_support::bits_from_str;
+//!
+//! let bytes = bits_from_str("000011 0011");
+//! let mut r = DataReader::new(&bytes);
+//! assert_eq!(r.read_fixed_integer::<u8>(6).unwrap(), 3);
+//! assert_eq!(r.read_fibonacci_integer::<u8>().unwrap(), 3);
+//!
+//! let mut w = DataWriter::new();
+//! w.write_fixed_integer(6, 3u8).unwrap();
+//! w.write_fibonacci_integer(3).unwrap();
+//! assert_bits_eq!(w.into_bytes(), "000011 0011");
+//! ```
+
+/// Converts a string of binary digits into bytes, most significant bit first.
+///
+/// Characters other than `0` and `1` are ignored. The last byte is completed with zero bits.
+pub fn bits_from_str(s: &str) -> Vec<u8> {
+    let bits = s
+        .bytes()
+        .filter(|&c| c == b'0' || c == b'1')
+        .collect::<Vec<_>>();
+
+    bits.chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |acc, (i, &c)| acc | u8::from(c == b'1') << (7 - i))
+        })
+        .collect()
+}
+
+/// Converts bytes into a string of binary digits, most significant bit first.
+///
+/// This is the inverse of [`bits_from_str`] for strings made of whole bytes.
+pub fn bytes_to_bit_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:08b}")).collect()
+}
+
+/// Asserts that bytes are equal to a string of binary digits, as read by [`bits_from_str`].
+///
+/// On failure, both sides are displayed as binary digits, which makes the differing bits
+/// easier to locate than in a list of bytes.
+#[macro_export]
+macro_rules! assert_bits_eq {
+    ($bytes:expr, $bits:expr $(,)?) => {
+        match &$bytes {
+            bytes => {
+                let left: &[u8] = ::core::convert::AsRef::as_ref(bytes);
+                let right = $crate::test_support::bits_from_str($bits);
+                assert_eq!(
+                    $crate::test_support::bytes_to_bit_string(left),
+                    $crate::test_support::bytes_to_bit_string(&right),
+                );
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("" => Vec::<u8>::new() ; "empty")]
+    #[test_case("1" => vec![0x80] ; "single bit")]
+    #[test_case("0000 0101" => vec![5] ; "separators")]
+    #[test_case("11111111 1" => vec![0xff, 0x80] ; "partial last byte")]
+    fn from_str(s: &str) -> Vec<u8> {
+        bits_from_str(s)
+    }
+
+    #[test_case(&[] => "" ; "empty")]
+    #[test_case(&[5, 0xff] => "0000010111111111" ; "two bytes")]
+    fn to_bit_string(bytes: &[u8]) -> String {
+        bytes_to_bit_string(bytes)
+    }
+
+    #[test]
+    fn round_trip() {
+        let s = "0110100111000011";
+        assert_eq!(bytes_to_bit_string(&bits_from_str(s)), s);
+    }
+
+    #[test]
+    #[should_panic]
+    fn assert_bits_eq_fails() {
+        assert_bits_eq!([5u8], "00000100");
+    }
+}
@@ -0,0 +1,134 @@
+//! Cache of parsed GPP strings.
+
+use crate::v1::{GPPDecodeError, GPPString, ParseOptions};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// A thread-safe cache of parsed GPP strings, keyed by the input string.
+///
+/// Services handling many requests often see the same consent strings repeatedly. This cache
+/// keeps the most recently used strings, so that identical inputs are only parsed once.
+///
+/// Parsed strings are shared as [`Arc<GPPString>`]. Strings which fail to parse are not cached.
+///
+/// The cache is guarded by a mutex, which is never held while a string is being parsed. For more
+/// advanced eviction policies, such as time-based expiration, use a dedicated cache crate like
+/// `moka` with [`GPPString::parse_str`] instead.
+///
+/// This type is available with the `cache` feature.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::v1::GPPCache;
+/// use std::num::NonZeroUsize;
+/// use std::sync::Arc;
+///
+/// let cache = GPPCache::new(NonZeroUsize::new(1000).unwrap());
+///
+/// let a = cache.get_or_parse("DBABTA~1YNN").unwrap();
+/// let b = cache.get_or_parse("DBABTA~1YNN").unwrap();
+///
+/// assert!(Arc::ptr_eq(&a, &b));
+/// ```
+pub struct GPPCache {
+    entries: Mutex<LruCache<Box<str>, Arc<GPPString>>>,
+    options: ParseOptions,
+}
+
+impl GPPCache {
+    /// Creates a cache holding at most `capacity` parsed strings.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self::with_options(capacity, ParseOptions::default())
+    }
+
+    /// Creates a cache holding at most `capacity` parsed strings, parsed with the given options.
+    pub fn with_options(capacity: NonZeroUsize, options: ParseOptions) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            options,
+        }
+    }
+
+    /// Returns the parsed string for the given input, parsing it if it is not cached yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if the string cannot be parsed.
+    pub fn get_or_parse(&self, s: &str) -> Result<Arc<GPPString>, GPPDecodeError> {
+        if let Some(gpp_str) = self.entries().get(s) {
+            return Ok(Arc::clone(gpp_str));
+        }
+
+        let gpp_str = Arc::new(GPPString::parse_str_with_options(s, &self.options)?);
+        self.entries().put(Box::from(s), Arc::clone(&gpp_str));
+
+        Ok(gpp_str)
+    }
+
+    /// Returns the number of cached strings.
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns `true` if no string is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries().is_empty()
+    }
+
+    /// Removes all cached strings.
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    // the cache is always left in a consistent state, so a poisoned lock can be recovered
+    fn entries(&self) -> MutexGuard<'_, LruCache<Box<str>, Arc<GPPString>>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> GPPCache {
+        GPPCache::new(NonZeroUsize::new(capacity).unwrap())
+    }
+
+    #[test]
+    fn reuses_parsed_string() {
+        let cache = cache(2);
+        let a = cache.get_or_parse("DBABTA~1YNN").unwrap();
+        let b = cache.get_or_parse("DBABTA~1YNN").unwrap();
+
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache(2);
+        let a = cache.get_or_parse("DBABTA~1YNN").unwrap();
+        cache.get_or_parse("DBABTA~1NNN").unwrap();
+        cache.get_or_parse("DBABTA~1YNN").unwrap();
+        cache.get_or_parse("DBABTA~1YYN").unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&a, &cache.get_or_parse("DBABTA~1YNN").unwrap()));
+    }
+
+    #[test]
+    fn errors_not_cached() {
+        let cache = cache(2);
+        assert!(cache.get_or_parse("invalid").is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn with_options() {
+        let options = ParseOptions::default().trim_whitespace(true);
+        let cache = GPPCache::with_options(NonZeroUsize::new(2).unwrap(), options);
+        assert!(cache.get_or_parse(" DBABTA~1YNN\n").is_ok());
+    }
+}
@@ -62,19 +62,35 @@
 //!
 //! If parsing fails, a [`GPPDecodeError`] is returned instead.
 //!
+use crate::core::base64::encode_bits;
 pub use crate::core::base64::DecodeError;
-use crate::core::{DataReader, DecodeExt};
-use crate::sections::{decode_section, DecodableSection, Section, SectionDecodeError, SectionId};
+use crate::core::{DataReader, DataWriter, DecodeExt};
+use crate::sections::char_reader::CharReader;
+use crate::sections::us_common::ValidationError;
+use crate::sections::uspv1::{self, UspV1};
+use crate::sections::{
+    decode_section, DecodableSection, DecodeOptions, FieldDiff, Framework, Section,
+    SectionDecodeError, SectionEncodeError, SectionId,
+};
 use fnv::FnvHashMap;
-use num_traits::FromPrimitive;
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::borrow::Cow;
+use std::collections::BTreeSet;
 use std::io;
 use std::iter::FusedIterator;
 use std::slice::Iter;
-use std::str::FromStr;
+use std::str::{FromStr, Split};
 use thiserror::Error;
 
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "cache")]
+pub use cache::GPPCache;
+
 const GPP_HEADER: u8 = 3;
 const GPP_VERSION: u8 = 1;
+// the encoded header of a string holding only the US Privacy section
+const USP_V1_HEADER: &str = "DBABTA";
 
 /// The error type for GPP String decoding operations.
 #[derive(Error, Debug)]
@@ -95,6 +111,12 @@ pub enum GPPDecodeError {
     /// If new versions are released, they will be implemented in other modules.
     #[error("invalid GPP version (expected {GPP_VERSION}, found {found})")]
     InvalidGPPVersion { found: u8 },
+    /// The header has a GPP version newer than the ones supported by this crate.
+    ///
+    /// The string may be well-formed, but its sections cannot be decoded by this module.
+    /// Use [`gpp_version`] to read the version of a string without decoding it.
+    #[error("unsupported GPP version {found} (maximum supported {GPP_VERSION})")]
+    UnsupportedGPPVersion { found: u8 },
     /// An I/O error occured while reading the string.
     ///
     /// This usually occurs if the input string is truncated.
@@ -107,6 +129,270 @@ pub enum GPPDecodeError {
     /// present in the string.
     #[error("ids do not match sections (number of ids {ids}, number of sections {sections}")]
     IdSectionMismatch { ids: usize, sections: usize },
+    /// The string header lists more sections than allowed by the parse options.
+    ///
+    /// The header is not read past the first section beyond the maximum, so `found` is always
+    /// `max + 1`.
+    #[error("too many sections (maximum {max}, found {found})")]
+    TooManySections { max: usize, found: usize },
+    /// The section at the position of an ID listed in the header cannot be decoded as such,
+    /// but can be decoded as another listed section, which suggests the sections do not follow
+    /// the order of the header.
+    ///
+    /// This error is only returned with the
+    /// [`verify_section_order`](ParseOptions::verify_section_order) option.
+    #[error("section at the position of {id} appears to be a {found} section")]
+    MisorderedSection { id: SectionId, found: SectionId },
+    /// A section present in the string cannot be decoded.
+    ///
+    /// This error is only returned by functions which decode a section along with the string,
+    /// such as [`decode_one`].
+    #[error("unable to decode section {id}")]
+    SectionDecode {
+        id: SectionId,
+        #[source]
+        source: SectionDecodeError,
+    },
+    /// The string is empty or only contains whitespace.
+    ///
+    /// This tells apart a missing consent string from a malformed one, such as a string with
+    /// an empty header, which returns [`NoHeaderFound`](Self::NoHeaderFound).
+    #[error("empty input")]
+    EmptyInput,
+}
+
+/// The error type for GPP String encoding operations.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum GPPEncodeError {
+    /// A section could not be encoded.
+    #[error("unable to encode section {id}")]
+    SectionEncode {
+        id: SectionId,
+        #[source]
+        source: SectionEncodeError,
+    },
+    /// A section could not be decoded, so it could not be encoded again.
+    #[error("unable to decode section {id}")]
+    SectionDecode {
+        id: SectionId,
+        #[source]
+        source: SectionDecodeError,
+    },
+    /// There are more sections than can be listed in the string header.
+    #[error("too many sections (maximum {max}, found {found})")]
+    TooManySections { max: usize, found: usize },
+    /// A header value does not fit in its declared bit width.
+    #[error("integer {value} does not fit in {bits} bits")]
+    IntegerOutOfRange { bits: u32, value: u64 },
+    /// The same section is present with different contents in strings being merged.
+    #[error("conflicting contents for section {0}")]
+    ConflictingSection(SectionId),
+    /// The same section is present more than once in the sections being assembled.
+    #[error("duplicate section {0}")]
+    DuplicateSection(SectionId),
+    /// An I/O error occurred while writing the string.
+    #[error("unable to write string")]
+    Write(#[from] io::Error),
+}
+
+/// Options controlling how a GPP string is parsed.
+///
+/// The default options are suitable for most uses. They can be changed with the provided
+/// setter methods before calling [`GPPString::parse_str_with_options`].
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::v1::{GPPString, ParseOptions};
+///
+/// let options = ParseOptions::default().max_sections(1);
+/// let r = GPPString::parse_str_with_options("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN", &options);
+///
+/// assert!(r.is_err());
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// The maximum number of sections a string may contain.
+    ///
+    /// Defaults to 32, which exceeds the number of sections defined by the specification.
+    pub max_sections: usize,
+    /// Whether leading and trailing ASCII whitespace is ignored.
+    ///
+    /// This is useful for strings coming from HTTP headers, cookies or shell pipelines, which
+    /// may carry surrounding spaces or a trailing newline. Whitespace within the string is
+    /// never ignored.
+    ///
+    /// Strings extracted from URLs are not percent-decoded: use
+    /// [`GPPString::parse_url_encoded`] for them.
+    ///
+    /// Defaults to `false`.
+    pub trim_whitespace: bool,
+    /// Whether truncated sections are decoded on a best-effort basis.
+    ///
+    /// Some CMPs produce TCF EU v2 strings whose core segment is cut after the purpose
+    /// fields. By default, decoding such a section fails with a
+    /// [`Read`](SectionDecodeError::Read) error. When this option is set, the missing fields
+    /// are decoded as if they were zero instead: no vendor consents, no vendor legitimate
+    /// interests and no publisher restrictions. All fields up to and including
+    /// `purpose_one_treatment` must still be present.
+    ///
+    /// This goes against the conservative approach of this crate, as the decoded section does
+    /// not reflect the full consent of the user. Only enable it if partial consent information
+    /// is preferable to none.
+    ///
+    /// The option applies to sections decoded through the [`Section`] enum, such as with
+    /// [`GPPString::decode_section`], but not to [`GPPString::decode`].
+    ///
+    /// Defaults to `false`.
+    pub lenient: bool,
+    /// Whether sections with data left after the last field of a segment are rejected.
+    ///
+    /// Encoders pad segments with zero bits, up to a whole group of 4 base64 characters at
+    /// most. By default, any data left after the last field of a segment is ignored. When this
+    /// option is set, decoding fails with [`SectionDecodeError::TrailingData`] if a segment
+    /// holds more unread bits than such padding, or if its padding bits are not zero. For the
+    /// US Privacy section, which is not base64 encoded, any character after the fourth one is
+    /// rejected.
+    ///
+    /// This helps CMP authors catch layout errors in their own encoders.
+    ///
+    /// As with [`lenient`](Self::lenient), the option applies to sections decoded through the
+    /// [`Section`] enum, but not to [`GPPString::decode`].
+    ///
+    /// Defaults to `false`.
+    pub strict_trailing_data: bool,
+    /// Whether the sections are checked to follow the order of the header.
+    ///
+    /// Sections are associated with the IDs listed in the header by position. Some
+    /// non-conformant producers emit them in a different order, which is only detected when
+    /// decoding the mis-associated sections.
+    ///
+    /// When this option is set, each section is decoded while parsing the string. If a section
+    /// cannot be decoded as the section listed at its position, but can be decoded as another
+    /// section listed in the header, parsing fails with
+    /// [`GPPDecodeError::MisorderedSection`]. Other decoding errors are still only reported
+    /// when decoding the section.
+    ///
+    /// Since all sections are decoded, this makes parsing noticeably slower.
+    ///
+    /// Defaults to `false`.
+    pub verify_section_order: bool,
+    /// Whether empty optional segments are ignored.
+    ///
+    /// Some producers emit a trailing `.` after the last segment of a section, such as
+    /// `BVVVVVg.`, which yields an empty segment. By default, decoding such a section fails
+    /// with [`SectionDecodeError::EmptySegment`]. When this option is set, empty optional
+    /// segments are skipped instead. The core segment may never be empty.
+    ///
+    /// As with [`lenient`](Self::lenient), the option applies to sections decoded through the
+    /// [`Section`] enum, but not to [`GPPString::decode`].
+    ///
+    /// Defaults to `false`.
+    pub allow_empty_segments: bool,
+    /// Whether enum fields holding a value without a matching variant are rejected.
+    ///
+    /// Fields of US sections such as notices, opt-outs and consents are 2-bit integers, of
+    /// which only 0 to 2 are defined. By default, the undefined value 3 is decoded as
+    /// `NotApplicable`, which cannot be told apart from a genuine 0. When this option is set,
+    /// decoding fails with [`SectionDecodeError::InvalidFieldValue`] instead, which helps
+    /// auditing malformed strings.
+    ///
+    /// As with [`lenient`](Self::lenient), the option applies to sections decoded through the
+    /// [`Section`] enum, but not to [`GPPString::decode`].
+    ///
+    /// Defaults to `false`.
+    pub strict_enums: bool,
+    /// Whether the `+` and `/` characters of the standard Base64 alphabet are accepted.
+    ///
+    /// GPP strings are encoded with the URL-safe alphabet, which uses `-` and `_` instead.
+    /// Strings which went through a standard Base64 encoder are rejected by default with
+    /// [`DecodeError::StandardAlphabet`]. When this option is set, `+` and `/` are decoded as
+    /// `-` and `_` respectively, in the header as well as in the sections.
+    ///
+    /// As with [`lenient`](Self::lenient), the option applies to sections decoded through the
+    /// [`Section`] enum, but not to [`GPPString::decode`].
+    ///
+    /// Defaults to `false`.
+    pub standard_base64: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_sections: 32,
+            trim_whitespace: false,
+            lenient: false,
+            strict_trailing_data: false,
+            verify_section_order: false,
+            allow_empty_segments: false,
+            strict_enums: false,
+            standard_base64: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Sets the maximum number of sections a string may contain.
+    pub fn max_sections(mut self, max_sections: usize) -> Self {
+        self.max_sections = max_sections;
+        self
+    }
+
+    /// Sets whether leading and trailing ASCII whitespace is ignored.
+    pub fn trim_whitespace(mut self, trim_whitespace: bool) -> Self {
+        self.trim_whitespace = trim_whitespace;
+        self
+    }
+
+    /// Sets whether truncated sections are decoded on a best-effort basis.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Sets whether sections with data left after the last field of a segment are rejected.
+    pub fn strict_trailing_data(mut self, strict_trailing_data: bool) -> Self {
+        self.strict_trailing_data = strict_trailing_data;
+        self
+    }
+
+    /// Sets whether the sections are checked to follow the order of the header.
+    pub fn verify_section_order(mut self, verify_section_order: bool) -> Self {
+        self.verify_section_order = verify_section_order;
+        self
+    }
+
+    /// Sets whether empty optional segments are ignored.
+    pub fn allow_empty_segments(mut self, allow_empty_segments: bool) -> Self {
+        self.allow_empty_segments = allow_empty_segments;
+        self
+    }
+
+    /// Sets whether enum fields holding a value without a matching variant are rejected.
+    pub fn strict_enums(mut self, strict_enums: bool) -> Self {
+        self.strict_enums = strict_enums;
+        self
+    }
+
+    /// Sets whether the `+` and `/` characters of the standard Base64 alphabet are accepted.
+    pub fn standard_base64(mut self, standard_base64: bool) -> Self {
+        self.standard_base64 = standard_base64;
+        self
+    }
+}
+
+impl From<&ParseOptions> for DecodeOptions {
+    fn from(options: &ParseOptions) -> Self {
+        Self {
+            lenient: options.lenient,
+            strict_trailing_data: options.strict_trailing_data,
+            allow_empty_segments: options.allow_empty_segments,
+            strict_enums: options.strict_enums,
+            standard_base64: options.standard_base64,
+        }
+    }
 }
 
 /// The representation of a parsed GPP consent string.
@@ -116,10 +402,19 @@ pub enum GPPDecodeError {
 ///
 /// It also offers methods to decode either a specific section, or all sections at once.
 ///
-#[derive(Debug)]
+/// As required by the specification, sections are associated with the IDs listed in the header
+/// by position: the first section is the one with the lowest ID, and so on. Sections which do
+/// not follow the order of the header are associated with the wrong IDs, which usually leads to
+/// decoding errors. Such strings can be detected early with the
+/// [`verify_section_order`](ParseOptions::verify_section_order) option.
+///
+#[derive(Clone, Debug)]
 pub struct GPPString {
+    // the header as it was parsed, or as encoded when building the string from sections
+    header: Box<str>,
     section_ids: Vec<SectionId>,
-    sections: FnvHashMap<SectionId, String>,
+    sections: FnvHashMap<SectionId, Box<str>>,
+    decode_options: DecodeOptions,
 }
 
 impl GPPString {
@@ -144,6 +439,149 @@ impl GPPString {
         s.parse()
     }
 
+    /// Parses a string with the given options and returns a [`GPPString`] if successful.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if unable to parse the string, or if the string does not
+    /// comply with the given options.
+    ///
+    pub fn parse_str_with_options(s: &str, options: &ParseOptions) -> Result<Self, GPPDecodeError> {
+        let s = if options.trim_whitespace {
+            s.trim_matches(|c: char| c.is_ascii_whitespace())
+        } else {
+            s
+        };
+        let (header, section_ids, sections_iter) = extract_gpp_sections_from_str(s, options)?;
+        let decode_options = DecodeOptions::from(options);
+
+        let mut sections =
+            FnvHashMap::with_capacity_and_hasher(section_ids.len(), Default::default());
+        for_each_section(&section_ids, sections_iter, |id, s| {
+            sections.insert(id, Box::from(s));
+        })?;
+        if options.verify_section_order {
+            verify_section_order(&section_ids, &sections, decode_options)?;
+        }
+
+        Ok(Self {
+            header: Box::from(header),
+            section_ids,
+            sections,
+            decode_options,
+        })
+    }
+
+    /// Reads a whole string from the given reader, and parses it.
+    ///
+    /// The input must be valid UTF-8. It is parsed as is: use
+    /// [`GPPString::from_reader_with_options`] to ignore surrounding whitespace, such as a
+    /// trailing newline.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPDecodeError::Read`] if the input cannot be read or is not valid UTF-8, or
+    /// another [`GPPDecodeError`] if unable to parse the string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let input: &[u8] = b"DBABTA~1YNN";
+    /// let gpp_str = GPPString::from_reader(input).unwrap();
+    ///
+    /// assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    /// ```
+    pub fn from_reader<R: io::Read>(r: R) -> Result<Self, GPPDecodeError> {
+        Self::from_reader_with_options(r, &ParseOptions::default())
+    }
+
+    /// Reads a whole string from the given reader, and parses it with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPDecodeError::Read`] if the input cannot be read or is not valid UTF-8, or
+    /// another [`GPPDecodeError`] if unable to parse the string.
+    pub fn from_reader_with_options<R: io::Read>(
+        mut r: R,
+        options: &ParseOptions,
+    ) -> Result<Self, GPPDecodeError> {
+        let mut s = String::new();
+        r.read_to_string(&mut s)?;
+        Self::parse_str_with_options(&s, options)
+    }
+
+    /// Parses a string taken from a URL, in which characters may be percent-encoded.
+    ///
+    /// Percent-encoded ASCII characters, such as `%7E` for the `~` section separator, are
+    /// decoded before parsing. Any other `%` sequence is left as is, and fails to parse. The
+    /// string may be only partially encoded, as happens when a web framework already decoded
+    /// part of it.
+    ///
+    /// Regular parsing methods such as [`GPPString::parse_str`] only accept the canonical
+    /// form, which never contains `%`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if unable to parse the decoded string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_url_encoded("DBABTA%7E1YNN").unwrap();
+    ///
+    /// assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    /// ```
+    pub fn parse_url_encoded(s: &str) -> Result<Self, GPPDecodeError> {
+        percent_decode(s).parse()
+    }
+
+    /// Builds a [`GPPString`] from a legacy US Privacy string, such as `1YNN`.
+    ///
+    /// The returned GPP string only contains the [`UspV1`]
+    /// section. This eases the migration of integrations which still receive bare US Privacy
+    /// strings, without a GPP header.
+    ///
+    /// Regular parsing methods such as [`GPPString::parse_str`] never accept such strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the string is not a valid US Privacy string, including
+    /// when characters follow its 4 characters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::from_legacy_usp("1YNN").unwrap();
+    ///
+    /// assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    /// assert_eq!(gpp_str.encode().unwrap(), "DBABTA~1YNN");
+    /// ```
+    pub fn from_legacy_usp(s: &str) -> Result<Self, SectionDecodeError> {
+        // validate the string, which must not go on past the 4 characters read by the section
+        UspV1::from_str(s)?;
+        if s.len() > 4 {
+            return Err(SectionDecodeError::TrailingData {
+                bits: (s.len() as u64 - 4) * 8,
+            });
+        }
+
+        Ok(Self {
+            header: Box::from(USP_V1_HEADER),
+            section_ids: vec![SectionId::UspV1],
+            sections: FnvHashMap::from_iter([(SectionId::UspV1, Box::from(s))]),
+            decode_options: DecodeOptions::default(),
+        })
+    }
+
     /// Returns a reference to a raw section contained in this GPP string.
     ///
     /// The method takes the section ID as parameter, and returns the reference
@@ -169,7 +607,116 @@ impl GPPString {
     /// }
     /// ```
     pub fn section(&self, id: SectionId) -> Option<&str> {
-        self.sections.get(&id).map(|s| s.as_str())
+        self.sections.get(&id).map(|s| s.as_ref())
+    }
+
+    /// Returns the decoded bytes of a section, without decoding its fields.
+    ///
+    /// The result holds the bytes of each segment of the section, separately, starting with
+    /// the core segment. The last byte of each segment is padded with zero bits. Since the US
+    /// Privacy section is not base64 encoded, its bytes are the ones of the string itself.
+    ///
+    /// This is meant for tools which hash or sign section payloads, or inspect them at the
+    /// bit level with a [`DataReader`].
+    ///
+    /// Returns `None` if the section is not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DecodeError`] if a segment is not valid base64. Characters of the standard
+    /// alphabet are only accepted if the string was parsed with
+    /// [`ParseOptions::standard_base64`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBABBg~BVqqqqpY.YA").unwrap();
+    /// let segments = gpp_str.section_raw_bytes(SectionId::UsCa).unwrap().unwrap();
+    ///
+    /// assert_eq!(segments, [vec![0x05, 0x5a, 0xaa, 0xaa, 0xaa, 0x58], vec![0x60, 0x00]]);
+    /// ```
+    pub fn section_raw_bytes(&self, id: SectionId) -> Option<Result<Vec<Vec<u8>>, DecodeError>> {
+        let s = self.section(id)?;
+        if id == SectionId::UspV1 {
+            return Some(Ok(vec![s.as_bytes().to_vec()]));
+        }
+
+        Some(
+            s.split('.')
+                .map(|s| {
+                    let mut buf = Vec::new();
+                    s.decode_base64_into(&mut buf, self.decode_options.standard_base64)?;
+                    Ok(buf)
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the version of a section, without decoding its fields.
+    ///
+    /// Only the first character of the section is decoded, which holds the 6-bit version field
+    /// of the core segment. This is much cheaper than decoding the whole section, when only
+    /// the version is needed, such as to route a section to the right decoder. For the US
+    /// Privacy section, the version is the leading digit of the string.
+    ///
+    /// The version is returned as is, even if it is not supported by this crate.
+    ///
+    /// Returns `None` if the section is not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the section is empty, or if its first character is
+    /// not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    ///
+    /// assert_eq!(gpp_str.section_version(SectionId::TcfEuV2).unwrap().unwrap(), 2);
+    /// assert_eq!(gpp_str.section_version(SectionId::UspV1).unwrap().unwrap(), 1);
+    /// assert!(gpp_str.section_version(SectionId::UsNat).is_none());
+    /// ```
+    pub fn section_version(&self, id: SectionId) -> Option<Result<u8, SectionDecodeError>> {
+        let s = self.section(id)?;
+        if id == SectionId::UspV1 {
+            return Some(CharReader::new(uspv1::KIND, s).read_digit());
+        }
+
+        let first = s.char_indices().nth(1).map_or(s, |(i, _)| &s[..i]);
+        let mut buf = Vec::with_capacity(1);
+        Some(
+            first
+                .decode_base64_into(&mut buf, self.decode_options.standard_base64)
+                .map_err(SectionDecodeError::from)
+                .and_then(|_| Ok(DataReader::new(&buf).read_fixed_integer(6)?)),
+        )
+    }
+
+    /// Returns the number of sections present in this GPP string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    ///
+    /// assert_eq!(gpp_str.section_count(), 2);
+    /// ```
+    pub fn section_count(&self) -> usize {
+        self.section_ids.len()
+    }
+
+    /// Returns `true` if this GPP string contains no section, i.e. only a header.
+    pub fn is_empty(&self) -> bool {
+        self.section_ids.is_empty()
     }
 
     /// Returns an iterator that yields the list of section IDs present in this GPP string.
@@ -191,10 +738,35 @@ impl GPPString {
     ///     Ok(())
     /// }
     /// ```
-    pub fn section_ids(&self) -> SectionIds {
+    pub fn section_ids(&self) -> SectionIds<'_> {
         SectionIds(self.section_ids.iter())
     }
 
+    /// Returns the legal frameworks covered by the sections of this GPP string.
+    ///
+    /// See [`SectionId::framework`] for the framework covered by each section.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Framework;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str =
+    ///     GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    ///
+    /// assert_eq!(
+    ///     gpp_str.frameworks().into_iter().collect::<Vec<_>>(),
+    ///     [Framework::GdprTcf, Framework::UsPrivacy]
+    /// );
+    /// ```
+    pub fn frameworks(&self) -> BTreeSet<Framework> {
+        self.section_ids
+            .iter()
+            .filter_map(SectionId::framework)
+            .collect()
+    }
+
     /// Returns an iterator that yields the list of raw section strings present in this GPP string.
     ///
     /// # Example
@@ -214,7 +786,7 @@ impl GPPString {
     ///     Ok(())
     /// }
     /// ```
-    pub fn sections(&self) -> Sections {
+    pub fn sections(&self) -> Sections<'_> {
         Sections {
             gpp_str: self,
             idx: 0,
@@ -259,7 +831,44 @@ impl GPPString {
         let s = self
             .section(id)
             .ok_or(SectionDecodeError::MissingSection(id))?;
-        decode_section(id, s)
+        decode_section(id, s, &mut Vec::new(), self.decode_options)
+    }
+
+    /// Decodes a single section of this GPP string, using the given buffer as scratch space.
+    ///
+    /// This behaves like [`decode_section`](GPPString::decode_section), but the buffer used
+    /// to hold the Base64-decoded bytes is cleared and reused instead of being allocated for
+    /// each call. This amortizes allocations when decoding many sections in a loop.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{Section, SectionId};
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let mut buf = Vec::new();
+    ///
+    /// for s in ["DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN"] {
+    ///     let gpp_str = GPPString::parse_str(s).unwrap();
+    ///     let section = gpp_str.decode_section_into(SectionId::TcfEuV2, &mut buf);
+    ///
+    ///     assert!(matches!(section, Ok(Section::TcfEuV2(_))));
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the section is missing or fails to decode.
+    ///
+    pub fn decode_section_into(
+        &self,
+        id: SectionId,
+        buf: &mut Vec<u8>,
+    ) -> Result<Section, SectionDecodeError> {
+        let s = self
+            .section(id)
+            .ok_or(SectionDecodeError::MissingSection(id))?;
+        decode_section(id, s, buf, self.decode_options)
     }
 
     /// Decodes and returns a single section of this GPP string.
@@ -298,9 +907,45 @@ impl GPPString {
     where
         T: DecodableSection,
     {
-        self.section(T::ID)
-            .ok_or(SectionDecodeError::MissingSection(T::ID))?
-            .parse()
+        let s = self
+            .section(T::ID)
+            .ok_or(SectionDecodeError::MissingSection(T::ID))?;
+        if s.is_empty() {
+            return Err(SectionDecodeError::EmptySection(T::ID));
+        }
+        s.parse()
+    }
+
+    /// Decodes and returns a single section of this GPP string, if present.
+    ///
+    /// Same as [`decode`](GPPString::decode), except that a section which is not present in
+    /// the string is returned as `None` instead of [`SectionDecodeError::MissingSection`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use iab_gpp::sections::uspv1::UspV1;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBABTA~1YNN").unwrap();
+    ///
+    /// assert!(gpp_str.try_decode::<UspV1>().unwrap().is_some());
+    /// assert!(gpp_str.try_decode::<TcfEuV2>().unwrap().is_none());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if the section is present but decoding it fails.
+    ///
+    pub fn try_decode<T>(&self) -> Result<Option<T>, SectionDecodeError>
+    where
+        T: DecodableSection,
+    {
+        if self.section(T::ID).is_none() {
+            return Ok(None);
+        }
+        self.decode().map(Some)
     }
 
     /// Decodes and returns all sections present in this GPP string.
@@ -332,227 +977,1918 @@ impl GPPString {
     /// Returns a [`SectionDecodeError`] for each section which fails to decode.
     ///
     pub fn decode_all_sections(&self) -> Vec<Result<Section, SectionDecodeError>> {
-        self.section_ids
-            .iter()
-            .map(|id| self.decode_section(*id))
-            .collect()
+        self.into_iter().collect()
     }
-}
-
-impl FromStr for GPPString {
-    type Err = GPPDecodeError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (section_ids, sections) = extract_gpp_sections_from_str(s)?;
 
-        let sections = section_ids
-            .iter()
-            .zip(sections)
-            .map(|(&id, s)| (id, s.to_string()))
-            .collect();
+    /// Decodes the sections of this GPP string in order, and returns the first decoding error.
+    ///
+    /// Decoding stops at the first section which fails to decode, and decoded sections are
+    /// dropped right away. This is cheaper than [`decode_all_sections`](Self::decode_all_sections)
+    /// when only a pass or fail signal is needed.
+    ///
+    /// Returns `None` if all sections decode successfully.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    /// assert!(gpp_str.first_decode_error().is_none());
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACTM~1YNN~BVqq").unwrap();
+    /// let (id, _) = gpp_str.first_decode_error().unwrap();
+    /// assert_eq!(id, SectionId::UsCa);
+    /// ```
+    pub fn first_decode_error(&self) -> Option<(SectionId, SectionDecodeError)> {
+        let mut buf = Vec::new();
+        self.section_ids.iter().find_map(|&id| {
+            self.decode_section_into(id, &mut buf)
+                .err()
+                .map(|e| (id, e))
+        })
+    }
+
+    /// Decodes and validates all sections present in this GPP string.
+    ///
+    /// The result contains one entry per section, in the order in which they are listed in the
+    /// string. Each entry holds either an empty result if the section is consistent, or the
+    /// list of all [`ValidationError`]s found in it. A section which fails to decode is
+    /// reported with a single [`ValidationError::Undecodable`] error.
+    ///
+    /// See [`Section::validate`] for the rules being checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACTM~1YNN~BVqqqqpY.YA").unwrap();
+    /// let report = gpp_str.validate_all();
+    ///
+    /// assert_eq!(report, vec![(SectionId::UspV1, Ok(())), (SectionId::UsCa, Ok(()))]);
+    /// ```
+    ///
+    pub fn validate_all(&self) -> Vec<(SectionId, Result<(), Vec<ValidationError>>)> {
+        self.section_ids
+            .iter()
+            .map(|&id| {
+                let errors = match self.decode_section(id) {
+                    Ok(section) => section.validate(),
+                    Err(e) => vec![ValidationError::Undecodable(e.to_string())],
+                };
+
+                (
+                    id,
+                    if errors.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(errors)
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Decodes all sections present in this GPP string, and returns the ones which could be
+    /// decoded in a map indexed by section ID.
+    ///
+    /// Sections which fail to decode are silently ignored. Use
+    /// [`decode_all_sections`](GPPString::decode_all_sections) to access decoding errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+    /// let sections = GPPString::parse_str(s).unwrap().decode_map();
+    ///
+    /// assert!(sections.contains_key(&SectionId::TcfEuV2));
+    /// assert!(sections.contains_key(&SectionId::UspV1));
+    /// ```
+    ///
+    pub fn decode_map(&self) -> FnvHashMap<SectionId, Section> {
+        self.section_ids
+            .iter()
+            .filter_map(|&id| self.decode_section(id).ok().map(|s| (id, s)))
+            .collect()
+    }
+
+    /// Compares the sections of this GPP string with the ones of another GPP string.
+    ///
+    /// Sections present in only one of the strings are reported as added or removed.
+    /// Sections present in both strings are decoded and compared field by field
+    /// using [`Section::diff`]. Identical sections are not reported.
+    ///
+    /// The differences are sorted by section ID.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::{GPPString, SectionDiff};
+    ///
+    /// let a = GPPString::parse_str("DBABTA~1YNN").unwrap();
+    /// let b = GPPString::parse_str("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    ///
+    /// let diff = a.diff(&b).unwrap();
+    ///
+    /// assert_eq!(
+    ///     diff,
+    ///     vec![
+    ///         SectionDiff::Added(SectionId::TcfEuV2),
+    ///         SectionDiff::Removed(SectionId::UspV1),
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] if a section present in both strings fails to decode.
+    ///
+    pub fn diff(&self, other: &GPPString) -> Result<Vec<SectionDiff>, SectionDecodeError> {
+        let mut ids = self
+            .section_ids
+            .iter()
+            .chain(&other.section_ids)
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+
+        let mut diffs = vec![];
+        for id in ids {
+            match (self.section(id), other.section(id)) {
+                (Some(_), None) => diffs.push(SectionDiff::Removed(id)),
+                (None, Some(_)) => diffs.push(SectionDiff::Added(id)),
+                (Some(a), Some(b)) if a != b => {
+                    let fields = self.decode_section(id)?.diff(&other.decode_section(id)?);
+                    if !fields.is_empty() {
+                        diffs.push(SectionDiff::Changed(id, fields));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(diffs)
+    }
+
+    /// Combines the sections of this GPP string with the sections of another one.
+    ///
+    /// A section present in both strings with different contents is a conflict, which is
+    /// resolved according to the given [`MergePolicy`]. Sections with identical contents are
+    /// never in conflict.
+    ///
+    /// Section IDs of the resulting string are sorted in ascending order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::{GPPString, MergePolicy};
+    ///
+    /// let us = GPPString::parse_str("DBABTA~1YNN").unwrap();
+    /// let tcf = GPPString::parse_str("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    ///
+    /// let merged = us.merge(&tcf, MergePolicy::Error).unwrap();
+    ///
+    /// assert_eq!(
+    ///     merged.section_ids().collect::<Vec<_>>(),
+    ///     vec![&SectionId::TcfEuV2, &SectionId::UspV1]
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPEncodeError::ConflictingSection`] if both strings contain different versions
+    /// of the same section and the policy is [`MergePolicy::Error`].
+    ///
+    pub fn merge(&self, other: &GPPString, policy: MergePolicy) -> Result<Self, GPPEncodeError> {
+        let mut sections = self.sections.clone();
+
+        for (&id, s) in &other.sections {
+            match sections.get(&id) {
+                Some(existing) if existing == s => {}
+                Some(_) => match policy {
+                    MergePolicy::Error => return Err(GPPEncodeError::ConflictingSection(id)),
+                    MergePolicy::PreferSelf => {}
+                    MergePolicy::PreferOther => {
+                        sections.insert(id, s.clone());
+                    }
+                },
+                None => {
+                    sections.insert(id, s.clone());
+                }
+            }
+        }
+
+        let mut section_ids = sections.keys().copied().collect::<Vec<_>>();
+        section_ids.sort();
 
         Ok(Self {
+            header: encode_header(&section_ids)?.into_boxed_str(),
             section_ids,
             sections,
+            decode_options: self.decode_options,
+        })
+    }
+
+    /// Encodes this GPP string, computing its header from the list of sections it contains.
+    ///
+    /// The header is padded to a whole byte, as done by the IAB encoder, and sections are
+    /// written as they were parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let s = "DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+    /// let gpp_str = GPPString::parse_str(s).unwrap();
+    ///
+    /// assert_eq!(gpp_str.encode().unwrap(), s);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPEncodeError`] if the header cannot be written.
+    ///
+    pub fn encode(&self) -> Result<String, GPPEncodeError> {
+        let mut s = encode_header(&self.section_ids)?;
+
+        for section in self.sections() {
+            s.push('~');
+            s.push_str(section);
+        }
+
+        Ok(s)
+    }
+
+    /// Encodes this GPP string into the shortest string holding the same sections.
+    ///
+    /// Each section is decoded and encoded again with
+    /// [`EncodableSection::encode_compact`](crate::sections::EncodableSection::encode_compact),
+    /// which chooses the shortest encoding of each set of IDs and does not pad segments. This
+    /// helps fitting strings within cookie or URL length limits.
+    ///
+    /// The header is written as with [`encode`](Self::encode).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBABBg~BVqqqqpYAAAA.YA").unwrap();
+    ///
+    /// assert_eq!(gpp_str.to_compact_string().unwrap(), "DBABBg~BVqqqqpY.Y");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPEncodeError::SectionDecode`] if a section cannot be decoded, or another
+    /// [`GPPEncodeError`] if a section or the header cannot be encoded.
+    ///
+    pub fn to_compact_string(&self) -> Result<String, GPPEncodeError> {
+        let mut s = encode_header(&self.section_ids)?;
+        let mut buf = Vec::new();
+
+        for &id in &self.section_ids {
+            let section = self
+                .decode_section_into(id, &mut buf)
+                .map_err(|source| GPPEncodeError::SectionDecode { id, source })?;
+            s.push('~');
+            s.push_str(
+                &section
+                    .encode_compact()
+                    .map_err(|source| GPPEncodeError::SectionEncode { id, source })?,
+            );
+        }
+
+        Ok(s)
+    }
+
+    /// Returns a copy of this GPP string in which the fields identifying the CMP and the time
+    /// of creation of each section are cleared.
+    ///
+    /// This produces a string which can be shared, for instance in a bug report, without
+    /// revealing where and when the original consent was collected. See [`Section::redact`]
+    /// for the fields being cleared. The sections and consent choices are kept, and the
+    /// result can still be decoded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    /// let redacted = gpp_str.redact().unwrap();
+    ///
+    /// let tcf = redacted.decode::<TcfEuV2>().unwrap();
+    /// assert_eq!(tcf.core.cmp_id, 0);
+    /// assert_eq!(redacted.section(iab_gpp::sections::SectionId::UspV1), Some("1YNN"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPEncodeError::SectionDecode`] if a section cannot be decoded, or
+    /// [`GPPEncodeError::SectionEncode`] if a redacted section cannot be encoded. A section
+    /// which cannot be redacted is never copied as is, as it could reveal the cleared fields.
+    ///
+    pub fn redact(&self) -> Result<GPPString, GPPEncodeError> {
+        let mut buf = Vec::new();
+        let sections = self
+            .section_ids
+            .iter()
+            .map(|&id| {
+                let mut section = self
+                    .decode_section_into(id, &mut buf)
+                    .map_err(|source| GPPEncodeError::SectionDecode { id, source })?;
+                section.redact();
+                let redacted = section
+                    .encode()
+                    .map_err(|source| GPPEncodeError::SectionEncode { id, source })?;
+                Ok((id, Box::from(redacted)))
+            })
+            .collect::<Result<_, GPPEncodeError>>()?;
+
+        Ok(GPPString {
+            header: self.header.clone(),
+            section_ids: self.section_ids.clone(),
+            sections,
+            decode_options: self.decode_options,
+        })
+    }
+
+    /// Returns a view of this GPP string shaped like the response of the GPP CMP JavaScript API.
+    ///
+    /// This eases porting logic written against the `__gpp` API of a CMP. As the string alone
+    /// does not tell which sections apply to the current transaction, all the sections listed
+    /// in the header are reported as applicable. The GPP string is reported as it was parsed,
+    /// without being encoded again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::{GPPString, SignalStatus};
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    /// let view = gpp_str.cmp_api_view();
+    ///
+    /// assert_eq!(view.applicable_sections, vec![2, 6]);
+    /// assert_eq!(view.signal_status, SignalStatus::Ready);
+    /// ```
+    pub fn cmp_api_view(&self) -> CmpApiView {
+        let section_list = self
+            .section_ids
+            .iter()
+            .map(|&id| id as i32)
+            .collect::<Vec<_>>();
+        let applicable_sections = if section_list.is_empty() {
+            vec![-1]
+        } else {
+            section_list.clone()
+        };
+
+        let mut gpp_string = String::from(&*self.header);
+        for section in self.sections() {
+            gpp_string.push('~');
+            gpp_string.push_str(section);
+        }
+
+        CmpApiView {
+            gpp_string,
+            applicable_sections,
+            section_list,
+            signal_status: SignalStatus::Ready,
+        }
+    }
+
+    /// Decodes all sections of this GPP string into a JSON object keyed by section name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBABTA~1YNN").unwrap();
+    /// let json = gpp_str.to_json().unwrap();
+    ///
+    /// # #[cfg(not(feature = "serde-iab-names"))]
+    /// assert_eq!(json["UspV1"]["opt_out_sale"], "No");
+    /// ```
+    ///
+    /// See [`Section::to_json`] for the naming of fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`serde_json::Error`] if a section fails to decode or to be serialized.
+    ///
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        use serde::ser::Error;
+
+        let mut map = serde_json::Map::new();
+        for &id in self.section_ids() {
+            let section = self.decode_section(id).map_err(serde_json::Error::custom)?;
+            if let serde_json::Value::Object(o) = section.to_json()? {
+                map.extend(o);
+            }
+        }
+
+        Ok(serde_json::Value::Object(map))
+    }
+}
+
+/// A difference between the sections of two GPP strings.
+///
+/// Created with the method [`diff`](GPPString::diff).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SectionDiff {
+    /// The section is only present in the other string.
+    Added(SectionId),
+    /// The section is only present in the original string.
+    Removed(SectionId),
+    /// The section is present in both strings, but some of its fields differ.
+    Changed(SectionId, Vec<FieldDiff>),
+}
+
+/// The policy used to resolve conflicts when merging GPP strings.
+///
+/// Used by the method [`merge`](GPPString::merge).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum MergePolicy {
+    /// Conflicting sections are an error.
+    #[default]
+    Error,
+    /// The section of the string being merged into is kept.
+    PreferSelf,
+    /// The section of the string being merged is kept.
+    PreferOther,
+}
+
+/// A view of a GPP string shaped like the response of the GPP CMP JavaScript API.
+///
+/// Created with the method [`cmp_api_view`](GPPString::cmp_api_view). With the `serde` feature,
+/// it serializes with the camel case field names of the JavaScript API.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+#[non_exhaustive]
+pub struct CmpApiView {
+    /// The GPP string, as it was parsed. For a string built from sections, this is the string
+    /// returned by [`GPPString::encode`].
+    pub gpp_string: String,
+    /// The IDs of the sections which apply to the transaction, or `[-1]` if there is none.
+    pub applicable_sections: Vec<i32>,
+    /// The IDs of the sections present in the string, in the order of the header.
+    pub section_list: Vec<i32>,
+    /// Whether the CMP is ready to provide the GPP string.
+    pub signal_status: SignalStatus,
+}
+
+/// The status of the signal reported by the GPP CMP JavaScript API.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum SignalStatus {
+    /// The GPP string is complete and can be relied upon.
+    #[cfg_attr(feature = "serde", serde(rename = "ready"))]
+    Ready,
+    /// The GPP string is not yet final, for instance while the user is making choices.
+    #[cfg_attr(feature = "serde", serde(rename = "not ready"))]
+    NotReady,
+}
+
+impl From<&GPPString> for FnvHashMap<SectionId, Section> {
+    fn from(gpp_str: &GPPString) -> Self {
+        gpp_str.decode_map()
+    }
+}
+
+impl From<GPPString> for FnvHashMap<SectionId, Section> {
+    fn from(gpp_str: GPPString) -> Self {
+        gpp_str.decode_map()
+    }
+}
+
+impl TryFrom<Vec<Section>> for GPPString {
+    type Error = GPPEncodeError;
+
+    /// Builds a GPP string by encoding each of the given sections.
+    ///
+    /// Sections are sorted by ID, regardless of the order in which they are given.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::Section;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let sections = vec![
+    ///     Section::UspV1("1YNN".parse().unwrap()),
+    ///     Section::UsCa("BVqqqqpY.YA".parse().unwrap()),
+    /// ];
+    /// let gpp_str = GPPString::try_from(sections).unwrap();
+    ///
+    /// assert_eq!(gpp_str.encode().unwrap(), "DBACTMA~1YNN~BVqqqqpY.YA");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPEncodeError::DuplicateSection`] if a section is given more than once, or
+    /// [`GPPEncodeError::SectionEncode`] if a section cannot be encoded.
+    ///
+    fn try_from(mut sections: Vec<Section>) -> Result<Self, Self::Error> {
+        sections.sort_by_key(Section::id);
+
+        let mut section_ids = Vec::with_capacity(sections.len());
+        let mut encoded = FnvHashMap::default();
+
+        for section in sections {
+            let id = section.id();
+            if section_ids.last() == Some(&id) {
+                return Err(GPPEncodeError::DuplicateSection(id));
+            }
+
+            let s = section
+                .encode()
+                .map_err(|source| GPPEncodeError::SectionEncode { id, source })?;
+            section_ids.push(id);
+            encoded.insert(id, s.into_boxed_str());
+        }
+
+        Ok(Self {
+            header: encode_header(&section_ids)?.into_boxed_str(),
+            section_ids,
+            sections: encoded,
+            decode_options: DecodeOptions::default(),
         })
     }
-}
+}
+
+impl FromIterator<Section> for Result<GPPString, GPPEncodeError> {
+    fn from_iter<I: IntoIterator<Item = Section>>(iter: I) -> Self {
+        GPPString::try_from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+/// Iterates over the decoded sections, in the order in which they are listed in the string.
+///
+/// Sections are decoded lazily, so that breaking out of the loop skips the decoding of the
+/// remaining sections.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::Section;
+/// use iab_gpp::v1::GPPString;
+///
+/// let gpp_str = GPPString::parse_str("DBABTA~1YNN").unwrap();
+///
+/// for section in &gpp_str {
+///     assert!(matches!(section, Ok(Section::UspV1(_))));
+/// }
+/// ```
+impl<'a> IntoIterator for &'a GPPString {
+    type Item = Result<Section, SectionDecodeError>;
+    type IntoIter = DecodedSections<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        DecodedSections {
+            gpp_str: self,
+            ids: self.section_ids.iter(),
+        }
+    }
+}
+
+impl FromStr for GPPString {
+    type Err = GPPDecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_str_with_options(s, &ParseOptions::default())
+    }
+}
+
+/// Compares a GPP string with its canonical encoding, as returned by [`GPPString::encode`].
+///
+/// Since the header is re-encoded, a string is not equal to a non-canonical input it was parsed
+/// from, such as one with an unpadded header. A string which cannot be encoded is not equal to
+/// any string.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::v1::GPPString;
+///
+/// let gpp_str = GPPString::parse_str("DBABTA~1YNN").unwrap();
+///
+/// assert_eq!(gpp_str, "DBABTA~1YNN");
+///
+/// let gpp_str = GPPString::parse_str("DBABT~1YNN").unwrap();
+///
+/// assert_ne!(gpp_str, "DBABT~1YNN");
+/// ```
+impl PartialEq<str> for GPPString {
+    fn eq(&self, other: &str) -> bool {
+        self.encode().is_ok_and(|s| s == other)
+    }
+}
+
+impl PartialEq<&str> for GPPString {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// Serializes a GPP string as its encoded form, as returned by [`GPPString::encode`].
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::v1::GPPString;
+///
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Request {
+///     gpp: GPPString,
+/// }
+///
+/// let request: Request = serde_json::from_str(r#"{"gpp":"DBABTA~1YNN"}"#).unwrap();
+/// assert_eq!(serde_json::to_string(&request).unwrap(), r#"{"gpp":"DBABTA~1YNN"}"#);
+/// ```
+#[cfg(feature = "serde")]
+impl serde::Serialize for GPPString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let s = self.encode().map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&s)
+    }
+}
+
+/// Deserializes a GPP string from its encoded form, as parsed by [`GPPString::from_str`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for GPPString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct GPPStringVisitor;
+
+        impl serde::de::Visitor<'_> for GPPStringVisitor {
+            type Value = GPPString;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a GPP string")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(GPPStringVisitor)
+    }
+}
+
+/// Reads the GPP version from the header of a string, without decoding its sections.
+///
+/// This succeeds for any well-formed header, including ones with a version which is not
+/// supported by this module, so that strings can be routed according to their version.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::v1::gpp_version;
+///
+/// assert_eq!(gpp_version("DBABTA~1YNN").unwrap(), 1);
+/// assert_eq!(gpp_version("DCABTA~1YNN").unwrap(), 2);
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`GPPDecodeError`] if the header cannot be decoded or has an invalid type.
+///
+pub fn gpp_version(s: &str) -> Result<u8, GPPDecodeError> {
+    let (header_str, _) = split_header(s)?;
+    let header = header_str.decode_base64_url()?;
+    let mut reader = DataReader::new(&header);
+
+    let header_type = reader.read_fixed_integer(6)?;
+    if header_type != GPP_HEADER {
+        return Err(GPPDecodeError::InvalidHeaderType { found: header_type });
+    }
+
+    Ok(reader.read_fixed_integer(6)?)
+}
+
+/// Parses a string and decodes a single section from it, if present.
+///
+/// This is a shortcut for parsing a [`GPPString`] and calling [`GPPString::decode`], for
+/// callers interested in a single section.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::uspv1::UspV1;
+/// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+///
+/// let usp = iab_gpp::decode_one::<UspV1>("DBABTA~1YNN").unwrap();
+/// assert!(usp.is_some());
+///
+/// let tcf = iab_gpp::decode_one::<TcfEuV2>("DBABTA~1YNN").unwrap();
+/// assert!(tcf.is_none());
+/// ```
+///
+/// # Errors
+///
+/// Returns a [`GPPDecodeError`] if the string cannot be parsed, or
+/// [`GPPDecodeError::SectionDecode`] if the section is present but cannot be decoded.
+///
+pub fn decode_one<T>(s: &str) -> Result<Option<T>, GPPDecodeError>
+where
+    T: DecodableSection,
+{
+    let (_, section_ids, sections_iter) =
+        extract_gpp_sections_from_str(s, &ParseOptions::default())?;
+    let mut section = None;
+    for_each_section(&section_ids, sections_iter, |id, s| {
+        if id == T::ID {
+            section = Some(s);
+        }
+    })?;
+    let Some(s) = section else {
+        return Ok(None);
+    };
+
+    if s.is_empty() {
+        return Err(GPPDecodeError::SectionDecode {
+            id: T::ID,
+            source: SectionDecodeError::EmptySection(T::ID),
+        });
+    }
+    s.parse()
+        .map(Some)
+        .map_err(|source| GPPDecodeError::SectionDecode { id: T::ID, source })
+}
+
+fn check_gpp_version(version: u8) -> Result<(), GPPDecodeError> {
+    match version {
+        GPP_VERSION => Ok(()),
+        0 => Err(GPPDecodeError::InvalidGPPVersion { found: version }),
+        _ => Err(GPPDecodeError::UnsupportedGPPVersion { found: version }),
+    }
+}
+
+// splits the header from the sections, telling apart an empty input from a missing header
+fn split_header(s: &str) -> Result<(&str, Split<'_, char>), GPPDecodeError> {
+    if s.bytes().all(|b| b.is_ascii_whitespace()) {
+        return Err(GPPDecodeError::EmptyInput);
+    }
+
+    let mut sections_iter = s.split('~');
+    match sections_iter.next() {
+        Some(header_str) if !header_str.is_empty() => Ok((header_str, sections_iter)),
+        _ => Err(GPPDecodeError::NoHeaderFound),
+    }
+}
+
+// parses the header, returning the section IDs it lists along with the remaining sections,
+// which are not split yet to avoid collecting them
+fn extract_gpp_sections_from_str<'a>(
+    s: &'a str,
+    options: &ParseOptions,
+) -> Result<(&'a str, Vec<SectionId>, Split<'a, char>), GPPDecodeError> {
+    let (header_str, sections_iter) = split_header(s)?;
+    let mut header = Vec::new();
+    header_str.decode_base64_into(&mut header, options.standard_base64)?;
+    let mut reader = DataReader::new(&header);
+
+    let header_type = reader.read_fixed_integer(6)?;
+    if header_type != GPP_HEADER {
+        return Err(GPPDecodeError::InvalidHeaderType { found: header_type });
+    }
+
+    check_gpp_version(reader.read_fixed_integer(6)?)?;
+
+    // a Fibonacci range only encodes strictly ascending IDs, so there are no duplicate or
+    // unsorted IDs to reject
+    let section_ids = reader.read_fibonacci_range_with_limit(options.max_sections)?;
+    if section_ids.len() > options.max_sections {
+        return Err(GPPDecodeError::TooManySections {
+            max: options.max_sections,
+            found: section_ids.len(),
+        });
+    }
+
+    let section_ids = section_ids
+        .into_iter()
+        .map(|id| SectionId::from_u8(id).ok_or(GPPDecodeError::UnsupportedSectionId(id)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((header_str, section_ids, sections_iter))
+}
+
+// calls `f` with each section and the ID at the same position in the header, failing if there
+// are not as many sections as IDs
+fn for_each_section<'a>(
+    section_ids: &[SectionId],
+    mut sections_iter: Split<'a, char>,
+    mut f: impl FnMut(SectionId, &'a str),
+) -> Result<(), GPPDecodeError> {
+    let mut found = 0;
+    for (&id, s) in section_ids.iter().zip(sections_iter.by_ref()) {
+        f(id, s);
+        found += 1;
+    }
+
+    let sections = found + sections_iter.count();
+    if sections != section_ids.len() {
+        return Err(GPPDecodeError::IdSectionMismatch {
+            ids: section_ids.len(),
+            sections,
+        });
+    }
+
+    Ok(())
+}
+
+fn verify_section_order(
+    section_ids: &[SectionId],
+    sections: &FnvHashMap<SectionId, Box<str>>,
+    options: DecodeOptions,
+) -> Result<(), GPPDecodeError> {
+    let mut buf = Vec::new();
+
+    for &id in section_ids {
+        let s = &sections[&id];
+        if decode_section(id, s, &mut buf, options).is_ok() {
+            continue;
+        }
+
+        let found = section_ids
+            .iter()
+            .find(|&&other| other != id && decode_section(other, s, &mut buf, options).is_ok());
+        if let Some(&found) = found {
+            return Err(GPPDecodeError::MisorderedSection { id, found });
+        }
+    }
+
+    Ok(())
+}
+
+// decodes %XX sequences of ASCII characters, leaving any other sequence untouched
+fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('%') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        match rest
+            .get(1..3)
+            .filter(|hex| hex.bytes().all(|c| c.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .filter(u8::is_ascii)
+        {
+            Some(b) => {
+                out.push(char::from(b));
+                rest = &rest[3..];
+            }
+            None => {
+                out.push('%');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    Cow::Owned(out)
+}
+
+fn encode_header(section_ids: &[SectionId]) -> Result<String, GPPEncodeError> {
+    write_header(section_ids).map_err(|e| match e {
+        SectionEncodeError::IntegerOutOfRange { bits, value } => {
+            GPPEncodeError::IntegerOutOfRange { bits, value }
+        }
+        SectionEncodeError::Write(e) => GPPEncodeError::Write(e),
+        e => GPPEncodeError::Write(io::Error::new(io::ErrorKind::InvalidInput, e)),
+    })
+}
+
+fn write_header(section_ids: &[SectionId]) -> Result<String, SectionEncodeError> {
+    let ids = section_ids
+        .iter()
+        .filter_map(|id| id.to_u16())
+        .collect::<BTreeSet<_>>();
+
+    let mut w = DataWriter::new();
+    w.write_fixed_integer(6, GPP_HEADER)?;
+    w.write_fixed_integer(6, GPP_VERSION)?;
+    w.write_fibonacci_range(&ids)?;
+
+    // like the IAB encoder, pad the header to a whole byte
+    let bits = w.position().next_multiple_of(8);
+    Ok(encode_bits(&w.into_bytes(), bits))
+}
+
+/// Created with the method [`sections`](GPPString::sections).
+pub struct Sections<'a> {
+    gpp_str: &'a GPPString,
+    idx: usize,
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let section_id = self.gpp_str.section_ids.get(self.idx)?;
+        self.idx += 1;
+        self.gpp_str.section(*section_id)
+    }
+}
+
+impl<'a> ExactSizeIterator for Sections<'a> {
+    fn len(&self) -> usize {
+        self.gpp_str.section_ids.len()
+    }
+}
+
+impl<'a> FusedIterator for Sections<'a> {}
+
+/// Created by iterating over a reference to a [`GPPString`].
+pub struct DecodedSections<'a> {
+    gpp_str: &'a GPPString,
+    ids: Iter<'a, SectionId>,
+}
+
+impl<'a> Iterator for DecodedSections<'a> {
+    type Item = Result<Section, SectionDecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        Some(self.gpp_str.decode_section(*id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for DecodedSections<'a> {
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<'a> FusedIterator for DecodedSections<'a> {}
+
+/// Created with the method [`section_ids`](GPPString::section_ids).
+pub struct SectionIds<'a>(Iter<'a, SectionId>);
+
+impl<'a> Iterator for SectionIds<'a> {
+    type Item = &'a SectionId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl<'a> ExactSizeIterator for SectionIds<'a> {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl<'a> FusedIterator for SectionIds<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::TcfCaV1 => Some("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA".to_string()) ; "tcf ca")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::UspV1 => Some("1YNN".to_string()) ; "usp v1")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::TcfEuV2 => None ; "tcf eu v2")]
+    fn gpp_string_section(s: &str, section_id: SectionId) -> Option<String> {
+        GPPString::from_str(s)
+            .unwrap()
+            .section(section_id)
+            .map(|s| s.to_string())
+    }
+
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
+    fn gpp_string_section_ids(s: &str) -> Vec<SectionId> {
+        GPPString::from_str(s).unwrap().section_ids
+    }
+
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"] ; "single section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "1YNN"] ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec!["BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA", "1YNN"] ; "tcf ca and us sections")]
+    fn gpp_string_sections(s: &str) -> Vec<String> {
+        GPPString::from_str(s)
+            .unwrap()
+            .sections()
+            .map(|s| s.to_string())
+            .collect()
+    }
+
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
+    fn gpp_string_decode_section(s: &str) -> Vec<SectionId> {
+        let s = GPPString::from_str(s).unwrap();
+        s.section_ids
+            .iter()
+            .map(|id| s.decode_section(*id).unwrap().id())
+            .collect()
+    }
+
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" ; "tcf ca and us sections")]
+    #[test_case("DBACTM~1YNN~BVqqqqpY.YA" ; "us ca with gpc")]
+    fn gpp_string_decode_section_into(s: &str) {
+        let s = GPPString::from_str(s).unwrap();
+        // a buffer with leftover data from previous uses
+        let mut buf = vec![0xff; 64];
+
+        for &id in &s.section_ids {
+            assert_eq!(
+                s.decode_section_into(id, &mut buf).unwrap(),
+                s.decode_section(id).unwrap()
+            );
+        }
+    }
+
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
+    fn gpp_string_decode_all(s: &str) -> Vec<SectionId> {
+        GPPString::from_str(s)
+            .unwrap()
+            .decode_all_sections()
+            .into_iter()
+            .map(|s| s.unwrap().id())
+            .collect()
+    }
+
+    #[test_case("DBABTA~1YN-" => UspV1 {
+        opt_out_notice: crate::sections::uspv1::Notice::Yes,
+        opt_out_sale: crate::sections::uspv1::OptOut::No,
+        lspa_covered_transaction: crate::sections::uspv1::Covered::NotApplicable,
+    } ; "mix")]
+    #[test_case("DBABTA~1NNN" => UspV1 {
+        opt_out_notice: crate::sections::uspv1::Notice::No,
+        opt_out_sale: crate::sections::uspv1::OptOut::No,
+        lspa_covered_transaction: crate::sections::uspv1::Covered::No,
+    } ; "all no")]
+    #[test_case("DBABTA~1YYY" => UspV1 {
+        opt_out_notice: crate::sections::uspv1::Notice::Yes,
+        opt_out_sale: crate::sections::uspv1::OptOut::Yes,
+        lspa_covered_transaction: crate::sections::uspv1::Covered::Yes,
+    } ; "all yes")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => UspV1 {
+        opt_out_notice: crate::sections::uspv1::Notice::Yes,
+        opt_out_sale: crate::sections::uspv1::OptOut::No,
+        lspa_covered_transaction: crate::sections::uspv1::Covered::No,
+    } ; "with other section")]
+    fn gpp_string_decode_uspv1(s: &str) -> UspV1 {
+        GPPString::from_str(s).unwrap().decode().unwrap()
+    }
+
+    #[test]
+    fn diff_changed_section() {
+        let a = GPPString::from_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")
+            .unwrap();
+        let b = GPPString::from_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YN-")
+            .unwrap();
+
+        assert_eq!(
+            a.diff(&b).unwrap(),
+            vec![SectionDiff::Changed(
+                SectionId::UspV1,
+                vec![FieldDiff {
+                    field: "lspa_covered_transaction".to_string(),
+                    old: Some("No".to_string()),
+                    new: Some("NotApplicable".to_string()),
+                }]
+            )]
+        );
+        assert_eq!(a.diff(&a).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn diff_nested_fields() {
+        let a = GPPString::from_str("DBABLA~BVVVVVVVVWA.YA").unwrap();
+        let b = GPPString::from_str("DBABLA~BVVVVVVVVWA").unwrap();
+
+        let diff = a.diff(&b).unwrap();
+        let SectionDiff::Changed(SectionId::UsNat, fields) = &diff[0] else {
+            panic!("unexpected diff {diff:?}");
+        };
+        assert_eq!(
+            fields,
+            &vec![FieldDiff {
+                field: "gpc".to_string(),
+                old: Some("true".to_string()),
+                new: Some("None".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn truncated_string() {
+        let r = GPPString::from_str("DBACNY~CPytTYAPytTYABEACBENDXCoAP_AAH_AAAIwgoNf_X__b3_v-_7___t0eY1f9_7__-0zjhfdt-8N3f_X_L8X_2M7");
+        assert!(matches!(
+            r,
+            Err(GPPDecodeError::IdSectionMismatch {
+                ids: 2,
+                sections: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn non_gpp_tcfeuv2_string() {
+        let r = GPPString::from_str("CP48G0AP48G0AEsACCPLAkEgAAAAAEPgAB5YAAAQaQD2F2K2kKFkPCmQWYAQBCijYEAhQAAAAkCBIAAgAUgQAgFIIAgAIFAAAAAAAAAQEgCQAAQABAAAIACgAAAAAAIAAAAAAAQQAAAAAIAAAAAAAAEAAAAAAAQAAAAIAABEhCAAQQAEAAAAAAAQAAAAAAAAAAABAAAAAAAAAAAAAAAAAAAAgAA");
+        assert!(matches!(
+            r,
+            Err(GPPDecodeError::InvalidHeaderType { found: 2 })
+        ));
+    }
+
+    #[test_case("DCABTA~1YNN" => matches GPPDecodeError::UnsupportedGPPVersion { found: 2 } ; "newer version")]
+    #[test_case("DAABTA~1YNN" => matches GPPDecodeError::InvalidGPPVersion { found: 0 } ; "version zero")]
+    fn invalid_gpp_version(s: &str) -> GPPDecodeError {
+        GPPString::from_str(s).unwrap_err()
+    }
+
+    #[test_case("DBABTA~1YNN" => 1 ; "v1")]
+    #[test_case("DCABTA~1YNN" => 2 ; "v2")]
+    #[test_case("DDAB" => 3 ; "header only")]
+    fn gpp_version_from_header(s: &str) -> u8 {
+        gpp_version(s).unwrap()
+    }
+
+    #[test_case("" => matches GPPDecodeError::EmptyInput ; "empty")]
+    #[test_case("  " => matches GPPDecodeError::EmptyInput ; "whitespace")]
+    #[test_case("\n" => matches GPPDecodeError::EmptyInput ; "newline")]
+    #[test_case("~" => matches GPPDecodeError::NoHeaderFound ; "separator only")]
+    #[test_case("~1YNN" => matches GPPDecodeError::NoHeaderFound ; "missing header")]
+    fn parse_missing_header(s: &str) -> GPPDecodeError {
+        assert!(matches!(
+            gpp_version(s),
+            Err(GPPDecodeError::EmptyInput | GPPDecodeError::NoHeaderFound)
+        ));
+        GPPString::from_str(s).unwrap_err()
+    }
+
+    #[test]
+    fn decode_one_present() {
+        let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let expected = GPPString::from_str(s).unwrap().decode::<UspV1>().unwrap();
+        assert_eq!(decode_one::<UspV1>(s).unwrap(), Some(expected));
+    }
+
+    #[test_case("DBAA" ; "header only")]
+    #[test_case("DBABTA~1YNN" ; "other section")]
+    fn decode_one_missing(s: &str) {
+        assert_eq!(
+            decode_one::<crate::sections::tcfeuv2::TcfEuV2>(s).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn decode_one_invalid_section() {
+        assert!(matches!(
+            decode_one::<UspV1>("DBABTA~2YNN"),
+            Err(GPPDecodeError::SectionDecode {
+                id: SectionId::UspV1,
+                source: SectionDecodeError::InvalidSectionVersion { .. },
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_one_invalid_header() {
+        assert!(matches!(
+            decode_one::<UspV1>("DBAB*"),
+            Err(GPPDecodeError::DecodeHeader(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_tcfca_section() {
+        let r = GPPString::from_str("DBABjw~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")
+            .unwrap()
+            .decode_all_sections();
+        assert!(matches!(
+            r[0],
+            Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 2 })
+        ));
+    }
+
+    #[test]
+    fn invalid_tcfeuv2_section() {
+        let r = GPPString::from_str("DBABMA~CQLvHAAQLvHAAAKA4DENBaFsAP_gAEPgAAwIKxtX_G9_bXlr8X736ftkeY1f99h77sQxBhZBk-4FzLvW_JwX32E7NA36tqYKmRIAu3TBIQNlHJDURVCgaogVrTDMaEyUoTtKJ6BkiFMRY2dYCFxvm4tjeQCY5vr991d52R-tbdrs3dzyy4hnv3a9_-S1WJCdA5-tDfv9bROb89IO5_x8v4v4_N7pE2_eT1l_tWvp7D9-ctv_9XX99_fbff9Pn_-uB_-_X__f_H37grAAQYCABAEAQICAAAAAQAAEAAEABAAAAAAACgAABEEAAEDAAAQAIAQAAABAABAAAAIAAAAAgACAAAAAEAgAAAACgADAAAAAAAYAAAMAEgIAAAAAQACmABAIFAAEJAFAEACEAEEAIQAABAEACAEABRwBAACBAoAAAQAAEAAAFgIDgAQEpAgACIgEAAAIAEAggAAEQjYACCAASCqqBAiiCAQLBoQFPaQAkgBACDgmQAgABQAHAAsA.f_gAAAAAAAAA").unwrap()
+            .decode_all_sections();
+        assert!(matches!(r[0], Err(SectionDecodeError::Read { .. })));
+    }
+
+    #[test]
+    fn decode_map_skips_failures() {
+        let gpp_str =
+            GPPString::from_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNX")
+                .unwrap();
+        let sections = FnvHashMap::from(&gpp_str);
+
+        assert_eq!(sections.len(), 1);
+        assert!(matches!(
+            sections.get(&SectionId::TcfEuV2),
+            Some(Section::TcfEuV2(_))
+        ));
+    }
+
+    const TCF_EU_V2: &str = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+
+    #[test_case("DBABTA~1YNN" => "DBABTA~1YNN" ; "padded header")]
+    #[test_case("DBABT~1YNN" => "DBABTA~1YNN" ; "unpadded header")]
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => "DBABMA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" ; "tcfeuv2")]
+    #[test_case("DBAA" => "DBAA" ; "no sections")]
+    fn encode(s: &str) -> String {
+        GPPString::from_str(s).unwrap().encode().unwrap()
+    }
+
+    #[test]
+    fn encode_sparse_section_ids() {
+        let sections = vec![
+            Section::TcfEuV2(TCF_EU_V2.parse().unwrap()),
+            Section::UspV1("1YNN".parse().unwrap()),
+            Section::UsCa("BVqqqqpY.YA".parse().unwrap()),
+            Section::UsVa("BVVVVWY".parse().unwrap()),
+        ];
+        let gpp_str = GPPString::try_from(sections).unwrap();
+
+        let parsed = GPPString::from_str(&gpp_str.encode().unwrap()).unwrap();
+        assert_eq!(
+            parsed.section_ids().copied().collect::<Vec<_>>(),
+            vec![
+                SectionId::TcfEuV2,
+                SectionId::UspV1,
+                SectionId::UsCa,
+                SectionId::UsVa
+            ]
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let us = GPPString::from_str("DBABTA~1YNN").unwrap();
+        let tcf = GPPString::from_str(&format!("DBABM~{TCF_EU_V2}")).unwrap();
+
+        let merged = us.merge(&tcf, MergePolicy::Error).unwrap();
+
+        assert_eq!(
+            merged.section_ids().copied().collect::<Vec<_>>(),
+            vec![SectionId::TcfEuV2, SectionId::UspV1]
+        );
+        assert_eq!(
+            merged.encode().unwrap(),
+            format!("DBACNYA~{TCF_EU_V2}~1YNN")
+        );
+    }
+
+    #[test]
+    fn merge_identical_sections() {
+        let a = GPPString::from_str("DBABTA~1YNN").unwrap();
+        let merged = a.merge(&a, MergePolicy::Error).unwrap();
+
+        assert_eq!(merged.section_count(), 1);
+        assert_eq!(merged.section(SectionId::UspV1), Some("1YNN"));
+    }
+
+    #[test_case(MergePolicy::PreferSelf => Some("1YNN".to_string()) ; "prefer self")]
+    #[test_case(MergePolicy::PreferOther => Some("1NNN".to_string()) ; "prefer other")]
+    fn merge_conflict(policy: MergePolicy) -> Option<String> {
+        let a = GPPString::from_str("DBABTA~1YNN").unwrap();
+        let b = GPPString::from_str("DBABTA~1NNN").unwrap();
+
+        a.merge(&b, policy)
+            .unwrap()
+            .section(SectionId::UspV1)
+            .map(String::from)
+    }
+
+    #[test]
+    fn merge_conflict_error() {
+        let a = GPPString::from_str("DBABTA~1YNN").unwrap();
+        let b = GPPString::from_str("DBABTA~1NNN").unwrap();
+
+        assert!(matches!(
+            a.merge(&b, MergePolicy::Error),
+            Err(GPPEncodeError::ConflictingSection(SectionId::UspV1))
+        ));
+    }
+
+    #[test_case("DBABTA~1YNN" ; "usp v1")]
+    #[test_case("DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" ; "tcf eu v2 and usp v1")]
+    #[test_case("DBACTMA~1YNN~BVqqqqpY.YA" ; "us ca and usp v1")]
+    #[test_case("DBAA" ; "no sections")]
+    fn from_sections(s: &str) {
+        let gpp_str = GPPString::from_str(s).unwrap();
+        let sections = gpp_str
+            .decode_all_sections()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let rebuilt = sections
+            .into_iter()
+            .rev()
+            .collect::<Result<GPPString, _>>()
+            .unwrap();
+
+        assert_eq!(rebuilt.encode().unwrap(), s);
+    }
+
+    #[test]
+    fn from_sections_duplicate() {
+        let sections = vec![
+            Section::UspV1("1YNN".parse().unwrap()),
+            Section::UspV1("1NNN".parse().unwrap()),
+        ];
+
+        assert!(matches!(
+            GPPString::try_from(sections),
+            Err(GPPEncodeError::DuplicateSection(SectionId::UspV1))
+        ));
+    }
+
+    #[test]
+    fn validate_all() {
+        let gpp_str = GPPString::from_str("DBACTM~1YNN~BRqqqqpU.YA").unwrap();
+
+        assert_eq!(
+            gpp_str.validate_all(),
+            vec![
+                (SectionId::UspV1, Ok(())),
+                (
+                    SectionId::UsCa,
+                    Err(vec![
+                        ValidationError::ConflictingMspaModes,
+                        ValidationError::NoticeInServiceProviderMode {
+                            notice: "sale_opt_out_notice".to_string(),
+                            value: crate::sections::us_common::Notice::Provided,
+                        },
+                    ])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_all_undecodable() {
+        let gpp_str = GPPString::from_str("DBACTM~1YNN~ZZ").unwrap();
+        let report = gpp_str.validate_all();
+
+        assert_eq!(report[0], (SectionId::UspV1, Ok(())));
+        assert!(matches!(
+            &report[1],
+            (SectionId::UsCa, Err(errors))
+                if matches!(errors.as_slice(), [ValidationError::Undecodable(_)])
+        ));
+    }
+
+    #[test_case("DBABTA~1YNN\n" ; "trailing newline")]
+    #[test_case(" \tDBABTA~1YNN\r\n" ; "surrounding whitespace")]
+    fn trim_whitespace(s: &str) {
+        let options = ParseOptions::default().trim_whitespace(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+
+        assert!(!matches!(
+            GPPString::parse_str(s),
+            Ok(g) if g.section(SectionId::UspV1) == Some("1YNN")
+        ));
+    }
+
+    #[test]
+    fn trim_whitespace_keeps_inner_whitespace() {
+        let options = ParseOptions::default().trim_whitespace(true);
+        let gpp_str = GPPString::parse_str_with_options("DBABTA~1Y NN", &options).unwrap();
+        assert_eq!(gpp_str.section(SectionId::UspV1), Some("1Y NN"));
+    }
+
+    #[test]
+    fn lenient() {
+        let s = "DBABMA~COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAA";
+        let gpp_str = GPPString::from_str(s).unwrap();
+        assert!(matches!(
+            gpp_str.decode_section(SectionId::TcfEuV2),
+            Err(SectionDecodeError::Read(_))
+        ));
+
+        let options = ParseOptions::default().lenient(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        let Section::TcfEuV2(tcf) = gpp_str.decode_section(SectionId::TcfEuV2).unwrap() else {
+            panic!("expected a TCF EU v2 section");
+        };
+        assert_eq!(tcf.core.purpose_consents, [1, 2, 3].into());
+        assert!(tcf.core.vendor_consents.is_empty());
+    }
 
-fn extract_gpp_sections_from_str(s: &str) -> Result<(Vec<SectionId>, Vec<&str>), GPPDecodeError> {
-    let mut sections_iter = s.split('~');
+    #[test_case("DBABBg~BVqqqqpY.YA" ; "minimal padding")]
+    #[test_case("DBABBg~BVqqqqpY.YAAA" ; "padding to base64 group")]
+    #[test_case("DBABTA~1YNN" ; "usp v1")]
+    fn strict_trailing_data(s: &str) {
+        let options = ParseOptions::default().strict_trailing_data(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        for &id in gpp_str.section_ids() {
+            assert!(gpp_str.decode_section(id).is_ok());
+        }
+    }
 
-    let header_str = sections_iter.next().ok_or(GPPDecodeError::NoHeaderFound)?;
-    let header = header_str.decode_base64_url()?;
-    let mut reader = DataReader::new(&header);
+    #[test_case("DBABBg~BVqqqqpYAAAA.YA" => 26 ; "extra core bytes")]
+    #[test_case("DBABBg~BVqqqqpZ.YA" => 2 ; "non zero core padding")]
+    #[test_case("DBABBg~BVqqqqpY.YQ" => 13 ; "non zero segment padding")]
+    #[test_case("DBABTA~1YNNX" => 8 ; "usp v1 extra character")]
+    fn strict_trailing_data_error(s: &str) -> u64 {
+        let gpp_str = GPPString::from_str(s).unwrap();
+        let id = *gpp_str.section_ids().next().unwrap();
+        assert!(gpp_str.decode_section(id).is_ok());
 
-    let header_type = reader.read_fixed_integer(6)?;
-    if header_type != GPP_HEADER {
-        return Err(GPPDecodeError::InvalidHeaderType { found: header_type });
+        let options = ParseOptions::default().strict_trailing_data(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        match gpp_str.decode_section(id) {
+            Err(SectionDecodeError::TrailingData { bits }) => bits,
+            r => panic!("unexpected result {r:?}"),
+        }
     }
 
-    let gpp_version = reader.read_fixed_integer(6)?;
-    if gpp_version != GPP_VERSION {
-        return Err(GPPDecodeError::InvalidGPPVersion { found: gpp_version });
+    #[test_case("DBABBg~BVqqqqpY." => 1 ; "trailing separator")]
+    #[test_case("DBABBg~BVqqqqpY..YA" => 1 ; "between segments")]
+    #[test_case("DBABBg~BVqqqqpY.YA." => 2 ; "after segment")]
+    fn empty_segment(s: &str) -> usize {
+        let options = ParseOptions::default().allow_empty_segments(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        assert!(gpp_str.decode_section(SectionId::UsCa).is_ok());
+
+        let gpp_str = GPPString::from_str(s).unwrap();
+        match gpp_str.decode_section(SectionId::UsCa) {
+            Err(SectionDecodeError::EmptySegment { index }) => index,
+            r => panic!("unexpected result {r:?}"),
+        }
     }
 
-    let section_ids = reader
-        .read_fibonacci_range()?
-        .into_iter()
-        .map(|id| SectionId::from_u8(id).ok_or(GPPDecodeError::UnsupportedSectionId(id)))
-        .collect::<Result<Vec<_>, _>>()?;
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => None ; "valid")]
+    #[test_case("DBACTM~1YNN~BVqq" => Some(SectionId::UsCa) ; "invalid last")]
+    #[test_case("DBACTM~1YN~BVqq" => Some(SectionId::UspV1) ; "first error only")]
+    fn first_decode_error(s: &str) -> Option<SectionId> {
+        GPPString::from_str(s)
+            .unwrap()
+            .first_decode_error()
+            .map(|(id, _)| id)
+    }
 
-    let sections = sections_iter.collect::<Vec<_>>();
-    if sections.len() != section_ids.len() {
-        return Err(GPPDecodeError::IdSectionMismatch {
-            ids: section_ids.len(),
-            sections: sections.len(),
-        });
+    #[test_case("DBABTA~1YNN" => "DBABTA~1YNN" ; "usp v1")]
+    #[test_case("DBABBg~BVqqqqpYAAAA.YA" => "DBABBg~BVqqqqpY.Y" ; "padded segment")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => "DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAQAIAAAA.YAAAAAAAAA~1YNN" ; "empty ranges")]
+    fn to_compact_string(s: &str) -> String {
+        GPPString::from_str(s).unwrap().to_compact_string().unwrap()
     }
 
-    Ok((section_ids, sections))
-}
+    #[test]
+    fn to_compact_string_shorter() {
+        let Ok(Section::TcfCaV1(mut tcf)) =
+            GPPString::from_str("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN")
+                .unwrap()
+                .decode_section(SectionId::TcfCaV1)
+        else {
+            panic!("unable to decode section");
+        };
+        tcf.core.vendor_express_consents = [3, 700].into();
+        tcf.core.vendor_implied_consents = [5, 6, 7, 8, 900].into();
 
-/// Created with the method [`sections`](GPPString::sections).
-pub struct Sections<'a> {
-    gpp_str: &'a GPPString,
-    idx: usize,
-}
+        let gpp_str = GPPString::try_from(vec![Section::TcfCaV1(tcf)]).unwrap();
+        let verbose = gpp_str.encode().unwrap();
+        let compact = gpp_str.to_compact_string().unwrap();
+        assert!(compact.len() < verbose.len(), "{compact} vs {verbose}");
 
-impl<'a> Iterator for Sections<'a> {
-    type Item = &'a str;
+        let compact = GPPString::from_str(&compact).unwrap();
+        assert_eq!(
+            compact.decode_section(SectionId::TcfCaV1).unwrap(),
+            gpp_str.decode_section(SectionId::TcfCaV1).unwrap()
+        );
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let section_id = self.gpp_str.section_ids.get(self.idx)?;
-        self.idx += 1;
-        self.gpp_str.section(*section_id)
+    #[test]
+    fn to_compact_string_invalid_section() {
+        let gpp_str = GPPString::from_str("DBACTM~1YNN~BVqq").unwrap();
+        assert!(matches!(
+            gpp_str.to_compact_string(),
+            Err(GPPEncodeError::SectionDecode {
+                id: SectionId::UsCa,
+                ..
+            })
+        ));
     }
-}
 
-impl<'a> ExactSizeIterator for Sections<'a> {
-    fn len(&self) -> usize {
-        self.gpp_str.section_ids.len()
+    #[test]
+    fn strict_enums() {
+        // the sale opt-out notice holds the undefined value 3
+        let s = "DBABBg~B1qqqqpY";
+
+        let gpp_str = GPPString::from_str(s).unwrap();
+        let Ok(Section::UsCa(usca)) = gpp_str.decode_section(SectionId::UsCa) else {
+            panic!("unable to decode section");
+        };
+        assert_eq!(
+            usca.core.as_v1().unwrap().sale_opt_out_notice,
+            crate::sections::us_common::Notice::NotApplicable
+        );
+
+        let options = ParseOptions::default().strict_enums(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        assert!(matches!(
+            gpp_str.decode_section(SectionId::UsCa),
+            Err(SectionDecodeError::InvalidFieldValue { found, .. }) if found == "3"
+        ));
     }
-}
 
-impl<'a> FusedIterator for Sections<'a> {}
+    #[test]
+    fn standard_base64() {
+        // "DBABMA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" with a standard alphabet
+        let s = "DBABMA~CPXxRfAPXxRfAAfKABENB+CgAAAAAAAAAAYgAAAAAAAA";
 
-/// Created with the method [`section_ids`](GPPString::section_ids).
-pub struct SectionIds<'a>(Iter<'a, SectionId>);
+        let gpp_str = GPPString::from_str(s).unwrap();
+        assert!(matches!(
+            gpp_str.decode_section(SectionId::TcfEuV2),
+            Err(SectionDecodeError::DecodeSegment {
+                index: 0,
+                source: DecodeError::StandardAlphabet(21, b'+')
+            })
+        ));
 
-impl<'a> Iterator for SectionIds<'a> {
-    type Item = &'a SectionId;
+        let options = ParseOptions::default().standard_base64(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        let expected = GPPString::from_str("DBABMA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA")
+            .unwrap()
+            .decode_section(SectionId::TcfEuV2)
+            .unwrap();
+        assert_eq!(
+            gpp_str.decode_section(SectionId::TcfEuV2).unwrap(),
+            expected
+        );
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0.next()
+    #[test_case("DBAA" => Vec::<Framework>::new() ; "header only")]
+    #[test_case("DBACTM~1YNN~BVqqqqpY.YA" => vec![Framework::UsPrivacy, Framework::UsStatePrivacy(SectionId::UsCa)] ; "us sections")]
+    fn frameworks(s: &str) -> Vec<Framework> {
+        GPPString::from_str(s)
+            .unwrap()
+            .frameworks()
+            .into_iter()
+            .collect()
     }
-}
 
-impl<'a> ExactSizeIterator for SectionIds<'a> {
-    fn len(&self) -> usize {
-        self.0.len()
+    #[test]
+    fn verify_section_order() {
+        let s = "DBACTM~BVqqqqpY.YA~1YNN";
+        assert!(GPPString::parse_str(s).is_ok());
+
+        let options = ParseOptions::default().verify_section_order(true);
+        let r = GPPString::parse_str_with_options(s, &options);
+        assert!(matches!(
+            r,
+            Err(GPPDecodeError::MisorderedSection {
+                id: SectionId::UspV1,
+                found: SectionId::UsCa
+            })
+        ));
     }
-}
 
-impl<'a> FusedIterator for SectionIds<'a> {}
+    #[test_case("DBACTM~1YNN~BVqqqqpY.YA" ; "ordered")]
+    #[test_case("DBACTM~1YNN~ZZ" ; "invalid section")]
+    fn verify_section_order_ok(s: &str) {
+        let options = ParseOptions::default().verify_section_order(true);
+        assert!(GPPString::parse_str_with_options(s, &options).is_ok());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::sections::uspv1::UspV1;
-    use test_case::test_case;
+    #[test]
+    fn from_reader() {
+        let options = ParseOptions::default().trim_whitespace(true);
+        let gpp_str = GPPString::from_reader_with_options(&b"DBABTA~1YNN\n"[..], &options).unwrap();
+        assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    }
 
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::TcfCaV1 => Some("BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA".to_string()) ; "tcf ca")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::UspV1 => Some("1YNN".to_string()) ; "usp v1")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::TcfEuV2 => None ; "tcf eu v2")]
-    fn gpp_string_section(s: &str, section_id: SectionId) -> Option<String> {
-        GPPString::from_str(s)
-            .unwrap()
-            .section(section_id)
-            .map(|s| s.to_string())
+    #[test]
+    fn from_reader_invalid_utf8() {
+        let r = GPPString::from_reader(&b"DBABTA~1YN\xff"[..]);
+        assert!(
+            matches!(r, Err(GPPDecodeError::Read(e)) if e.kind() == io::ErrorKind::InvalidData)
+        );
     }
 
-    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
-    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
-    fn gpp_string_section_ids(s: &str) -> Vec<SectionId> {
-        GPPString::from_str(s).unwrap().section_ids
+    #[test_case("DBABTA~1YNN" => "DBABTA~1YNN" ; "canonical")]
+    #[test_case("DBABTA%7E1YNN" => "DBABTA~1YNN" ; "encoded separator")]
+    #[test_case("DBABTA%7e1YNN" => "DBABTA~1YNN" ; "lowercase hex")]
+    #[test_case("DBABTA%7E1YNN%7" => "DBABTA~1YNN%7" ; "truncated sequence")]
+    #[test_case("DBABTA%+71YNN" => "DBABTA%+71YNN" ; "invalid hex")]
+    #[test_case("DBABTA%C3%A91YNN" => "DBABTA%C3%A91YNN" ; "non ascii")]
+    fn percent_decode(s: &str) -> String {
+        super::percent_decode(s).into_owned()
     }
 
-    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"] ; "single section")]
-    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "1YNN"] ; "tcf eu and us sections")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec!["BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA", "1YNN"] ; "tcf ca and us sections")]
-    fn gpp_string_sections(s: &str) -> Vec<String> {
-        GPPString::from_str(s)
-            .unwrap()
-            .sections()
-            .map(|s| s.to_string())
-            .collect()
+    #[test]
+    fn parse_url_encoded() {
+        let gpp_str = GPPString::parse_url_encoded(
+            "DBACNY%7ECPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA%7E1YNN",
+        )
+        .unwrap();
+        assert_eq!(gpp_str.section_count(), 2);
+        assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+
+        assert!(GPPString::parse_str("DBABTA%7E1YNN").is_err());
+        assert!(GPPString::parse_url_encoded("DBAB%4A%7E1YNN").is_err());
     }
 
-    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
-    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
-    fn gpp_string_decode_section(s: &str) -> Vec<SectionId> {
-        let s = GPPString::from_str(s).unwrap();
-        s.section_ids
-            .iter()
-            .map(|id| s.decode_section(*id).unwrap().id())
-            .collect()
+    #[test]
+    fn from_legacy_usp() {
+        let gpp_str = GPPString::from_legacy_usp("1YNN").unwrap();
+        assert_eq!(
+            gpp_str.section_ids().collect::<Vec<_>>(),
+            [&SectionId::UspV1]
+        );
+        assert_eq!(
+            gpp_str.decode::<UspV1>().unwrap(),
+            UspV1::from_str("1YNN").unwrap()
+        );
+
+        assert!(GPPString::parse_str("1YNN").is_err());
     }
 
-    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec![SectionId::TcfEuV2] ; "single section")]
-    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec![SectionId::TcfEuV2, SectionId::UspV1] ; "tcf eu and us sections")]
-    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec![SectionId::TcfCaV1, SectionId::UspV1] ; "tcf ca and us sections")]
-    fn gpp_string_decode_all(s: &str) -> Vec<SectionId> {
-        GPPString::from_str(s)
-            .unwrap()
-            .decode_all_sections()
-            .into_iter()
-            .map(|s| s.unwrap().id())
-            .collect()
+    #[test_case("" ; "empty")]
+    #[test_case("DBABTA~1YNN" ; "gpp string")]
+    #[test_case("2YNN" ; "invalid version")]
+    fn from_legacy_usp_error(s: &str) {
+        assert!(GPPString::from_legacy_usp(s).is_err());
     }
 
-    #[test_case("DBABTA~1YN-" => UspV1 {
-        opt_out_notice: crate::sections::uspv1::Flag::Yes,
-        opt_out_sale: crate::sections::uspv1::Flag::No,
-        lspa_covered_transaction: crate::sections::uspv1::Flag::NotApplicable,
-    } ; "mix")]
-    #[test_case("DBABTA~1NNN" => UspV1 {
-        opt_out_notice: crate::sections::uspv1::Flag::No,
-        opt_out_sale: crate::sections::uspv1::Flag::No,
-        lspa_covered_transaction: crate::sections::uspv1::Flag::No,
-    } ; "all no")]
-    #[test_case("DBABTA~1YYY" => UspV1 {
-        opt_out_notice: crate::sections::uspv1::Flag::Yes,
-        opt_out_sale: crate::sections::uspv1::Flag::Yes,
-        lspa_covered_transaction: crate::sections::uspv1::Flag::Yes,
-    } ; "all yes")]
-    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => UspV1 {
-        opt_out_notice: crate::sections::uspv1::Flag::Yes,
-        opt_out_sale: crate::sections::uspv1::Flag::No,
-        lspa_covered_transaction: crate::sections::uspv1::Flag::No,
-    } ; "with other section")]
-    fn gpp_string_decode_uspv1(s: &str) -> UspV1 {
-        GPPString::from_str(s).unwrap().decode().unwrap()
+    #[test_case("1YNN~junk" ; "section separator")]
+    #[test_case("1YNN.x" ; "segment separator")]
+    #[test_case("1YNNY" ; "extra character")]
+    fn from_legacy_usp_trailing_data(s: &str) {
+        assert!(matches!(
+            GPPString::from_legacy_usp(s),
+            Err(SectionDecodeError::TrailingData { .. })
+        ));
+    }
+
+    #[test_case("DBAA" => (0, true) ; "header only")]
+    #[test_case("DBABTA~1YNN" => (1, false) ; "one section")]
+    fn section_count(s: &str) -> (usize, bool) {
+        let gpp_str = GPPString::from_str(s).unwrap();
+        (gpp_str.section_count(), gpp_str.is_empty())
     }
 
     #[test]
-    fn truncated_string() {
-        let r = GPPString::from_str("DBACNY~CPytTYAPytTYABEACBENDXCoAP_AAH_AAAIwgoNf_X__b3_v-_7___t0eY1f9_7__-0zjhfdt-8N3f_X_L8X_2M7");
+    fn too_many_sections() {
+        let options = ParseOptions::default().max_sections(1);
+        let r = GPPString::parse_str_with_options(
+            "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN",
+            &options,
+        );
         assert!(matches!(
             r,
-            Err(GPPDecodeError::IdSectionMismatch {
-                ids: 2,
-                sections: 1
-            })
+            Err(GPPDecodeError::TooManySections { max: 1, found: 2 })
         ));
+
+        let r = GPPString::parse_str_with_options("DBABTA~1YNN", &options);
+        assert!(r.is_ok());
     }
 
     #[test]
-    fn non_gpp_tcfeuv2_string() {
-        let r = GPPString::from_str("CP48G0AP48G0AEsACCPLAkEgAAAAAEPgAB5YAAAQaQD2F2K2kKFkPCmQWYAQBCijYEAhQAAAAkCBIAAgAUgQAgFIIAgAIFAAAAAAAAAQEgCQAAQABAAAIACgAAAAAAIAAAAAAAQQAAAAAIAAAAAAAAEAAAAAAAQAAAAIAABEhCAAQQAEAAAAAAAQAAAAAAAAAAABAAAAAAAAAAAAAAAAAAAAgAA");
+    fn too_many_sections_stops_reading() {
+        // the header lists 3 sections but is truncated after the IDs 2 and 6, so the range
+        // can only be rejected for its length if reading stops before the third ID
+        let s = "DBADNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
         assert!(matches!(
-            r,
-            Err(GPPDecodeError::InvalidHeaderType { found: 2 })
+            GPPString::from_str(s),
+            Err(GPPDecodeError::Read(_))
+        ));
+
+        let options = ParseOptions::default().max_sections(1);
+        assert!(matches!(
+            GPPString::parse_str_with_options(s, &options),
+            Err(GPPDecodeError::TooManySections { max: 1, found: 2 })
         ));
     }
 
     #[test]
-    fn invalid_tcfca_section() {
-        let r = GPPString::from_str("DBABjw~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")
-            .unwrap()
-            .decode_all_sections();
+    fn empty_section() {
+        let gpp_str = GPPString::from_str("DBABTA~").unwrap();
         assert!(matches!(
-            r[0],
-            Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 2 })
+            gpp_str.decode_section(SectionId::UspV1),
+            Err(SectionDecodeError::EmptySection(SectionId::UspV1))
+        ));
+        assert!(matches!(
+            gpp_str.decode::<UspV1>(),
+            Err(SectionDecodeError::EmptySection(SectionId::UspV1))
         ));
     }
 
     #[test]
-    fn invalid_tcfeuv2_section() {
-        let r = GPPString::from_str("DBABMA~CQLvHAAQLvHAAAKA4DENBaFsAP_gAEPgAAwIKxtX_G9_bXlr8X736ftkeY1f99h77sQxBhZBk-4FzLvW_JwX32E7NA36tqYKmRIAu3TBIQNlHJDURVCgaogVrTDMaEyUoTtKJ6BkiFMRY2dYCFxvm4tjeQCY5vr991d52R-tbdrs3dzyy4hnv3a9_-S1WJCdA5-tDfv9bROb89IO5_x8v4v4_N7pE2_eT1l_tWvp7D9-ctv_9XX99_fbff9Pn_-uB_-_X__f_H37grAAQYCABAEAQICAAAAAQAAEAAEABAAAAAAACgAABEEAAEDAAAQAIAQAAABAABAAAAIAAAAAgACAAAAAEAgAAAACgADAAAAAAAYAAAMAEgIAAAAAQACmABAIFAAEJAFAEACEAEEAIQAABAEACAEABRwBAACBAoAAAQAAEAAAFgIDgAQEpAgACIgEAAAIAEAggAAEQjYACCAASCqqBAiiCAQLBoQFPaQAkgBACDgmQAgABQAHAAsA.f_gAAAAAAAAA").unwrap()
-            .decode_all_sections();
-        assert!(matches!(r[0], Err(SectionDecodeError::Read { .. })));
+    fn try_decode() {
+        let gpp_str = GPPString::from_str("DBABTA~1YNN").unwrap();
+        assert_eq!(
+            gpp_str.try_decode::<UspV1>().unwrap(),
+            Some(gpp_str.decode::<UspV1>().unwrap())
+        );
+        assert!(matches!(
+            gpp_str.try_decode::<crate::sections::tcfeuv2::TcfEuV2>(),
+            Ok(None)
+        ));
+
+        let gpp_str = GPPString::from_str("DBABTA~").unwrap();
+        assert!(matches!(
+            gpp_str.try_decode::<UspV1>(),
+            Err(SectionDecodeError::EmptySection(SectionId::UspV1))
+        ));
+
+        let gpp_str = GPPString::from_str("DBABTA~2YNN").unwrap();
+        assert!(matches!(
+            gpp_str.try_decode::<UspV1>(),
+            Err(SectionDecodeError::InvalidSectionVersion { found: 2, .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_json() {
+        let gpp_str =
+            GPPString::from_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")
+                .unwrap();
+        let json = gpp_str.to_json().unwrap();
+
+        let o = json.as_object().unwrap();
+        assert_eq!(o.len(), 2);
+        #[cfg(not(feature = "serde-iab-names"))]
+        {
+            assert_eq!(json["TcfEuV2"]["core"]["cmp_id"], 31);
+            assert_eq!(json["UspV1"]["opt_out_notice"], "Yes");
+        }
+        #[cfg(feature = "serde-iab-names")]
+        {
+            assert_eq!(json["TcfEuV2"]["Core"]["CmpId"], 31);
+            assert_eq!(json["UspV1"]["OptOutNotice"], "Yes");
+        }
+
+        let gpp_str = GPPString::from_str("DBACTMA~1YNN~BVqqqqpY.YA").unwrap();
+        let json = gpp_str.to_json().unwrap();
+        #[cfg(not(feature = "serde-iab-names"))]
+        assert_eq!(
+            json["UsCa"]["core"]["V1"]["sale_opt_out_notice"],
+            "Provided"
+        );
+        #[cfg(feature = "serde-iab-names")]
+        assert_eq!(json["UsCa"]["Core"]["V1"]["SaleOptOutNotice"], "Provided");
+    }
+
+    #[test]
+    fn redact() {
+        let gpp_str = GPPString::from_str(&format!("DBACNY~{TCF_EU_V2}~1YNN")).unwrap();
+        let redacted = GPPString::from_str(&gpp_str.redact().unwrap().encode().unwrap()).unwrap();
+
+        let (original, tcf) = (
+            gpp_str
+                .decode::<crate::sections::tcfeuv2::TcfEuV2>()
+                .unwrap(),
+            redacted
+                .decode::<crate::sections::tcfeuv2::TcfEuV2>()
+                .unwrap(),
+        );
+        assert_eq!(tcf.core.created.deciseconds(), 0);
+        assert_eq!(tcf.core.last_updated.deciseconds(), 0);
+        assert_eq!(tcf.core.cmp_id, 0);
+        assert_eq!(tcf.core.cmp_version, 0);
+        assert_eq!(tcf.core.consent_screen, 0);
+        assert_eq!(tcf.core.purpose_consents, original.core.purpose_consents);
+        assert_eq!(tcf.core.vendor_consents, original.core.vendor_consents);
+        assert_eq!(
+            redacted.decode::<UspV1>().unwrap(),
+            gpp_str.decode::<UspV1>().unwrap()
+        );
+    }
+
+    #[test]
+    fn redact_invalid_section() {
+        let gpp_str = GPPString::from_str(&format!("DBACNY~{TCF_EU_V2}~1YNX")).unwrap();
+        let e = gpp_str.redact().unwrap_err();
+        assert!(matches!(
+            e,
+            GPPEncodeError::SectionDecode {
+                id: SectionId::UspV1,
+                ..
+            }
+        ));
+        assert!(!e.to_string().contains("1YNX"));
+    }
+
+    #[test_case("DBAA" => (vec![-1], vec![]) ; "header only")]
+    #[test_case("DBABTA~1YNN" => (vec![6], vec![6]) ; "single section")]
+    #[test_case("DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => (vec![2, 6], vec![2, 6]) ; "two sections")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => (vec![2, 6], vec![2, 6]) ; "unpadded header")]
+    fn cmp_api_view(s: &str) -> (Vec<i32>, Vec<i32>) {
+        let view = GPPString::from_str(s).unwrap().cmp_api_view();
+        assert_eq!(view.gpp_string, s);
+        assert_eq!(view.signal_status, SignalStatus::Ready);
+        (view.applicable_sections, view.section_list)
+    }
+
+    #[test]
+    fn cmp_api_view_built() {
+        let gpp_str = GPPString::from_legacy_usp("1YNN").unwrap();
+        assert_eq!(gpp_str.cmp_api_view().gpp_string, "DBABTA~1YNN");
+
+        let sections = vec![Section::UspV1("1YNN".parse().unwrap())];
+        let gpp_str = GPPString::try_from(sections).unwrap();
+        assert_eq!(gpp_str.cmp_api_view().gpp_string, "DBABTA~1YNN");
+    }
+
+    #[test]
+    fn usp_v1_header() {
+        assert_eq!(encode_header(&[SectionId::UspV1]).unwrap(), USP_V1_HEADER);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn cmp_api_view_json() {
+        let view = GPPString::from_str("DBABTA~1YNN").unwrap().cmp_api_view();
+        assert_eq!(
+            serde_json::to_value(view).unwrap(),
+            serde_json::json!({
+                "gppString": "DBABTA~1YNN",
+                "applicableSections": [6],
+                "sectionList": [6],
+                "signalStatus": "ready",
+            })
+        );
+    }
+
+    #[test_case("DBABTA~1YNN", "DBABTA~1YNN" => true ; "canonical")]
+    #[test_case("DBABT~1YNN", "DBABTA~1YNN" => true ; "unpadded header")]
+    #[test_case("DBABT~1YNN", "DBABT~1YNN" => false ; "non canonical")]
+    #[test_case("DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN", "DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => true ; "two sections")]
+    #[test_case("DBABTA~1YNN", "DBABTA~1YNY" => false ; "different section")]
+    #[test_case("DBABTA~1YNN", "" => false ; "empty")]
+    fn eq_str(s: &str, other: &str) -> bool {
+        let gpp_str = GPPString::from_str(s).unwrap();
+        assert_eq!(gpp_str == *other, gpp_str == other);
+        gpp_str == other
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let s = "DBACNYA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let gpp_str: GPPString = serde_json::from_value(serde_json::json!(s)).unwrap();
+        assert_eq!(
+            gpp_str.section_ids().copied().collect::<Vec<_>>(),
+            vec![SectionId::TcfEuV2, SectionId::UspV1]
+        );
+        assert_eq!(
+            serde_json::to_value(&gpp_str).unwrap(),
+            serde_json::json!(s)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test_case(serde_json::json!("DBACNY~1YNN") ; "invalid string")]
+    #[test_case(serde_json::json!(42) ; "not a string")]
+    fn serde_deserialize_error(value: serde_json::Value) {
+        assert!(serde_json::from_value::<GPPString>(value).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_json_decode_error() {
+        let gpp_str = GPPString::from_str("DBABTA~1YNX").unwrap();
+        assert!(gpp_str.to_json().is_err());
     }
 
     macro_rules! assert_implements {
@@ -568,11 +2904,105 @@ mod tests {
 
     #[test]
     fn gpp_string_implements_traits() {
-        assert_implements!(GPPString, [Send, Sync]);
+        assert_implements!(GPPString, [Send, Sync, Clone]);
     }
 
     #[test]
     fn section_implements_traits() {
-        assert_implements!(Section, [Send, Sync]);
+        assert_implements!(Section, [Send, Sync, Clone, Eq, std::hash::Hash, Ord]);
+    }
+
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN", SectionId::UspV1 => Some(vec![b"1YNN".to_vec()]) ; "usp v1")]
+    #[test_case("DBABBg~BVqqqqpY", SectionId::UsCa => Some(vec![vec![0x05, 0x5a, 0xaa, 0xaa, 0xaa, 0x58]]) ; "core only")]
+    #[test_case("DBABBg~BVqqqqpY", SectionId::UspV1 => None ; "missing")]
+    fn section_raw_bytes(s: &str, id: SectionId) -> Option<Vec<Vec<u8>>> {
+        GPPString::from_str(s)
+            .unwrap()
+            .section_raw_bytes(id)
+            .map(Result::unwrap)
+    }
+
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN", SectionId::TcfEuV2 => matches Some(Ok(2)) ; "tcf eu v2")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::TcfCaV1 => matches Some(Ok(1)) ; "tcf ca")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN", SectionId::UspV1 => matches Some(Ok(1)) ; "usp v1")]
+    #[test_case("DBABBg~BVqqqqpY.YA", SectionId::UsCa => matches Some(Ok(1)) ; "us ca")]
+    #[test_case("DBABBg~CVqqqqpY", SectionId::UsCa => matches Some(Ok(2)) ; "unsupported version")]
+    #[test_case("DBABBg~BVqqqqpY", SectionId::UsNat => matches None ; "missing section")]
+    #[test_case("DBABBg~*Vqq", SectionId::UsCa => matches Some(Err(SectionDecodeError::DecodeSegment { index: 0, source: DecodeError::InvalidByte(0, b'*') })) ; "invalid character")]
+    #[test_case("DBABTA~XYNN", SectionId::UspV1 => matches Some(Err(SectionDecodeError::InvalidCharacter { character: 'X', .. })) ; "invalid usp v1 version")]
+    #[test_case("DBABBg~", SectionId::UsCa => matches Some(Err(SectionDecodeError::Read(_))) ; "empty section")]
+    fn section_version(s: &str, id: SectionId) -> Option<Result<u8, SectionDecodeError>> {
+        GPPString::from_str(s).unwrap().section_version(id)
+    }
+
+    #[test]
+    fn section_raw_bytes_standard_base64() {
+        let s = "DBABMA~CPXxRfAPXxRfAAfKABENB+CgAAAAAAAAAAYgAAAAAAAA";
+        let expected = GPPString::from_str("DBABMA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA")
+            .unwrap()
+            .section_raw_bytes(SectionId::TcfEuV2)
+            .unwrap()
+            .unwrap();
+
+        let gpp_str = GPPString::from_str(s).unwrap();
+        assert!(matches!(
+            gpp_str.section_raw_bytes(SectionId::TcfEuV2),
+            Some(Err(DecodeError::StandardAlphabet(21, b'+')))
+        ));
+
+        let options = ParseOptions::default().standard_base64(true);
+        let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+        assert_eq!(
+            gpp_str
+                .section_raw_bytes(SectionId::TcfEuV2)
+                .unwrap()
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn section_raw_bytes_invalid() {
+        let gpp_str = GPPString::from_str("DBABBg~BVqq*qpY").unwrap();
+        assert!(matches!(
+            gpp_str.section_raw_bytes(SectionId::UsCa),
+            Some(Err(DecodeError::InvalidByte(_, b'*')))
+        ));
+    }
+
+    #[test]
+    fn into_iterator() {
+        let gpp_str = GPPString::from_str("DBACTM~1YNN~BVqqqqpY.YA").unwrap();
+        let mut it = (&gpp_str).into_iter();
+        assert_eq!(it.len(), 2);
+        assert!(matches!(it.next(), Some(Ok(Section::UspV1(_)))));
+        assert_eq!(it.len(), 1);
+        assert!(matches!(it.next(), Some(Ok(Section::UsCa(_)))));
+        assert!(it.next().is_none());
+    }
+
+    #[test]
+    fn into_iterator_with_invalid_section() {
+        // the second section cannot be decoded, which does not affect the first one
+        let gpp_str = GPPString::from_str("DBACTM~1YNN~B").unwrap();
+        let mut decoded = 0;
+        for section in &gpp_str {
+            if section.is_err() {
+                break;
+            }
+            decoded += 1;
+        }
+        assert_eq!(decoded, 1);
+        assert!((&gpp_str).into_iter().take(1).all(|r| r.is_ok()));
+    }
+
+    #[test]
+    fn distinct_sections() {
+        let sections = ["DBABTA~1YNN", "DBABTA~1YNN", "DBABTA~1NNN"]
+            .into_iter()
+            .flat_map(|s| GPPString::from_str(s).unwrap().decode_all_sections())
+            .collect::<Result<std::collections::HashSet<_>, _>>()
+            .unwrap();
+        assert_eq!(sections.len(), 2);
     }
 }
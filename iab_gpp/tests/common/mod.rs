@@ -0,0 +1,33 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A decoding test vector, read from a JSON fixture file.
+///
+/// The expected sections are listed in the order of the string, each one in the
+/// representation returned by `Section::to_json`.
+#[derive(Debug, Deserialize)]
+pub struct TestCase {
+    pub gpp_string: String,
+    pub expected_sections: Vec<serde_json::Value>,
+}
+
+impl TestCase {
+    pub fn load(path: &Path) -> Self {
+        let json = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("unable to read {}: {e}", path.display()));
+        serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("unable to parse {}: {e}", path.display()))
+    }
+}
+
+/// Returns the paths of all JSON fixture files of the given directory, sorted by name.
+pub fn fixture_paths(dir: &Path) -> Vec<PathBuf> {
+    let mut paths = fs::read_dir(dir)
+        .unwrap_or_else(|e| panic!("unable to read {}: {e}", dir.display()))
+        .map(|entry| entry.expect("unable to read directory entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
@@ -0,0 +1,53 @@
+//! Decodes each fixture of the `tests/fixtures` directory and compares the result with the
+//! expected sections.
+//!
+//! Fixtures use the field names of the Rust types, so the suite does not run with the
+//! `serde-iab-names` feature. More test vectors, such as the ones published by the IAB, can
+//! be added by dropping files of the same format in the directory.
+#![cfg(all(feature = "serde", not(feature = "serde-iab-names")))]
+
+mod common;
+
+use common::{fixture_paths, TestCase};
+use iab_gpp::v1::GPPString;
+use std::path::Path;
+
+fn decode(test_case: &TestCase) -> Result<Vec<serde_json::Value>, String> {
+    let gpp_str = GPPString::parse_str(&test_case.gpp_string).map_err(|e| e.to_string())?;
+
+    gpp_str
+        .decode_all_sections()
+        .into_iter()
+        .map(|r| {
+            r.map_err(|e| e.to_string())?
+                .to_json()
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let paths = fixture_paths(&dir);
+    assert!(!paths.is_empty(), "no fixtures found in {}", dir.display());
+
+    // report all failing fixtures at once
+    let failures = paths
+        .iter()
+        .filter_map(|path| {
+            let test_case = TestCase::load(path);
+            match decode(&test_case) {
+                Ok(sections) if sections == test_case.expected_sections => None,
+                Ok(sections) => Some(format!(
+                    "{}: decoded {}",
+                    path.display(),
+                    serde_json::Value::from(sections)
+                )),
+                Err(e) => Some(format!("{}: {e}", path.display())),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
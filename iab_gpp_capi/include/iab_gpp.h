@@ -0,0 +1,81 @@
+/*
+ * C API for the IAB GPP Consent String implementation.
+ *
+ * Handles returned by gpp_parse() must be released using gpp_free().
+ */
+#ifndef IAB_GPP_H
+#define IAB_GPP_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum GppStatus {
+    GPP_OK = 0,
+    GPP_NULL_POINTER = -1,
+    GPP_INVALID_UTF8 = -2,
+    GPP_INVALID_INDEX = -3,
+    GPP_SERIALIZATION = -4,
+    GPP_UNKNOWN = -9,
+
+    /* GPP string errors */
+    GPP_NO_HEADER_FOUND = -10,
+    GPP_DECODE_HEADER = -11,
+    GPP_INVALID_HEADER_TYPE = -12,
+    GPP_INVALID_GPP_VERSION = -13,
+    GPP_READ = -14,
+    GPP_UNSUPPORTED_SECTION_ID = -15,
+    GPP_ID_SECTION_MISMATCH = -16,
+    GPP_TOO_MANY_SECTIONS = -19,
+
+    /* Section errors */
+    GPP_MISSING_SECTION = -20,
+    GPP_SECTION_UNSUPPORTED_SECTION_ID = -21,
+    GPP_SECTION_READ = -22,
+    GPP_UNEXPECTED_END_OF_STRING = -23,
+    GPP_INVALID_CHARACTER = -24,
+    GPP_INVALID_SECTION_VERSION = -25,
+    GPP_DECODE_SEGMENT = -26,
+    GPP_UNKNOWN_SEGMENT_VERSION = -27,
+    GPP_UNKNOWN_SEGMENT_TYPE = -28,
+    GPP_DUPLICATE_SEGMENT_TYPE = -29,
+    GPP_MISSING_CORE_SEGMENT = -30,
+    GPP_INVALID_FIELD_VALUE = -31,
+    GPP_EMPTY_SECTION = -32,
+    GPP_TRAILING_DATA = -33,
+    GPP_EMPTY_SEGMENT = -34,
+
+    /* GPP string errors, continued */
+    GPP_UNSUPPORTED_GPP_VERSION = -40,
+    GPP_MISORDERED_SECTION = -41,
+    GPP_EMPTY_INPUT = -42
+} GppStatus;
+
+typedef struct GppHandle GppHandle;
+
+/* Parses a NUL-terminated GPP string. Returns NULL on failure. */
+GppHandle *gpp_parse(const char *s, GppStatus *status);
+
+/* Returns the number of sections in the string, or 0 if handle is NULL. */
+size_t gpp_section_count(const GppHandle *handle);
+
+/* Returns the ID of the section at the given index, or a negative GppStatus. */
+int gpp_section_id_at(const GppHandle *handle, size_t index);
+
+/*
+ * Writes the JSON representation of a decoded section into buf, truncating
+ * and NUL-terminating it if needed. Returns the full JSON length, or a
+ * negative GppStatus.
+ */
+int gpp_decode_section_json(const GppHandle *handle, int id, char *buf, size_t len);
+
+/* Releases a handle returned by gpp_parse(). */
+void gpp_free(GppHandle *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* IAB_GPP_H */
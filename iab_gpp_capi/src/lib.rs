@@ -0,0 +1,356 @@
+//! C API for the [`iab_gpp`] crate.
+//!
+//! This crate exposes a small set of `extern "C"` functions allowing non-Rust programs
+//! to parse GPP strings and obtain decoded sections as JSON documents.
+//!
+//! The matching C declarations can be found in the `include/iab_gpp.h` header.
+//!
+//! # Memory management
+//!
+//! A handle returned by [`gpp_parse`] is owned by the caller and must be released
+//! with [`gpp_free`]. Buffers passed to [`gpp_decode_section_json`] are always owned
+//! by the caller.
+//!
+//! # JSON field names
+//!
+//! Decoded sections use the Rust field names of the [`iab_gpp`] crate, such as
+//! `opt_out_sale`. With the `serde-iab-names` feature, they use the PascalCase names of the
+//! IAB specification instead, such as `OptOutSale`.
+//!
+//! # Error handling
+//!
+//! Functions which can fail report errors using the [`GppStatus`] enumeration.
+//! Its values are stable and will never be renumbered.
+//!
+use iab_gpp::sections::{SectionDecodeError, SectionId};
+use iab_gpp::v1::{GPPDecodeError, GPPString};
+use num_traits::FromPrimitive;
+use std::ffi::{c_char, c_int, CStr};
+use std::ptr;
+
+/// Status codes returned by the C API.
+///
+/// All error codes are negative, which allows functions returning a size or an identifier
+/// to report errors using the same return value.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GppStatus {
+    Ok = 0,
+    /// A mandatory pointer argument was null.
+    NullPointer = -1,
+    /// The input string is not valid UTF-8.
+    InvalidUtf8 = -2,
+    /// A section index is out of bounds.
+    InvalidIndex = -3,
+    /// The decoded section could not be serialized to JSON.
+    Serialization = -4,
+    /// An error not covered by any other status code.
+    Unknown = -9,
+
+    // GPP string errors, see GPPDecodeError
+    NoHeaderFound = -10,
+    DecodeHeader = -11,
+    InvalidHeaderType = -12,
+    InvalidGppVersion = -13,
+    Read = -14,
+    UnsupportedSectionId = -15,
+    IdSectionMismatch = -16,
+    TooManySections = -19,
+
+    // Section errors, see SectionDecodeError
+    MissingSection = -20,
+    SectionUnsupportedSectionId = -21,
+    SectionRead = -22,
+    UnexpectedEndOfString = -23,
+    InvalidCharacter = -24,
+    InvalidSectionVersion = -25,
+    DecodeSegment = -26,
+    UnknownSegmentVersion = -27,
+    UnknownSegmentType = -28,
+    DuplicateSegmentType = -29,
+    MissingCoreSegment = -30,
+    InvalidFieldValue = -31,
+    EmptySection = -32,
+    TrailingData = -33,
+    EmptySegment = -34,
+
+    // GPP string errors added after the section errors, see GPPDecodeError
+    UnsupportedGppVersion = -40,
+    MisorderedSection = -41,
+    EmptyInput = -42,
+}
+
+impl From<&GPPDecodeError> for GppStatus {
+    fn from(e: &GPPDecodeError) -> Self {
+        match e {
+            GPPDecodeError::NoHeaderFound => Self::NoHeaderFound,
+            GPPDecodeError::DecodeHeader(_) => Self::DecodeHeader,
+            GPPDecodeError::InvalidHeaderType { .. } => Self::InvalidHeaderType,
+            GPPDecodeError::InvalidGPPVersion { .. } => Self::InvalidGppVersion,
+            GPPDecodeError::UnsupportedGPPVersion { .. } => Self::UnsupportedGppVersion,
+            GPPDecodeError::Read(_) => Self::Read,
+            GPPDecodeError::UnsupportedSectionId(_) => Self::UnsupportedSectionId,
+            GPPDecodeError::IdSectionMismatch { .. } => Self::IdSectionMismatch,
+            GPPDecodeError::TooManySections { .. } => Self::TooManySections,
+            GPPDecodeError::MisorderedSection { .. } => Self::MisorderedSection,
+            GPPDecodeError::SectionDecode { source, .. } => Self::from(source),
+            GPPDecodeError::EmptyInput => Self::EmptyInput,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl From<&SectionDecodeError> for GppStatus {
+    fn from(e: &SectionDecodeError) -> Self {
+        match e {
+            SectionDecodeError::MissingSection(_) => Self::MissingSection,
+            SectionDecodeError::UnsupportedSectionId(_) => Self::SectionUnsupportedSectionId,
+            SectionDecodeError::Read(_) => Self::SectionRead,
+            SectionDecodeError::UnexpectedEndOfString(_) => Self::UnexpectedEndOfString,
+            SectionDecodeError::InvalidCharacter { .. } => Self::InvalidCharacter,
+            SectionDecodeError::InvalidSectionVersion { .. } => Self::InvalidSectionVersion,
+            SectionDecodeError::DecodeSegment { .. } => Self::DecodeSegment,
+            SectionDecodeError::UnknownSegmentVersion { .. } => Self::UnknownSegmentVersion,
+            SectionDecodeError::UnknownSegmentType { .. } => Self::UnknownSegmentType,
+            SectionDecodeError::DuplicateSegmentType { .. } => Self::DuplicateSegmentType,
+            SectionDecodeError::MissingCoreSegment => Self::MissingCoreSegment,
+            SectionDecodeError::InvalidFieldValue { .. } => Self::InvalidFieldValue,
+            SectionDecodeError::EmptySection(_) => Self::EmptySection,
+            SectionDecodeError::TrailingData { .. } => Self::TrailingData,
+            SectionDecodeError::EmptySegment { .. } => Self::EmptySegment,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Opaque handle to a parsed GPP string.
+pub struct GppHandle(GPPString);
+
+/// Parses a NUL-terminated GPP string.
+///
+/// Returns a handle to the parsed string, or a null pointer if parsing fails.
+/// If `status` is not null, it receives the outcome of the operation.
+///
+/// # Safety
+///
+/// `s` must be null or point to a valid NUL-terminated string.
+/// `status` must be null or point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn gpp_parse(s: *const c_char, status: *mut GppStatus) -> *mut GppHandle {
+    let (handle, st) = match parse(s) {
+        Ok(gpp_str) => (Box::into_raw(Box::new(GppHandle(gpp_str))), GppStatus::Ok),
+        Err(st) => (ptr::null_mut(), st),
+    };
+
+    if !status.is_null() {
+        *status = st;
+    }
+
+    handle
+}
+
+unsafe fn parse(s: *const c_char) -> Result<GPPString, GppStatus> {
+    if s.is_null() {
+        return Err(GppStatus::NullPointer);
+    }
+
+    let s = CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| GppStatus::InvalidUtf8)?;
+
+    GPPString::parse_str(s).map_err(|e| GppStatus::from(&e))
+}
+
+/// Returns the number of sections present in a parsed GPP string.
+///
+/// Returns 0 if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by [`gpp_parse`] which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gpp_section_count(handle: *const GppHandle) -> usize {
+    match handle.as_ref() {
+        Some(h) => h.0.section_ids().len(),
+        None => 0,
+    }
+}
+
+/// Returns the ID of the section at position `index` in a parsed GPP string.
+///
+/// Returns a negative [`GppStatus`] value if `handle` is null or `index` is out of bounds.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by [`gpp_parse`] which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gpp_section_id_at(handle: *const GppHandle, index: usize) -> c_int {
+    let Some(h) = handle.as_ref() else {
+        return GppStatus::NullPointer as c_int;
+    };
+
+    match h.0.section_ids().nth(index) {
+        Some(&id) => id as c_int,
+        None => GppStatus::InvalidIndex as c_int,
+    }
+}
+
+/// Decodes a section and writes its JSON representation into `buf`.
+///
+/// At most `len - 1` bytes are written, followed by a terminating NUL byte.
+/// Passing a null `buf` or a zero `len` only computes the required length.
+///
+/// Returns the length of the full JSON document, not including the terminating NUL byte,
+/// which may be larger than the number of bytes actually written.
+/// Returns a negative [`GppStatus`] value on error.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by [`gpp_parse`] which has not been freed.
+/// `buf` must be null or point to at least `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gpp_decode_section_json(
+    handle: *const GppHandle,
+    id: c_int,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    let Some(h) = handle.as_ref() else {
+        return GppStatus::NullPointer as c_int;
+    };
+
+    let json = match decode_section_json(&h.0, id) {
+        Ok(json) => json,
+        Err(st) => return st as c_int,
+    };
+
+    if !buf.is_null() && len > 0 {
+        let n = json.len().min(len - 1);
+        ptr::copy_nonoverlapping(json.as_ptr(), buf.cast(), n);
+        *buf.add(n) = 0;
+    }
+
+    c_int::try_from(json.len()).unwrap_or(GppStatus::Serialization as c_int)
+}
+
+fn decode_section_json(gpp_str: &GPPString, id: c_int) -> Result<String, GppStatus> {
+    let id = u8::try_from(id)
+        .ok()
+        .and_then(SectionId::from_u8)
+        .ok_or(GppStatus::UnsupportedSectionId)?;
+    let section = gpp_str
+        .decode_section(id)
+        .map_err(|e| GppStatus::from(&e))?;
+
+    serde_json::to_string(&section).map_err(|_| GppStatus::Serialization)
+}
+
+/// Releases a handle returned by [`gpp_parse`].
+///
+/// Does nothing if `handle` is null.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by [`gpp_parse`] which has not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn gpp_free(handle: *mut GppHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use test_case::test_case;
+
+    fn parse_str(s: &str) -> (*mut GppHandle, GppStatus) {
+        let s = CString::new(s).unwrap();
+        let mut status = GppStatus::Unknown;
+        let handle = unsafe { gpp_parse(s.as_ptr(), &mut status) };
+        (handle, status)
+    }
+
+    #[test]
+    fn parse_and_free() {
+        let (handle, status) =
+            parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN");
+        assert_eq!(status, GppStatus::Ok);
+        assert!(!handle.is_null());
+
+        unsafe {
+            assert_eq!(gpp_section_count(handle), 2);
+            assert_eq!(gpp_section_id_at(handle, 0), 2);
+            assert_eq!(gpp_section_id_at(handle, 1), 6);
+            assert_eq!(
+                gpp_section_id_at(handle, 2),
+                GppStatus::InvalidIndex as c_int
+            );
+            gpp_free(handle);
+        }
+    }
+
+    #[test_case("" => GppStatus::EmptyInput ; "empty string")]
+    #[test_case("~1YNN" => GppStatus::NoHeaderFound ; "missing header")]
+    #[test_case("DBACNY~1YNN" => GppStatus::IdSectionMismatch ; "missing section")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => GppStatus::InvalidHeaderType ; "not a gpp string")]
+    fn parse_error(s: &str) -> GppStatus {
+        let (handle, status) = parse_str(s);
+        assert!(handle.is_null());
+        status
+    }
+
+    #[test]
+    fn null_pointers() {
+        let mut status = GppStatus::Ok;
+        unsafe {
+            assert!(gpp_parse(ptr::null(), &mut status).is_null());
+            assert_eq!(status, GppStatus::NullPointer);
+            assert_eq!(gpp_section_count(ptr::null()), 0);
+            assert_eq!(
+                gpp_section_id_at(ptr::null(), 0),
+                GppStatus::NullPointer as c_int
+            );
+            gpp_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn decode_section_json() {
+        let (handle, _) = parse_str("DBABTA~1YN-");
+        #[cfg(not(feature = "serde-iab-names"))]
+        let expected = r#"{"UspV1":{"opt_out_notice":"Yes","opt_out_sale":"No","lspa_covered_transaction":"NotApplicable"}}"#;
+        #[cfg(feature = "serde-iab-names")]
+        let expected = r#"{"UspV1":{"OptOutNotice":"Yes","OptOutSale":"No","LspaCoveredTransaction":"NotApplicable"}}"#;
+
+        unsafe {
+            let n = gpp_decode_section_json(handle, 6, ptr::null_mut(), 0);
+            assert_eq!(n, expected.len() as c_int);
+
+            let mut buf = vec![0 as c_char; n as usize + 1];
+            let n = gpp_decode_section_json(handle, 6, buf.as_mut_ptr(), buf.len());
+            assert_eq!(n, expected.len() as c_int);
+            assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str(), Ok(expected));
+
+            // truncated output is still NUL-terminated
+            let mut buf = vec![0 as c_char; 8];
+            gpp_decode_section_json(handle, 6, buf.as_mut_ptr(), buf.len());
+            assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str(), Ok(&expected[..7]));
+
+            gpp_free(handle);
+        }
+    }
+
+    #[test_case(2 => GppStatus::MissingSection as c_int ; "missing section")]
+    #[test_case(0 => GppStatus::UnsupportedSectionId as c_int ; "invalid id")]
+    #[test_case(300 => GppStatus::UnsupportedSectionId as c_int ; "out of range id")]
+    fn decode_section_json_error(id: c_int) -> c_int {
+        let (handle, _) = parse_str("DBABTA~1YN-");
+        unsafe {
+            let n = gpp_decode_section_json(handle, id, ptr::null_mut(), 0);
+            gpp_free(handle);
+            n
+        }
+    }
+}
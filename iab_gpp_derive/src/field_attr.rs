@@ -4,7 +4,7 @@ use quote::{format_ident, quote};
 use syn::meta::ParseNestedMeta;
 use syn::parse::ParseStream;
 use syn::punctuated::Punctuated;
-use syn::{parenthesized, parse, token, Attribute, Expr, ExprCall, LitInt};
+use syn::{parenthesized, parse, token, Attribute, Expr, ExprCall, LitInt, Token, Type};
 
 pub enum GPPFieldParser {
     FromDataReader,
@@ -26,12 +26,85 @@ impl GPPFieldParser {
             },
         }
     }
+
+    // a fixed integer read with a literal width must fit the field type,
+    // otherwise the layout is wrong and every decode would fail, so this is
+    // checked when the section is compiled
+    pub fn width_check(&self, name: &Ident, ty: &Type) -> Option<proc_macro2::TokenStream> {
+        let GPPFieldParser::ReaderCall(c) = &self else {
+            return None;
+        };
+        let Expr::Path(p) = c.func.as_ref() else {
+            return None;
+        };
+        if !p.path.is_ident("read_fixed_integer") || c.args.len() != 1 {
+            return None;
+        }
+        let Some(Expr::Lit(bits)) = c.args.first() else {
+            return None;
+        };
+        let msg = format!(
+            "fixed integer width of field {name} does not fit in {}",
+            quote!(#ty)
+        );
+        Some(quote! {
+            const _: () = assert!(#bits <= <#ty>::BITS, #msg);
+        })
+    }
+
+    // the writer mirrors the parser: a call to r.read_X(args) becomes a
+    // call to w.write_X(args, value), and a parse_with function is paired
+    // with a write_with function
+    pub fn to_writer_token_stream(
+        &self,
+        write_with: Option<&Ident>,
+        value: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        match &self {
+            GPPFieldParser::FromDataReader => quote! {
+                w.write(#value)
+            },
+            GPPFieldParser::ReaderCall(c) => {
+                let mut c = c.clone();
+                // integers are written by value
+                let value = match c.func.as_ref() {
+                    Expr::Path(p) if p.path.is_ident("read_fixed_integer") => quote! { *#value },
+                    _ => value,
+                };
+                if let Expr::Path(p) = c.func.as_mut() {
+                    if let Some(segment) = p.path.segments.last_mut() {
+                        let name = segment.ident.to_string();
+                        let name = name.strip_prefix("read_").unwrap_or(&name);
+                        segment.ident = format_ident!("write_{name}");
+                    }
+                }
+                c.args.push(Expr::Verbatim(value));
+                quote! {
+                    w.#c
+                }
+            }
+            GPPFieldParser::Function(f) => match write_with {
+                Some(f) => quote! {
+                    #f(w, #value)
+                },
+                None => {
+                    let msg = format!("field parsed with {f} requires a write_with function");
+                    quote! {
+                        compile_error!(#msg)
+                    }
+                }
+            },
+        }
+    }
 }
 
 pub struct GPPFieldHelperAttribute {
     pub optional_segment_type: Option<u8>,
     pub where_spec: Option<WhereSpec>,
     pub parser: GPPFieldParser,
+    pub write_with: Option<Ident>,
+    pub skip: bool,
+    pub segment_order: bool,
 }
 
 pub struct WhereSpec {
@@ -45,6 +118,9 @@ impl GPPFieldHelperAttribute {
             optional_segment_type: None,
             where_spec: None,
             parser: GPPFieldParser::FromDataReader,
+            write_with: None,
+            skip: false,
+            segment_order: false,
         };
         if let Some(attr) = find_gpp_attr(attrs) {
             attr.parse_nested_meta(|meta| {
@@ -64,6 +140,32 @@ impl GPPFieldHelperAttribute {
                     return Ok(());
                 }
 
+                // #[gpp(write_with = fn_name)]
+                // the counterpart of parse_with, used when writing
+                if meta.path.is_ident("write_with") {
+                    let value = meta.value()?; // parses the `=`
+                    let s = value.parse::<Ident>()?;
+                    gpp_attr.write_with = Some(s);
+                    return Ok(());
+                }
+
+                // #[gpp(skip)]
+                // the field is not part of the bit stream, it is initialized
+                // with its default value when reading and ignored when writing
+                if meta.path.is_ident("skip") {
+                    gpp_attr.skip = true;
+                    return Ok(());
+                }
+
+                // #[gpp(segment_order)]
+                // the field records the order of the optional segments when
+                // reading, which is followed when writing, it is otherwise skipped
+                if meta.path.is_ident("segment_order") {
+                    gpp_attr.segment_order = true;
+                    gpp_attr.skip = true;
+                    return Ok(());
+                }
+
                 // #[gpp(where(n = PARSER))]
                 // declares that the current field is preceded by a
                 // binding named "n" which is parsed using PARSER as
@@ -71,7 +173,7 @@ impl GPPFieldHelperAttribute {
                 if meta.path.is_ident("where") {
                     meta.parse_nested_meta(|where_meta| {
                         gpp_attr.where_spec = Self::parse_where_meta(where_meta)?;
-                        return Ok(());
+                        Ok(())
                     })?;
 
                     return Ok(());
@@ -79,7 +181,10 @@ impl GPPFieldHelperAttribute {
 
                 // #[gpp(PARSER)] where PARSER interpreted as a call like r.read_PARSER
                 // if no parenthesis, assume call without args
-                // if arg is a literal, use as-is
+                // if args are literals, use as-is, optionally named as in
+                // #[gpp(PARSER(x = 6, y = 2))], such as the width of an integer
+                // field overriding the default of its type in
+                // #[gpp(fixed_integer(bits = 12))]
                 // if arg is an ident, assume a 6 bit integer to be reused
                 // as many times as referenced
                 if let Some(ident) = meta.path.get_ident() {
@@ -124,9 +229,13 @@ impl GPPFieldHelperAttribute {
         if input.peek(token::Paren) {
             let content;
             parenthesized!(content in input);
-            let arg: Expr = content.parse()?;
+            let args = Punctuated::<Expr, Token![,]>::parse_terminated_with(
+                &content,
+                Self::parse_argument,
+            )?;
+            let args = args.into_iter().collect::<Vec<_>>();
             Ok(Some(GPPFieldParser::ReaderCall(
-                Self::create_read_function_call(ident, &[arg]),
+                Self::create_read_function_call(ident, &args),
             )))
         } else {
             Ok(Some(GPPFieldParser::ReaderCall(
@@ -135,6 +244,16 @@ impl GPPFieldHelperAttribute {
         }
     }
 
+    // arguments are either plain expressions, or named as in `name = expr`
+    // for readability, in which case the name is dropped
+    fn parse_argument(input: ParseStream) -> Result<Expr, syn::Error> {
+        if input.peek(syn::Ident) && input.peek2(Token![=]) {
+            input.parse::<Ident>()?;
+            input.parse::<Token![=]>()?;
+        }
+        input.parse()
+    }
+
     fn create_read_function_call(func_name: &Ident, args: &[Expr]) -> ExprCall {
         let name = format_ident!("read_{func_name}");
         let mut call = ExprCall {
@@ -0,0 +1,67 @@
+use crate::field_attr::GPPFieldHelperAttribute;
+use proc_macro2::Ident;
+use quote::quote;
+use syn::{DataEnum, DataStruct, Visibility};
+
+pub fn derive_struct_fields(input: &DataStruct, ident: &Ident) -> proc_macro2::TokenStream {
+    // generate Fields impl block
+    // # loop over all public named fields
+    // - prefix the field name with the parent path if any
+    // - let the field type push its own values
+    let mut push_statements = vec![];
+
+    for field in &input.fields {
+        let Some(name) = field.ident.clone() else {
+            // ignore nameless fields
+            continue;
+        };
+        // ignore non-public fields
+        if !matches!(field.vis, Visibility::Public(_)) {
+            continue;
+        }
+
+        // skipped fields are not part of the section
+        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+        if attr.skip {
+            continue;
+        }
+
+        let name_str = name.to_string();
+        push_statements.push(quote! {
+            crate::sections::Fields::push_fields(
+                &self.#name,
+                &crate::sections::field_path(prefix, #name_str),
+                out,
+            );
+        });
+    }
+
+    quote! {
+        impl crate::sections::Fields for #ident {
+            fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+                #(#push_statements)*
+            }
+        }
+    }
+}
+
+pub fn derive_enum_fields(input: &DataEnum, ident: &Ident) -> proc_macro2::TokenStream {
+    // generate Fields impl block
+    // variants wrap a single value whose fields are pushed as-is
+    let arms = input.variants.iter().map(|variant| {
+        let name = &variant.ident;
+        quote! {
+            Self::#name(v) => crate::sections::Fields::push_fields(v, prefix, out),
+        }
+    });
+
+    quote! {
+        impl crate::sections::Fields for #ident {
+            fn push_fields(&self, prefix: &str, out: &mut Vec<(String, String)>) {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
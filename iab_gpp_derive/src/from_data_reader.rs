@@ -20,7 +20,9 @@ pub fn derive_struct_from_data_reader(
 
     if let Some(version) = struct_attr.section_version {
         parse_statements.push(quote! {
+            r.enter_field("version");
             let version = r.read_fixed_integer(6)?;
+            r.exit_field();
             if version != #version {
                 return Err(crate::sections::SectionDecodeError::UnknownSegmentVersion {
                     segment_version: version,
@@ -33,25 +35,40 @@ pub fn derive_struct_from_data_reader(
         let name = field.ident.clone();
 
         // ignore nameless fields
-        if name.is_none() {
+        let Some(name) = name else {
+            continue;
+        };
+
+        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+
+        // skipped fields are not read, whatever their visibility
+        if attr.skip {
+            parse_statements.push(quote! {
+                let #name = ::std::default::Default::default();
+            });
+            field_names.push(name);
             continue;
         }
+
         // ignore non-public fields
         if !matches!(field.vis, Visibility::Public(_)) {
             continue;
         }
 
-        let name = name.unwrap();
         field_names.push(name.clone());
 
-        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
-
         // Handle where attribute
+        // the binding is recorded as a sibling of the field, so that the
+        // bits of the field itself follow it
+        let name_str = name.to_string();
         if let Some(where_spec) = attr.where_spec {
-            let name = where_spec.name;
+            let binding = where_spec.name;
+            let binding_str = format!("{name_str}.{binding}");
             let expr = where_spec.parser.to_token_stream();
             parse_statements.push(quote! {
-                let #name: u64 = #expr?;
+                r.enter_field(#binding_str);
+                let #binding: u64 = #expr?;
+                r.exit_field();
             })
         }
 
@@ -61,9 +78,14 @@ pub fn derive_struct_from_data_reader(
                 let #name = None;
             });
         } else {
+            if let Some(check) = attr.parser.width_check(&name, &field.ty) {
+                parse_statements.push(check);
+            }
             let expr = attr.parser.to_token_stream();
             parse_statements.push(quote! {
+                r.enter_field(#name_str);
                 let #name = #expr?;
+                r.exit_field();
             });
         }
     }
@@ -109,7 +131,9 @@ pub fn derive_enum_from_data_reader(input: &DataEnum, ident: &Ident) -> proc_mac
             type Err = crate::sections::SectionDecodeError;
 
             fn from_data_reader(r: &mut crate::core::DataReader) -> Result<Self, Self::Err> {
+                r.enter_field("version");
                 let version = r.read_fixed_integer(6)?;
+                r.exit_field();
                 match version {
                     #(#versions)*
                     v => Err(crate::sections::SectionDecodeError::UnknownSegmentVersion { segment_version: v }),
@@ -1,6 +1,11 @@
+use crate::fields::{derive_enum_fields, derive_struct_fields};
 use crate::from_data_reader::{derive_enum_from_data_reader, derive_struct_from_data_reader};
 use crate::optional_segment_parser::derive_optional_segment_parser;
+use crate::schema::{derive_enum_schema, derive_struct_schema};
 use crate::struct_attr::{GPPStructHelperAttribute, GPPStructKind};
+use crate::to_data_writer::{
+    derive_encodable_section, derive_enum_to_data_writer, derive_struct_to_data_writer,
+};
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use quote::{quote, TokenStreamExt};
@@ -8,9 +13,12 @@ use syn::{parse_macro_input, Attribute, Data, DataStruct, DeriveInput};
 
 mod enum_variant_attr;
 mod field_attr;
+mod fields;
 mod from_data_reader;
 mod optional_segment_parser;
+mod schema;
 mod struct_attr;
+mod to_data_writer;
 
 #[proc_macro_derive(FromDataReader, attributes(gpp))]
 pub fn derive_from_data_reader(input: TokenStream) -> TokenStream {
@@ -20,31 +28,84 @@ pub fn derive_from_data_reader(input: TokenStream) -> TokenStream {
         Data::Struct(s) => {
             let attr =
                 GPPStructHelperAttribute::new(&input.attrs).expect("attribute parsing failed");
-            derive_struct_from_data_reader(&s, &input.ident, &attr).into()
+            let mut stream = derive_struct_from_data_reader(&s, &input.ident, &attr);
+            stream.append_all(derive_struct_fields(&s, &input.ident));
+            stream.append_all(derive_struct_schema(&s, &input.ident, &attr));
+            stream.into()
         }
         Data::Enum(e) => {
             // we don't support enum-level attributes
-            derive_enum_from_data_reader(&e, &input.ident).into()
+            let mut stream = derive_enum_from_data_reader(&e, &input.ident);
+            stream.append_all(derive_enum_fields(&e, &input.ident));
+            stream.append_all(derive_enum_schema(&e, &input.ident));
+            stream.into()
         }
         _ => TokenStream::new(),
     }
 }
 
+#[proc_macro_derive(ToDataWriter, attributes(gpp))]
+pub fn derive_to_data_writer(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match input.data {
+        Data::Struct(s) => {
+            let attr =
+                GPPStructHelperAttribute::new(&input.attrs).expect("attribute parsing failed");
+            derive_struct_to_data_writer(&s, &input.ident, &attr).into()
+        }
+        Data::Enum(e) => derive_enum_to_data_writer(&e, &input.ident).into(),
+        _ => TokenStream::new(),
+    }
+}
+
 #[proc_macro_derive(GPPSection, attributes(gpp))]
 pub fn derive_gpp_section(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let ident = input.ident;
 
     if let Data::Struct(s) = input.data {
+        // section deriving depends on what kind of section we're dealing with
+        let attr = GPPStructHelperAttribute::new(&input.attrs).expect("attribute parsing failed");
+
         // first derive DecodableSection which applies to all sections
+        let parse_with_options = match attr.kind {
+            GPPStructKind::Base64Data => quote! {
+                use crate::sections::Base64EncodedStr;
+                s.parse_base64_str_with_options(buf, options)
+            },
+            GPPStructKind::WithOptionalSegments(_) => quote! {
+                use crate::sections::SegmentedStr;
+                s.parse_segmented_str_with_options(buf, options)
+            },
+        };
         let stream = quote! {
             impl crate::sections::DecodableSection for #ident {
                 const ID: crate::sections::SectionId = crate::sections::SectionId::#ident;
+
+                fn from_str_with_buffer(
+                    s: &str,
+                    buf: &mut Vec<u8>,
+                ) -> Result<Self, crate::sections::SectionDecodeError> {
+                    <Self as crate::sections::DecodeWithOptions>::decode_with_options(
+                        s,
+                        buf,
+                        crate::sections::DecodeOptions::default(),
+                    )
+                }
+            }
+
+            impl crate::sections::DecodeWithOptions for #ident {
+                fn decode_with_options(
+                    s: &str,
+                    buf: &mut Vec<u8>,
+                    options: crate::sections::DecodeOptions,
+                ) -> Result<Self, crate::sections::SectionDecodeError> {
+                    #parse_with_options
+                }
             }
         };
 
-        // section deriving depends on what kind of section we're dealing with
-        let attr = GPPStructHelperAttribute::new(&input.attrs).expect("attribute parsing failed");
         match attr.kind {
             GPPStructKind::Base64Data => {
                 // simple FromDataReader impl that read all fields in sequence
@@ -81,9 +142,19 @@ fn impl_base64_gpp_section(
                 s.parse_base64_str()
             }
         }
+
+        impl crate::sections::ExplainSection for #ident {
+            fn read_spans(s: &str) -> Result<Vec<crate::sections::FieldSpan>, crate::sections::SectionDecodeError> {
+                <str as crate::sections::Base64EncodedStr<Self>>::read_base64_spans(s)
+            }
+        }
     });
 
     stream.append_all(derive_struct_from_data_reader(&s, &ident, attr));
+    stream.append_all(derive_struct_fields(&s, &ident));
+    stream.append_all(derive_struct_schema(&s, &ident, attr));
+    stream.append_all(derive_struct_to_data_writer(&s, &ident, attr));
+    stream.append_all(derive_encodable_section(&s, &ident, attr));
 
     stream.into()
 }
@@ -104,9 +175,28 @@ fn impl_segmented_gpp_section(
                 s.parse_segmented_str()
             }
         }
+
+        impl crate::sections::SegmentedSection for #ident {
+            fn parse_core_only(
+                s: &str,
+            ) -> Result<(Self, ::std::collections::BTreeSet<u8>), crate::sections::SectionDecodeError> {
+                use crate::sections::SegmentedStr;
+                s.parse_core_segment()
+            }
+        }
+
+        impl crate::sections::ExplainSection for #ident {
+            fn read_spans(s: &str) -> Result<Vec<crate::sections::FieldSpan>, crate::sections::SectionDecodeError> {
+                <str as crate::sections::SegmentedStr<Self>>::read_segmented_spans(s)
+            }
+        }
     });
 
     stream.append_all(derive_struct_from_data_reader(&s, &ident, attr));
+    stream.append_all(derive_struct_fields(&s, &ident));
+    stream.append_all(derive_struct_schema(&s, &ident, attr));
+    stream.append_all(derive_struct_to_data_writer(&s, &ident, attr));
+    stream.append_all(derive_encodable_section(&s, &ident, attr));
 
     // OptionalSegmentParser impl
     stream.append_all(derive_optional_segment_parser(&s, &ident, attr));
@@ -10,6 +10,8 @@ pub fn derive_optional_segment_parser(
     struct_attr: &GPPStructHelperAttribute,
 ) -> proc_macro2::TokenStream {
     let mut parse_match_arms = vec![];
+    let mut segment_types = vec![];
+    let mut set_segment_order = None;
 
     for field in &input.fields {
         let name = field.ident.clone();
@@ -18,19 +20,57 @@ pub fn derive_optional_segment_parser(
         if name.is_none() {
             continue;
         }
+
+        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+
+        // the segment order field may be private
+        if attr.segment_order {
+            set_segment_order = Some(quote! {
+                fn set_segment_order(&mut self, order: &[u8]) {
+                    self.#name = crate::sections::SegmentOrder::from(order);
+                }
+            });
+            continue;
+        }
+
         // ignore non-public fields
         if !matches!(field.vis, Visibility::Public(_)) {
             continue;
         }
         let name = name.unwrap();
 
-        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
-
         if let Some(segment_type) = attr.optional_segment_type {
+            // the segment type must be representable with the declared bit width,
+            // otherwise it could never be read or written
+            if let GPPStructKind::WithOptionalSegments(bits) = struct_attr.kind {
+                if u32::from(segment_type) >= 1 << bits {
+                    let msg = format!(
+                        "optional segment type {segment_type} of field {name} does not fit in {bits} bits"
+                    );
+                    return quote! {
+                        compile_error!(#msg);
+                    };
+                }
+            }
+            if segment_types.contains(&segment_type) {
+                let msg =
+                    format!("optional segment type {segment_type} of field {name} is already used");
+                return quote! {
+                    compile_error!(#msg);
+                };
+            }
+            segment_types.push(segment_type);
+
+            // the segment type was read before the segment fields
+            let name_str = name.to_string();
+            let segment_type_str = format!("{name_str}.segment_type");
             let expr = attr.parser.to_token_stream();
             parse_match_arms.push(quote! {
                 #segment_type => {
+                    r.record_field(#segment_type_str, 0);
+                    r.enter_field(#name_str);
                     into.#name = Some(#expr?);
+                    r.exit_field();
                 }
             });
         }
@@ -48,6 +88,8 @@ pub fn derive_optional_segment_parser(
 
     quote! {
         impl crate::sections::OptionalSegmentParser for #ident {
+            const SEGMENT_TYPES: &'static [u8] = &[#(#segment_types),*];
+
             #read_segment_type_override
 
             fn parse_optional_segment(
@@ -63,6 +105,8 @@ pub fn derive_optional_segment_parser(
                 }
                 Ok(())
             }
+
+            #set_segment_order
         }
     }
 }
@@ -0,0 +1,218 @@
+use crate::enum_variant_attr::GPPEnumVariantHelperAttribute;
+use crate::field_attr::{GPPFieldHelperAttribute, GPPFieldParser};
+use crate::struct_attr::{GPPStructHelperAttribute, GPPStructKind};
+use proc_macro2::Ident;
+use quote::quote;
+use syn::{DataEnum, DataStruct, Expr, GenericArgument, Lit, PathArguments, Type, Visibility};
+
+pub fn derive_struct_schema(
+    input: &DataStruct,
+    ident: &Ident,
+    struct_attr: &GPPStructHelperAttribute,
+) -> proc_macro2::TokenStream {
+    // generate Schema impl block
+    // - describe the version first if needed
+    // # loop over all public named fields, in wire order
+    // - optional segments are preceded by their segment type
+    // - fields read with DataReader methods are described by the method call
+    // - other fields let their type describe itself
+    let mut push_statements = vec![];
+
+    if struct_attr.section_version.is_some() {
+        push_statements.push(push_entry(
+            quote! { &crate::sections::field_path(prefix, "version") },
+            "u8",
+            "fixed_integer(6)".to_string(),
+            Some(6),
+        ));
+    }
+
+    for field in &input.fields {
+        let Some(name) = field.ident.clone() else {
+            // ignore nameless fields
+            continue;
+        };
+        // ignore non-public fields
+        if !matches!(field.vis, Visibility::Public(_)) {
+            continue;
+        }
+
+        // skipped fields are not part of the bit stream
+        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+        if attr.skip {
+            continue;
+        }
+
+        let name_str = name.to_string();
+        push_statements.push(quote! {
+            let path = crate::sections::field_path(prefix, #name_str);
+        });
+
+        let mut ty = &field.ty;
+        if attr.optional_segment_type.is_some() {
+            if let GPPStructKind::WithOptionalSegments(bits) = struct_attr.kind {
+                push_statements.push(push_entry(
+                    quote! { &crate::sections::field_path(&path, "segment_type") },
+                    "u8",
+                    format!("fixed_integer({bits})"),
+                    Some(bits),
+                ));
+            }
+            ty = option_inner_type(ty).unwrap_or(ty);
+        }
+
+        if let Some(where_spec) = &attr.where_spec {
+            let (encoding, bits) = describe_parser(&where_spec.parser);
+            let binding = where_spec.name.to_string();
+            push_statements.push(push_entry(
+                quote! { &crate::sections::field_path(&path, #binding) },
+                "u64",
+                encoding,
+                bits,
+            ));
+        }
+
+        if let GPPFieldParser::FromDataReader = attr.parser {
+            push_statements.push(quote! {
+                <#ty as crate::sections::Schema>::push_schema(&path, out);
+            });
+        } else {
+            let (encoding, bits) = describe_parser(&attr.parser);
+            push_statements.push(push_entry(quote! { &path }, &type_name(ty), encoding, bits));
+        }
+    }
+
+    quote! {
+        impl crate::sections::Schema for #ident {
+            fn push_schema(prefix: &str, out: &mut Vec<crate::sections::FieldSchema>) {
+                #(#push_statements)*
+            }
+        }
+    }
+}
+
+pub fn derive_enum_schema(input: &DataEnum, ident: &Ident) -> proc_macro2::TokenStream {
+    // generate Schema impl block
+    // the version selects the variant, whose fields are listed under the
+    // name of each version
+    let mut push_statements = vec![push_entry(
+        quote! { &crate::sections::field_path(prefix, "version") },
+        "u8",
+        "fixed_integer(6)".to_string(),
+        Some(6),
+    )];
+
+    for variant in &input.variants {
+        let attr =
+            GPPEnumVariantHelperAttribute::new(&variant.attrs).expect("attribute parsing failed");
+        let (Some(version), Some(field)) = (attr.variant_version, variant.fields.iter().next())
+        else {
+            continue;
+        };
+
+        let ty = &field.ty;
+        let name = format!("v{version}");
+        push_statements.push(quote! {
+            <#ty as crate::sections::Schema>::push_schema(
+                &crate::sections::field_path(prefix, #name),
+                out,
+            );
+        });
+    }
+
+    quote! {
+        impl crate::sections::Schema for #ident {
+            fn push_schema(prefix: &str, out: &mut Vec<crate::sections::FieldSchema>) {
+                #(#push_statements)*
+            }
+        }
+    }
+}
+
+fn push_entry(
+    path: proc_macro2::TokenStream,
+    rust_type: &str,
+    encoding: String,
+    bits: Option<u32>,
+) -> proc_macro2::TokenStream {
+    let bits = match bits {
+        Some(b) => quote! { Some(#b) },
+        None => quote! { None },
+    };
+    quote! {
+        out.push(crate::sections::FieldSchema::new(#path, #rust_type, #encoding, #bits));
+    }
+}
+
+// the encoding is named after the reader method, without its read_ prefix,
+// and the width is only known for fixed-size encodings with literal arguments
+fn describe_parser(parser: &GPPFieldParser) -> (String, Option<u32>) {
+    match parser {
+        GPPFieldParser::FromDataReader => (String::new(), None),
+        GPPFieldParser::ReaderCall(c) => {
+            let Expr::Path(p) = c.func.as_ref() else {
+                return (String::new(), None);
+            };
+            let name = p
+                .path
+                .segments
+                .last()
+                .map(|s| s.ident.to_string())
+                .unwrap_or_default();
+            let name = name.strip_prefix("read_").unwrap_or(&name).to_string();
+
+            let args = c
+                .args
+                .iter()
+                .map(|a| quote!(#a).to_string())
+                .collect::<Vec<_>>();
+            let literal = c.args.first().and_then(|a| match a {
+                Expr::Lit(l) => match &l.lit {
+                    Lit::Int(i) => i.base10_parse::<u32>().ok(),
+                    _ => None,
+                },
+                _ => None,
+            });
+            let bits = match name.as_str() {
+                "bool" => Some(1),
+                "datetime_deciseconds" => Some(36),
+                "fixed_integer" | "signed_integer" | "fixed_bitfield" => literal,
+                "string" => literal.map(|n| n * 6),
+                "bytes" => literal.map(|n| n * 8),
+                _ => None,
+            };
+
+            if args.is_empty() {
+                (name, bits)
+            } else {
+                (format!("{name}({})", args.join(", ")), bits)
+            }
+        }
+        GPPFieldParser::Function(f) => {
+            let name = f.to_string();
+            let name = name.strip_prefix("parse_").unwrap_or(&name).to_string();
+            (name, None)
+        }
+    }
+}
+
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(p) = ty else {
+        return None;
+    };
+    let segment = p.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(t) => Some(t),
+        _ => None,
+    }
+}
+
+fn type_name(ty: &Type) -> String {
+    quote!(#ty).to_string().replace(' ', "")
+}
@@ -33,6 +33,11 @@ impl GPPStructHelperAttribute {
                                 let s = value.parse::<LitInt>()?;
                                 bits = s.base10_parse()?;
 
+                                // segment types are read into an u8
+                                if !(1..=8).contains(&bits) {
+                                    return Err(meta.error("bits must be between 1 and 8"));
+                                }
+
                                 return Ok(());
                             }
 
@@ -0,0 +1,226 @@
+use crate::enum_variant_attr::GPPEnumVariantHelperAttribute;
+use crate::field_attr::GPPFieldHelperAttribute;
+use crate::struct_attr::{GPPStructHelperAttribute, GPPStructKind};
+use proc_macro2::Ident;
+use quote::quote;
+use syn::{DataEnum, DataStruct, Visibility};
+
+pub fn derive_struct_to_data_writer(
+    input: &DataStruct,
+    ident: &Ident,
+    struct_attr: &GPPStructHelperAttribute,
+) -> proc_macro2::TokenStream {
+    // generate ToDataWriter impl block, mirroring FromDataReader
+    // - write version first if needed
+    // # loop over all fields
+    // - by default call a ToDataWriter implementation
+    // - use DataWriter methods if specified
+    // - skip optional segments, which are written separately
+    let mut write_statements = vec![];
+
+    if let Some(version) = struct_attr.section_version {
+        write_statements.push(quote! {
+            w.write_fixed_integer(6, #version)?;
+        });
+    }
+
+    for field in &input.fields {
+        let Some(name) = field.ident.clone() else {
+            // ignore nameless fields
+            continue;
+        };
+        // ignore non-public fields
+        if !matches!(field.vis, Visibility::Public(_)) {
+            continue;
+        }
+
+        let attr = GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+
+        // skipped fields are not part of the bit stream
+        if attr.skip {
+            continue;
+        }
+
+        // where bindings cannot be recovered from the decoded value
+        if attr.where_spec.is_some() {
+            let msg = format!("field {name} has a where binding and cannot be written");
+            return quote! {
+                compile_error!(#msg);
+            };
+        }
+
+        if attr.optional_segment_type.is_none() {
+            let expr = attr
+                .parser
+                .to_writer_token_stream(attr.write_with.as_ref(), quote! { &self.#name });
+            write_statements.push(quote! {
+                #expr?;
+            });
+        }
+    }
+
+    quote! {
+        impl crate::core::ToDataWriter for #ident {
+            fn to_data_writer(
+                &self,
+                w: &mut crate::core::DataWriter,
+            ) -> Result<(), crate::sections::SectionEncodeError> {
+                #(#write_statements)*
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn derive_enum_to_data_writer(input: &DataEnum, ident: &Ident) -> proc_macro2::TokenStream {
+    // generate ToDataWriter impl block
+    // # loop over all variants
+    // - write version attribute
+    // - call a ToDataWriter implementation
+    let mut arms = vec![];
+
+    for variant in &input.variants {
+        let name = variant.ident.clone();
+
+        let attr =
+            GPPEnumVariantHelperAttribute::new(&variant.attrs).expect("attribute parsing failed");
+
+        if let Some(v) = attr.variant_version {
+            arms.push(quote! {
+                Self::#name(v) => {
+                    w.write_fixed_integer(6, #v)?;
+                    w.write(v)
+                }
+            });
+        }
+    }
+
+    quote! {
+        impl crate::core::ToDataWriter for #ident {
+            fn to_data_writer(
+                &self,
+                w: &mut crate::core::DataWriter,
+            ) -> Result<(), crate::sections::SectionEncodeError> {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
+
+pub fn derive_encodable_section(
+    input: &DataStruct,
+    ident: &Ident,
+    struct_attr: &GPPStructHelperAttribute,
+) -> proc_macro2::TokenStream {
+    // generate EncodableSection impl block
+    // - the struct itself is written as the first segment
+    // - each optional segment that is present follows, prefixed with its type
+    // - segments follow the recorded order of the segment order field, if any
+    let mut segment_statements = vec![];
+    let mut count_statements = vec![];
+    let mut sort_statement = None;
+
+    if let GPPStructKind::WithOptionalSegments(bits) = struct_attr.kind {
+        for field in &input.fields {
+            let Some(name) = field.ident.clone() else {
+                // ignore nameless fields
+                continue;
+            };
+
+            let attr =
+                GPPFieldHelperAttribute::new(&field.attrs).expect("attribute parsing failed");
+
+            // the segment order field may be private
+            if attr.segment_order {
+                sort_statement = Some(quote! {
+                    self.#name.sort(&mut segments);
+                });
+                continue;
+            }
+
+            // ignore non-public fields
+            if !matches!(field.vis, Visibility::Public(_)) {
+                continue;
+            }
+
+            if let Some(segment_type) = attr.optional_segment_type {
+                let expr = attr
+                    .parser
+                    .to_writer_token_stream(attr.write_with.as_ref(), quote! { v });
+                segment_statements.push(quote! {
+                    if let Some(v) = &self.#name {
+                        let mut w = new_writer();
+                        w.write_fixed_integer(#bits, #segment_type)?;
+                        #expr?;
+                        segments.push((#segment_type, w));
+                    }
+                });
+                count_statements.push(quote! {
+                    if let Some(v) = &self.#name {
+                        w.write_fixed_integer(#bits, #segment_type)?;
+                        #expr?;
+                    }
+                });
+            }
+        }
+    }
+
+    let segments = if segment_statements.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            let mut segments: Vec<(u8, crate::core::DataWriter)> = Vec::new();
+            #(#segment_statements)*
+            #sort_statement
+        })
+    };
+    let binding = if segments.is_none() {
+        quote! { let s }
+    } else {
+        quote! { let mut s }
+    };
+    // compact writers choose the shortest encoding of sets of IDs, and do not pad
+    // segments to whole bytes
+    let encode_body = |new_writer: proc_macro2::TokenStream| {
+        let push_segments = segments.as_ref().map(|_| {
+            quote! {
+                for (_, w) in segments {
+                    s.push('.');
+                    s.push_str(&w.into_base64());
+                }
+            }
+        });
+        quote! {
+            let new_writer: fn() -> crate::core::DataWriter = #new_writer;
+            let mut w = new_writer();
+            w.write(self)?;
+            #segments
+            #binding = w.into_base64();
+            #push_segments
+            Ok(s)
+        }
+    };
+    let encode = encode_body(quote! { crate::core::DataWriter::new });
+    let encode_compact = encode_body(quote! { crate::core::DataWriter::compact });
+
+    quote! {
+        impl crate::sections::EncodableSection for #ident {
+            fn encode(&self) -> Result<String, crate::sections::SectionEncodeError> {
+                #encode
+            }
+
+            fn encode_compact(&self) -> Result<String, crate::sections::SectionEncodeError> {
+                #encode_compact
+            }
+
+            fn raw_len_bits(&self) -> Result<usize, crate::sections::SectionEncodeError> {
+                let mut w = crate::core::DataWriter::counting();
+                w.write(self)?;
+                #(#count_statements)*
+                Ok(w.position() as usize)
+            }
+        }
+    }
+}